@@ -29,6 +29,11 @@ pub enum ExitCode {
     /// This indicates normal termination of a program with an interior exit
     /// code returned from the guest program. A halted program cannot be
     /// resumed.
+    ///
+    /// A guest that panics halts with the exit code
+    /// [PANIC_EXIT_CODE](risc0_zkvm_platform::syscall::halt::PANIC_EXIT_CODE)
+    /// (101) and no output, rather than faulting. Such a session can be
+    /// proven, and its claim shows that the guest panicked.
     Halted(u32),
 
     /// This indicates the execution ended in a paused state with an interior
@@ -53,6 +58,20 @@ pub enum ExitCode {
     /// NOTE: The current version of the RISC Zero zkVM will never exit with an exit code of SessionLimit.
     /// This is because the system cannot currently prove that the session limit as been reached.
     SessionLimit,
+
+    /// This indicates that the guest ran out of the fuel allotted by the host.
+    ///
+    /// NOTE: Fuel is only metered by the executor, so the RISC Zero zkVM will
+    /// never prove a session with an exit code of OutOfFuel.
+    OutOfFuel,
+
+    /// This indicates that the guest consumed the whole fuel limit set by the
+    /// host. Unlike [ExitCode::OutOfFuel], the limit cannot be refilled by the
+    /// guest.
+    ///
+    /// NOTE: Fuel is only metered by the executor, so the RISC Zero zkVM will
+    /// never prove a session with an exit code of FuelExhausted.
+    FuelExhausted,
 }
 
 impl ExitCode {
@@ -62,6 +81,8 @@ impl ExitCode {
             ExitCode::Paused(user_exit) => (1, user_exit),
             ExitCode::SystemSplit => (2, 0),
             ExitCode::SessionLimit => (2, 2),
+            ExitCode::OutOfFuel => (2, 3),
+            ExitCode::FuelExhausted => (2, 4),
         }
     }
 
@@ -69,6 +90,10 @@ impl ExitCode {
         match sys_exit {
             0 => Ok(ExitCode::Halted(user_exit)),
             1 => Ok(ExitCode::Paused(user_exit)),
+            // OutOfFuel and FuelExhausted are never proven, but decode from
+            // their own pairs so that they round-trip through into_pair.
+            2 if user_exit == 3 => Ok(ExitCode::OutOfFuel),
+            2 if user_exit == 4 => Ok(ExitCode::FuelExhausted),
             2 => Ok(ExitCode::SystemSplit),
             _ => Err(InvalidExitCodeError(sys_exit, user_exit)),
         }
@@ -78,7 +103,10 @@ impl ExitCode {
     pub fn expects_output(&self) -> bool {
         match self {
             ExitCode::Halted(_) | ExitCode::Paused(_) => true,
-            ExitCode::SystemSplit | ExitCode::SessionLimit => false,
+            ExitCode::SystemSplit
+            | ExitCode::SessionLimit
+            | ExitCode::OutOfFuel
+            | ExitCode::FuelExhausted => false,
         }
     }
 
@@ -104,3 +132,29 @@ impl fmt::Display for InvalidExitCodeError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for InvalidExitCodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::ExitCode;
+
+    #[test]
+    fn out_of_fuel_pair() {
+        let pair = ExitCode::OutOfFuel.into_pair();
+        assert_eq!(pair, (2, 3));
+        assert_eq!(
+            ExitCode::from_pair(pair.0, pair.1).unwrap(),
+            ExitCode::OutOfFuel
+        );
+        assert_eq!(ExitCode::from_pair(2, 0).unwrap(), ExitCode::SystemSplit);
+    }
+
+    #[test]
+    fn fuel_exhausted_pair() {
+        let pair = ExitCode::FuelExhausted.into_pair();
+        assert_eq!(pair, (2, 4));
+        assert_eq!(
+            ExitCode::from_pair(pair.0, pair.1).unwrap(),
+            ExitCode::FuelExhausted
+        );
+    }
+}
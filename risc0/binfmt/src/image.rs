@@ -29,6 +29,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::{elf::Program, Digestible, SystemState};
 
+/// The contents of a [MemoryImage] snapshot file, along with its ImageID to
+/// detect a corrupt or mismatched file on load.
+#[cfg(feature = "snapshot")]
+#[derive(Serialize, Deserialize)]
+struct ImageArchive<T> {
+    image: T,
+    image_id: Digest,
+}
+
 /// An image of a zkVM guest's memory
 ///
 /// This is an image of the full memory state of the zkVM, including the data,
@@ -317,6 +326,42 @@ impl MemoryImage {
         compute_image_id(&self.compute_root_hash(), self.pc)
     }
 
+    /// Write this image to a snapshot file at `path`.
+    ///
+    /// The snapshot holds the pages, including the page table, and the
+    /// ImageID, so [MemoryImage::load] can skip loading the ELF binary. This
+    /// requires the `snapshot` feature.
+    #[cfg(feature = "snapshot")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        use std::io::Write as _;
+
+        let archive = ImageArchive {
+            image: self,
+            image_id: self.compute_id(),
+        };
+        let file = std::fs::File::create(path)?;
+        let mut out = std::io::BufWriter::new(file);
+        bincode::serialize_into(&mut out, &archive)?;
+        Ok(out.flush()?)
+    }
+
+    /// Read back an image written by [MemoryImage::save].
+    ///
+    /// Fails if the ImageID of the loaded image does not match the one
+    /// recorded in the snapshot.
+    #[cfg(feature = "snapshot")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let archive: ImageArchive<Self> = bincode::deserialize_from(std::io::BufReader::new(file))?;
+        let image_id = archive.image.compute_id();
+        ensure!(
+            image_id == archive.image_id,
+            "image snapshot is corrupt: ImageID {image_id} != {}",
+            archive.image_id
+        );
+        Ok(archive.image)
+    }
+
     /// Return the [SystemState] for this image.
     pub fn get_system_state(&self) -> SystemState {
         SystemState {
@@ -15,15 +15,34 @@
 #[cfg(test)]
 mod tests;
 
-use std::{array, cell::RefCell, collections::BTreeSet, mem, rc::Rc};
+use std::{
+    array,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, mem,
+    ops::Range,
+    rc::Rc,
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use anyhow::{bail, Result};
-use crypto_bigint::{CheckedMul as _, Encoding as _, NonZero, U256, U512};
+use anyhow::{anyhow, bail, ensure, Result};
+use crypto_bigint::{
+    CheckedMul as _, NonZero, Uint, U1024, U2048, U256, U384, U4096, U512, U768, U8192,
+};
 use risc0_binfmt::{ExitCode, MemoryImage, Program, SystemState};
+use risc0_core::field::baby_bear::BabyBearElem;
 use risc0_zkp::{
     core::{
         digest::{Digest, DIGEST_BYTES, DIGEST_WORDS},
-        hash::sha::{BLOCK_BYTES, BLOCK_WORDS},
+        hash::{
+            poseidon::unpadded_hash,
+            sha::{BLOCK_BYTES, BLOCK_WORDS},
+        },
         log2_ceil,
     },
     MAX_CYCLES_PO2, MIN_CYCLES_PO2, ZK_CYCLES,
@@ -33,22 +52,31 @@ use risc0_zkvm_platform::{
     memory::{is_guest_memory, GUEST_MAX_MEM},
     syscall::{
         bigint, ecall, halt,
-        reg_abi::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_MAX, REG_T0},
-        IO_CHUNK_WORDS,
+        nr::SYS_SPLIT,
+        poseidon, reduce,
+        reg_abi::{
+            REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_A7, REG_MAX, REG_RA, REG_T0,
+        },
+        sha512, sha_batch, IO_CHUNK_WORDS,
     },
     PAGE_SIZE, WORD_SIZE,
 };
+use serde::{Deserialize, Serialize};
 use sha2::digest::generic_array::GenericArray;
+use sha3::{Digest as _, Keccak256};
 
 use super::{
     addr::{ByteAddr, WordAddr},
     pager::PagedMemory,
-    rv32im::{DecodedInstruction, EmuContext, Emulator, Instruction, TrapCause},
-    BIGINT_CYCLES, SYSTEM_START,
+    rv32im::{
+        DecodedInstruction, EmuContext, Emulator, InsnKind, Instruction, MajorType, TrapCause,
+    },
+    BIGINT_ADD_CYCLES, BIGINT_CYCLES, BIGINT_SUB_CYCLES, SHA512_BLOCK_CYCLES, SHA_BLOCK_CYCLES,
+    SHA_INIT, SYSTEM_START,
 };
 use crate::{
     prove::{
-        emu::sha_cycles,
+        emu::{keccak_cycles, poseidon_cycles, reduce_cycles, sha_blocks},
         engine::loader::{FINI_CYCLES, INIT_CYCLES},
         segment::{Segment, SyscallRecord},
     },
@@ -57,6 +85,28 @@ use crate::{
 
 pub const DEFAULT_SEGMENT_LIMIT_PO2: usize = 20;
 
+// at least one HaltCycle needs to appear in the body
+const MIN_HALT_CYCLES: usize = 1;
+
+// a final "is_done" PageFault cycle is required when a split occurs
+const PAGE_FINI_CYCLES: usize = 1;
+
+/// The number of cycles of every segment that are reserved for the circuit,
+/// in addition to those spent on instructions and paging.
+pub const RESERVED_CYCLES: usize =
+    INIT_CYCLES + MIN_HALT_CYCLES + PAGE_FINI_CYCLES + FINI_CYCLES + ZK_CYCLES;
+
+/// The default maximum length, in bytes, of a string that the guest passes to
+/// the host, e.g. the name of a software syscall.
+pub const DEFAULT_MAX_STRING_LEN: usize = 1024;
+
+/// The number of steps between checks of the wall clock timeout and the
+/// cancel flag.
+const POLL_STEPS: usize = 1 << 16;
+
+/// The maximum number of cycles a single [SyscallContext::call_guest] may run.
+const GUEST_CALL_CYCLE_LIMIT: usize = 1 << DEFAULT_SEGMENT_LIMIT_PO2;
+
 /// A host-side implementation of a system call.
 pub trait Syscall {
     /// Invokes the system call.
@@ -91,6 +141,307 @@ pub trait SyscallContext {
     /// Returns the current cycle count.
     // TODO(breaking change): use `u64`
     fn get_cycle(&self) -> usize;
+
+    /// Returns the number of cycles left in the current segment before it is
+    /// split, or zero if the segment is already full.
+    fn get_segment_remaining(&self) -> usize;
+
+    /// Calls the guest function at `addr` and returns the value it leaves in
+    /// `a0`.
+    ///
+    /// Up to eight `args` are passed in `a0`-`a7`, and the function must return
+    /// through `ra`. The call is speculative: every register and memory write
+    /// it makes is rolled back once it returns and its cycles are not charged,
+    /// so the only observable effect is the returned value, which the calling
+    /// syscall then records as usual.
+    ///
+    /// This re-enters the executor while the calling syscall is still in
+    /// progress, so the callee may not issue any ecalls and calls cannot be
+    /// nested.
+    fn call_guest(&mut self, addr: ByteAddr, args: &[u32]) -> Result<u32>;
+}
+
+/// The failures of a run that a caller may want to handle.
+///
+/// These are returned wrapped in an [anyhow::Error] and can be recovered with
+/// [anyhow::Error::downcast_ref].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutorError {
+    /// The guest issued a software syscall that no handler is registered for.
+    UnknownSyscall(String),
+    /// The guest issued an ecall with an unknown selector in `t0`.
+    UnknownEcall(u32),
+    /// The guest halted with a halt type other than terminate or pause.
+    IllegalHaltType(u32),
+    /// The session ran for more cycles than its configured limit.
+    SessionLimitExceeded,
+    /// The session produced more segments than its configured limit.
+    TooManySegments,
+    /// A segment needed more cycles than the segment limit allows, e.g.
+    /// because a single instruction does not fit in a segment of `2^po2`
+    /// cycles.
+    SegmentOverflow {
+        /// The index of the segment within the session.
+        index: usize,
+        /// The number of cycles the segment needed.
+        cycles: usize,
+        /// The configured segment limit, as a power of two.
+        po2: usize,
+    },
+    /// The run took longer than its wall clock timeout. See
+    /// [Executor::wall_clock_timeout].
+    Timeout(Duration),
+    /// The run was stopped through its cancel flag. See
+    /// [Executor::cancel_flag].
+    Cancelled,
+}
+
+impl fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSyscall(name) => write!(f, "Unknown syscall: {name:?}"),
+            Self::UnknownEcall(ecall) => write!(f, "Unknown ecall {ecall:?}"),
+            Self::IllegalHaltType(halt_type) => write!(f, "Illegal halt type: {halt_type}"),
+            Self::SessionLimitExceeded => write!(f, "Session limit exceeded"),
+            Self::TooManySegments => write!(f, "Segment count limit exceeded"),
+            Self::SegmentOverflow { index, cycles, po2 } => write!(
+                f,
+                "Segment {index} needs {cycles} cycles, more than the segment limit of 2^{po2}"
+            ),
+            Self::Timeout(timeout) => write!(f, "Wall clock timeout exceeded: {timeout:?}"),
+            Self::Cancelled => write!(f, "Execution cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for ExecutorError {}
+
+/// The instruction that a failed run stopped at, and why. See
+/// [Executor::fault].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    /// The pc of the faulting instruction.
+    pub pc: u32,
+    /// Why the instruction faulted.
+    pub cause: FaultCause,
+}
+
+/// Why an instruction faulted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultCause {
+    /// The instruction could not be decoded.
+    DecodeError,
+    /// The guest issued an ecall with an unknown selector in `t0`.
+    UnknownEcall(u32),
+    /// The instruction accessed the given address outside of guest memory, or
+    /// without the required alignment.
+    MemoryError(u32),
+    /// The instruction failed for any other reason, e.g. an error returned by
+    /// a syscall handler.
+    StepError,
+}
+
+impl FaultCause {
+    fn from_error(err: &anyhow::Error) -> Self {
+        if let Some(trap) = err.downcast_ref::<Trap>() {
+            return match &trap.cause {
+                TrapCause::IllegalInstruction(_) => Self::DecodeError,
+                TrapCause::InstructionAddressMisaligned(addr)
+                | TrapCause::InstructionAccessFault(addr)
+                | TrapCause::LoadAddressMisaligned(addr)
+                | TrapCause::LoadAccessFault(addr)
+                | TrapCause::StoreAddressMisaligned(addr)
+                | TrapCause::StoreAccessFault(addr) => Self::MemoryError(addr.0),
+                TrapCause::Breakpoint | TrapCause::EnvironmentCallFromUserMode => Self::StepError,
+            };
+        }
+        match err.downcast_ref::<ExecutorError>() {
+            Some(ExecutorError::UnknownEcall(ecall)) => Self::UnknownEcall(*ecall),
+            _ => Self::StepError,
+        }
+    }
+}
+
+// The error returned by [EmuContext::trap], kept typed so that the fault can
+// be attributed to its cause.
+#[derive(Debug)]
+struct Trap {
+    cause: TrapCause,
+    pc: ByteAddr,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Trap: {:08x?}, pc: {:?}", self.cause, self.pc)
+    }
+}
+
+impl std::error::Error for Trap {}
+
+/// The number of cycles charged by the accelerated ecalls.
+///
+/// The default matches the current circuit. Any other model is only useful to
+/// estimate the cost of a program under an alternate circuit: the resulting
+/// segments do not match what the prover expects and cannot be proven.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleCostModel {
+    /// Cycles per block compressed by the SHA ecall.
+    pub sha_per_block: usize,
+    /// Cycles per block compressed by the SHA512 ecall.
+    pub sha512_per_block: usize,
+    /// Cycles per BigInt multiplication.
+    pub bigint_per_op: usize,
+    /// Cycles per BigInt addition.
+    pub bigint_add_per_op: usize,
+    /// Cycles per BigInt subtraction.
+    pub bigint_sub_per_op: usize,
+    /// Cycles charged for a software syscall in addition to the ecall itself
+    /// and one per chunk transferred into the guest.
+    pub syscall_overhead: usize,
+}
+
+impl Default for CycleCostModel {
+    fn default() -> Self {
+        Self {
+            sha_per_block: SHA_BLOCK_CYCLES,
+            sha512_per_block: SHA512_BLOCK_CYCLES,
+            bigint_per_op: BIGINT_CYCLES,
+            bigint_add_per_op: BIGINT_ADD_CYCLES,
+            bigint_sub_per_op: BIGINT_SUB_CYCLES,
+            syscall_overhead: 1,
+        }
+    }
+}
+
+/// The cycles the circuit spends loading a segment before its first
+/// instruction and finalizing it after its last, see [Executor::loader_cycles].
+///
+/// The default matches the loader of the current circuit. Like
+/// [CycleCostModel], any other value is only useful to estimate the segments of
+/// a program under an alternate runtime: they cannot be proven.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoaderCycles {
+    /// Cycles spent before the body of every segment.
+    pub init: usize,
+    /// Cycles spent after the body of every segment.
+    pub fini: usize,
+}
+
+impl Default for LoaderCycles {
+    fn default() -> Self {
+        Self {
+            init: INIT_CYCLES,
+            fini: FINI_CYCLES,
+        }
+    }
+}
+
+impl LoaderCycles {
+    /// The number of cycles of every segment reserved for the circuit with
+    /// this loader, [RESERVED_CYCLES] for the default one.
+    pub fn reserved_cycles(&self) -> usize {
+        self.init + MIN_HALT_CYCLES + PAGE_FINI_CYCLES + self.fini + ZK_CYCLES
+    }
+}
+
+/// What to do when a run reaches its session limit, see
+/// [Executor::session_limit_behavior].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SessionLimitBehavior {
+    /// Fail execution with [ExecutorError::SessionLimitExceeded].
+    #[default]
+    Error,
+    /// End execution with [ExitCode::SessionLimit], keeping the segments
+    /// produced so far.
+    Truncate,
+}
+
+/// A snapshot of the progress of a run, see [Executor::progress_callback].
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressInfo {
+    /// The number of user cycles executed so far in the session.
+    pub session_cycle: u64,
+    /// The number of segments that have been completed so far.
+    pub segments_completed: usize,
+    /// The program counter of the next instruction to execute.
+    pub pc: u32,
+}
+
+/// A segment split, see [Executor::split_callback].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitInfo {
+    /// The index of the segment that ended at the split.
+    pub index: usize,
+    /// The number of instructions executed in the session before the split,
+    /// i.e. the index of the first instruction of the next segment.
+    pub split_insn: u64,
+    /// The number of user cycles executed in the session at the split.
+    pub session_cycle: u64,
+}
+
+/// An ecall handled by the executor, see [Executor::ecall_callback].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcallInfo {
+    /// The ecall id, taken from `t0`, e.g. [ecall::SOFTWARE].
+    pub ecall: u32,
+    /// The name of the syscall, for software ecalls.
+    pub syscall: Option<String>,
+    /// The host time spent handling the ecall, including any syscall handler.
+    pub duration: Duration,
+}
+
+/// The paging activity of a run, accumulated over all of its segments.
+///
+/// Pages are loaded at most once per segment, and dirty pages are written
+/// back at the end of it, so a page used by several segments is counted once
+/// for each of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PagingStats {
+    /// The number of pages read in, including the pages of the merkle tree.
+    pub page_ins: u64,
+    /// The number of pages written back, including the pages of the merkle
+    /// tree.
+    pub page_outs: u64,
+    /// The cycles spent reading pages in.
+    pub page_read_cycles: u64,
+    /// The cycles spent writing pages back.
+    pub page_write_cycles: u64,
+}
+
+/// Why a run that completed cannot be proven, see
+/// [ExecutorResult::unprovable].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Unprovable {
+    /// The host paused execution, on a syscall rate limit, a breakpoint, a
+    /// memory breakpoint, or a watchpoint. The circuit only accepts a pause
+    /// requested by the guest with the HALT ecall.
+    HostPause,
+    /// Ecalls were charged with a [CycleCostModel] other than the default.
+    CycleCostModel,
+    /// Instructions were charged custom costs, see
+    /// [Executor::insn_cycle_costs].
+    InsnCycleCosts,
+    /// Segments were charged a [LoaderCycles] other than the default.
+    LoaderCycles,
+    /// Segments reserved a number of cycles other than [RESERVED_CYCLES] for
+    /// the circuit.
+    ReservedCycles,
+    /// The memory image is paged by a size other than [PAGE_SIZE], which the
+    /// circuit pages by.
+    PageSize,
+}
+
+impl fmt::Display for Unprovable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::HostPause => write!(f, "execution was paused by the host"),
+            Self::CycleCostModel => write!(f, "a custom cycle cost model was used"),
+            Self::InsnCycleCosts => write!(f, "custom instruction cycle costs were used"),
+            Self::LoaderCycles => write!(f, "custom loader cycles were used"),
+            Self::ReservedCycles => write!(f, "a custom number of reserved cycles was used"),
+            Self::PageSize => write!(f, "a custom page size was used"),
+        }
+    }
 }
 
 pub struct ExecutorResult {
@@ -102,6 +453,36 @@ pub struct ExecutorResult {
     pub pre_state: SystemState,
     pub post_state: SystemState,
     pub output_digest: Option<Digest>,
+    /// The number of instructions executed, by kind.
+    pub opcode_counts: BTreeMap<InsnKind, u64>,
+    /// The pc of every instruction executed at least once, if coverage was
+    /// tracked. See [Executor::track_coverage].
+    pub covered_pcs: BTreeSet<u32>,
+    /// The pc of every instruction fetched from a word written earlier in the
+    /// run, in the order they were first executed, if detection was enabled.
+    /// See [Executor::detect_self_modifying_code].
+    pub self_modified_pcs: Vec<u32>,
+    /// The number of instructions executed over all segments. An instruction
+    /// that is replayed at the start of a segment after a split is only
+    /// counted once.
+    pub total_insns: u64,
+    /// The `(addr, value)` of the memory breakpoint that paused execution, if
+    /// any. See [Executor::pause_on_memory_value].
+    pub memory_breakpoint: Option<(u32, u32)>,
+    /// The address of the word whose write triggered a watchpoint and paused
+    /// execution, if any. See [Executor::add_watchpoint].
+    pub watchpoint: Option<u32>,
+    /// The pc of the breakpoint that paused execution, if any. See
+    /// [Executor::add_breakpoint].
+    pub breakpoint: Option<u32>,
+    /// The paging activity over all segments.
+    pub paging_stats: PagingStats,
+    /// The executor-only ecalls the guest made, which prevent the session from
+    /// being proven. See [ecall::is_executor_only].
+    pub executor_only_ecalls: BTreeSet<u32>,
+    /// The reasons, other than executor-only ecalls, that the run cannot be
+    /// proven.
+    pub unprovable: BTreeSet<Unprovable>,
 }
 
 #[derive(Default)]
@@ -110,6 +491,37 @@ struct SessionCycles {
     total: usize,
 }
 
+/// The result of [Executor::dry_run].
+pub struct DryRunResult {
+    pub segments: usize,
+    pub exit_code: ExitCode,
+    pub user_cycles: u64,
+    pub total_cycles: u64,
+    /// The po2 of each segment, in order.
+    pub segment_po2s: Vec<usize>,
+}
+
+/// What a single instruction changed, see [Executor::step_detailed].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepDelta {
+    /// The pc of the instruction.
+    pub pc: u32,
+    /// The pc of the next instruction to execute.
+    pub next_pc: u32,
+    /// The kind of the instruction.
+    pub kind: InsnKind,
+    /// The encoded instruction.
+    pub insn: u32,
+    /// The `(idx, value)` of each register written, ordered by register.
+    pub register_writes: Vec<(usize, u32)>,
+    /// The [TraceEvent::MemorySet] events for each memory write, ordered by
+    /// address.
+    pub memory_writes: Vec<TraceEvent>,
+    /// The exit code the instruction ended execution with, if any, e.g. for
+    /// an ecall that halts.
+    pub exit_code: Option<ExitCode>,
+}
+
 pub struct SimpleSession {
     pub segments: Vec<Segment>,
     pub result: ExecutorResult,
@@ -119,11 +531,17 @@ pub struct SimpleSession {
 struct PendingState {
     pc: ByteAddr,
     insn: u32,
+    kind: Option<InsnKind>,
     cycles: usize,
     syscall: Option<SyscallRecord>,
     output_digest: Option<Digest>,
     exit_code: Option<ExitCode>,
     events: BTreeSet<TraceEvent>,
+    fuel: u64,
+    breakpoint: Option<(u32, u32)>,
+    watchpoint: Option<u32>,
+    split: bool,
+    ecall: Option<EcallInfo>,
 }
 
 pub struct Executor<'a, 'b, S: Syscall> {
@@ -136,16 +554,92 @@ pub struct Executor<'a, 'b, S: Syscall> {
     output_digest: Option<Digest>,
     pending: PendingState,
     trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
+    // Set while stepping, to record the trace events of the instruction even
+    // without any trace callbacks.
+    record_events: bool,
     cycles: SessionCycles,
+    paging_stats: PagingStats,
+    executor_only_ecalls: BTreeSet<u32>,
+    unprovable: BTreeSet<Unprovable>,
+    in_guest_call: bool,
+    readonly_regions: Vec<Range<u32>>,
+    syscall_rate_limits: HashMap<String, u64>,
+    syscall_counts: HashMap<String, u64>,
+    opcode_counts: HashMap<InsnKind, u64>,
+    track_coverage: bool,
+    covered_pcs: BTreeSet<u32>,
+    detect_self_modifying_code: bool,
+    written_words: HashSet<u32>,
+    self_modified_pcs: Vec<u32>,
+    initial_fuel: Option<u64>,
+    fuel_costs: HashMap<MajorType, u64>,
+    fuel: u64,
+    fuel_limit: Option<u64>,
+    fuel_used: u64,
+    memory_breakpoints: Vec<(u32, u32)>,
+    memory_breakpoint: Option<(u32, u32)>,
+    watchpoints: BTreeSet<u32>,
+    watchpoint: Option<u32>,
+    breakpoints: HashSet<u32>,
+    breakpoint: Option<u32>,
+    fault: Option<Fault>,
+    syscall_replay: bool,
+    cost_model: CycleCostModel,
+    // The cycles available to each segment of the current run, excluding the
+    // reserved cycles.
+    segment_limit: usize,
+    insn_costs: HashMap<MajorType, u32>,
+    session_limit_behavior: SessionLimitBehavior,
+    expected_output_digest: Option<Digest>,
+    wall_clock_timeout: Option<Duration>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    progress_callback: Option<Box<dyn FnMut(ProgressInfo) + 'b>>,
+    split_callback: Option<Box<dyn FnMut(SplitInfo) + 'b>>,
+    ecall_callback: Option<Box<dyn FnMut(EcallInfo) + 'b>>,
+    progress_interval: Option<usize>,
+    split_every_insns: Option<usize>,
+    max_segments: Option<usize>,
+    loader_cycles: LoaderCycles,
+    reserved_cycles: usize,
+    max_string_len: usize,
+    segment_insns: usize,
+    total_insns: u64,
+    split_requested: bool,
+    // Kept across runs so that its decode cache stays warm.
+    emu: Option<Emulator>,
 }
 
 impl PendingState {
+    fn new(pc: ByteAddr) -> Self {
+        Self {
+            pc,
+            insn: 0,
+            kind: None,
+            cycles: 0,
+            syscall: None,
+            output_digest: None,
+            exit_code: None,
+            events: BTreeSet::new(),
+            fuel: 0,
+            breakpoint: None,
+            watchpoint: None,
+            split: false,
+            ecall: None,
+        }
+    }
+
     fn reset(&mut self, pc: ByteAddr) {
         self.pc = pc;
+        self.kind = None;
         self.cycles = 0;
         self.syscall = None;
         self.output_digest = None;
         self.exit_code = None;
+        self.fuel = 0;
+        self.breakpoint = None;
+        self.watchpoint = None;
+        self.split = false;
+        self.ecall = None;
     }
 }
 
@@ -164,18 +658,525 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             syscalls: Vec::new(),
             syscall_handler,
             output_digest: None,
-            pending: PendingState {
-                pc,
-                insn: 0,
-                cycles: 0,
-                syscall: None,
-                output_digest: None,
-                exit_code: None,
-                events: BTreeSet::new(),
-            },
+            pending: PendingState::new(pc),
             trace,
+            record_events: false,
             cycles: SessionCycles::default(),
+            paging_stats: PagingStats::default(),
+            executor_only_ecalls: BTreeSet::new(),
+            unprovable: BTreeSet::new(),
+            in_guest_call: false,
+            readonly_regions: Vec::new(),
+            syscall_rate_limits: HashMap::new(),
+            syscall_counts: HashMap::new(),
+            opcode_counts: HashMap::new(),
+            track_coverage: false,
+            covered_pcs: BTreeSet::new(),
+            detect_self_modifying_code: false,
+            written_words: HashSet::new(),
+            self_modified_pcs: Vec::new(),
+            initial_fuel: None,
+            fuel_costs: HashMap::new(),
+            fuel: 0,
+            fuel_limit: None,
+            fuel_used: 0,
+            memory_breakpoints: Vec::new(),
+            memory_breakpoint: None,
+            watchpoints: BTreeSet::new(),
+            watchpoint: None,
+            breakpoints: HashSet::new(),
+            breakpoint: None,
+            fault: None,
+            syscall_replay: true,
+            cost_model: CycleCostModel::default(),
+            segment_limit: 0,
+            insn_costs: HashMap::new(),
+            session_limit_behavior: SessionLimitBehavior::default(),
+            expected_output_digest: None,
+            wall_clock_timeout: None,
+            cancel_flag: None,
+            progress_callback: None,
+            split_callback: None,
+            ecall_callback: None,
+            progress_interval: None,
+            split_every_insns: None,
+            max_segments: None,
+            loader_cycles: LoaderCycles::default(),
+            reserved_cycles: RESERVED_CYCLES,
+            max_string_len: DEFAULT_MAX_STRING_LEN,
+            segment_insns: 0,
+            total_insns: 0,
+            split_requested: false,
+            emu: None,
+        }
+    }
+
+    /// Mark the given range of guest memory as read-only.
+    ///
+    /// Any store that overlaps a read-only region, whether from an instruction
+    /// or an ecall, causes execution to fail.
+    pub fn readonly_region(&mut self, region: Range<u32>) -> &mut Self {
+        self.readonly_regions.push(region);
+        self
+    }
+
+    /// Pause execution the first time the guest stores `value` to the word
+    /// containing `addr`.
+    ///
+    /// The store, whether from an instruction or an ecall, completes before
+    /// execution pauses with [ExitCode::Paused] at the following instruction,
+    /// so that the state can be inspected with e.g. [Self::read_memory] and
+    /// [Self::get_register]. The breakpoint that caused the pause is reported
+    /// in [ExecutorResult::memory_breakpoint] and does not fire again. Like
+    /// any pause initiated by the host, it reports [Unprovable::HostPause].
+    pub fn pause_on_memory_value(&mut self, addr: u32, value: u32) -> &mut Self {
+        self.memory_breakpoints.push((addr, value));
+        self
+    }
+
+    /// Pause execution every time the guest stores to the word containing
+    /// `addr`, whatever the value written.
+    ///
+    /// As with [Self::pause_on_memory_value], the store completes before
+    /// execution pauses with [ExitCode::Paused] at the following instruction,
+    /// so the new value can be read with [Self::read_memory]. The address of
+    /// the word written is reported in [ExecutorResult::watchpoint]. Unlike a
+    /// memory breakpoint, a watchpoint stays installed and fires again on the
+    /// next write once execution is resumed. Each pause reports
+    /// [Unprovable::HostPause].
+    pub fn add_watchpoint(&mut self, addr: u32) -> &mut Self {
+        self.watchpoints.insert(ByteAddr(addr).waddr().baddr().0);
+        self
+    }
+
+    /// Pause execution whenever the guest reaches `pc`, before the instruction
+    /// there is executed.
+    ///
+    /// Execution pauses with [ExitCode::Paused] and the breakpoint is reported
+    /// in [ExecutorResult::breakpoint]. Running again from there executes the
+    /// instruction at `pc` instead of pausing on it a second time, so a
+    /// breakpoint fires once each time the guest arrives at it. Each pause
+    /// reports [Unprovable::HostPause].
+    pub fn add_breakpoint(&mut self, pc: u32) -> &mut Self {
+        self.breakpoints.insert(pc);
+        self
+    }
+
+    /// Remove a breakpoint installed with [Self::add_breakpoint].
+    pub fn remove_breakpoint(&mut self, pc: u32) -> &mut Self {
+        self.breakpoints.remove(&pc);
+        self
+    }
+
+    /// Control whether a syscall that is re-executed after a segment split
+    /// replays the recorded result of its first invocation.
+    ///
+    /// Replay is enabled by default. Disabling it invokes the handler afresh
+    /// every time, which is useful to observe raw handler behavior or to
+    /// stress-test that a handler is deterministic. This is a debugging aid
+    /// only: a handler that returns a different result when re-invoked yields
+    /// segments that are inconsistent with each other and cannot be proven.
+    pub fn syscall_replay(&mut self, enabled: bool) -> &mut Self {
+        self.syscall_replay = enabled;
+        self
+    }
+
+    /// Charge cycles for ecalls according to `model` instead of the default
+    /// [CycleCostModel].
+    ///
+    /// A run with any other model reports [Unprovable::CycleCostModel].
+    pub fn cycle_cost_model(&mut self, model: CycleCostModel) -> &mut Self {
+        self.cost_model = model;
+        self
+    }
+
+    /// Charge the given number of cycles for each instruction of a
+    /// [MajorType] instead of its cost in the circuit. Types missing from
+    /// `costs` keep their default cost. The resulting segments cannot be
+    /// proven, and a run with any costs reports [Unprovable::InsnCycleCosts].
+    pub fn insn_cycle_costs(&mut self, costs: HashMap<MajorType, u32>) -> &mut Self {
+        self.insn_costs = costs;
+        self
+    }
+
+    /// Choose whether reaching the session limit passed to [Self::run] is an
+    /// error, or ends the run with [ExitCode::SessionLimit].
+    pub fn session_limit_behavior(&mut self, behavior: SessionLimitBehavior) -> &mut Self {
+        self.session_limit_behavior = behavior;
+        self
+    }
+
+    /// Fail execution if the guest halts with an output digest other than
+    /// `digest`.
+    ///
+    /// Only [ExitCode::Halted] is checked, since the output of a paused guest
+    /// is not final.
+    pub fn expect_output_digest(&mut self, digest: Digest) -> &mut Self {
+        self.expected_output_digest = Some(digest);
+        self
+    }
+
+    /// Fail a run that takes longer than `timeout` of wall clock time.
+    ///
+    /// The clock is only sampled every so many steps, so a run may overshoot
+    /// the timeout slightly before failing with [ExecutorError::Timeout].
+    pub fn wall_clock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.wall_clock_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail a run with [ExecutorError::Cancelled] once `flag` is set, e.g. from
+    /// another thread.
+    ///
+    /// Like the [wall clock timeout](Self::wall_clock_timeout), the flag is
+    /// only polled every so many steps.
+    pub fn cancel_flag(&mut self, flag: Arc<AtomicBool>) -> &mut Self {
+        self.cancel_flag = Some(flag);
+        self
+    }
+
+    /// Call `callback` with the progress of a run each time a segment is
+    /// completed and, if `interval` is set, every `interval` steps.
+    pub fn progress_callback(
+        &mut self,
+        interval: Option<usize>,
+        callback: impl FnMut(ProgressInfo) + 'b,
+    ) -> &mut Self {
+        self.progress_callback = Some(Box::new(callback));
+        self.progress_interval = interval.filter(|&interval| interval > 0);
+        self
+    }
+
+    /// Record the pc of every instruction that is executed, to be returned in
+    /// [ExecutorResult::covered_pcs].
+    pub fn track_coverage(&mut self, enable: bool) -> &mut Self {
+        self.track_coverage = enable;
+        self
+    }
+
+    /// Report every instruction that is fetched from a word written earlier in
+    /// the run, in [ExecutorResult::self_modified_pcs].
+    ///
+    /// Writes are taken from the [TraceEvent::MemorySet] events, including
+    /// those of syscalls that write to guest memory, so this records the
+    /// events of every instruction and slows down execution.
+    pub fn detect_self_modifying_code(&mut self, enable: bool) -> &mut Self {
+        self.detect_self_modifying_code = enable;
+        self
+    }
+
+    /// Call `callback` each time a segment is split, i.e. whenever a segment
+    /// ends without the guest having halted or paused.
+    pub fn split_callback(&mut self, callback: impl FnMut(SplitInfo) + 'b) -> &mut Self {
+        self.split_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` after each ecall, with the host time spent on it.
+    ///
+    /// This tells the latency of the host, e.g. of a slow syscall handler,
+    /// apart from the cycles charged to the guest for the ecall. Each ecall is
+    /// reported once, when it completes, even if it is replayed in a new
+    /// segment after a split.
+    pub fn ecall_callback(&mut self, callback: impl FnMut(EcallInfo) + 'b) -> &mut Self {
+        self.ecall_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Split a segment once it has executed `insns` instructions, even if it
+    /// has not reached the cycle limit.
+    ///
+    /// The cycle limit still applies, so a segment ends at whichever limit is
+    /// reached first. This makes the segment boundaries independent of the
+    /// cost of each instruction, e.g. for reproducible benchmarks. A limit of
+    /// zero is ignored.
+    pub fn split_every_insns(&mut self, insns: u32) -> &mut Self {
+        self.split_every_insns = Some(insns as usize).filter(|&insns| insns > 0);
+        self
+    }
+
+    /// Limit the number of segments per run to `max_segments`.
+    ///
+    /// Once that many segments have ended, a further split fails execution with
+    /// [ExecutorError::TooManySegments] rather than starting another segment.
+    pub fn max_segments(&mut self, max_segments: u32) -> &mut Self {
+        self.max_segments = Some(max_segments as usize);
+        self
+    }
+
+    /// Reserve `cycles` of every segment for the circuit instead of
+    /// [RESERVED_CYCLES].
+    ///
+    /// Like [Self::cycle_cost_model], this is only useful to estimate the
+    /// segment count of a program under an alternate circuit: the resulting
+    /// segments cannot be proven, and the run reports
+    /// [Unprovable::ReservedCycles].
+    pub fn reserved_cycles(&mut self, cycles: usize) -> &mut Self {
+        self.reserved_cycles = cycles;
+        self
+    }
+
+    /// Model a loader that spends `loader` cycles initializing and finalizing
+    /// every segment, e.g. that of an alternate guest runtime.
+    ///
+    /// This replaces the cycles reserved for the circuit with
+    /// [LoaderCycles::reserved_cycles], unless [Self::reserved_cycles] is set
+    /// afterwards, and is recorded in every [Segment]. Like
+    /// [Self::reserved_cycles], the resulting segments cannot be proven: a run
+    /// with any other loader reports [Unprovable::LoaderCycles].
+    pub fn loader_cycles(&mut self, loader: LoaderCycles) -> &mut Self {
+        self.loader_cycles = loader;
+        self.reserved_cycles = loader.reserved_cycles();
+        self
+    }
+
+    /// Fail execution when the guest passes the host a string longer than
+    /// `len` bytes, instead of [DEFAULT_MAX_STRING_LEN].
+    ///
+    /// This bounds how far the executor scans for the terminating nul of a
+    /// string, e.g. a syscall name, that the guest failed to terminate.
+    pub fn max_string_len(&mut self, len: usize) -> &mut Self {
+        self.max_string_len = len;
+        self
+    }
+
+    /// Limit the number of times the named syscall may be invoked per run.
+    ///
+    /// Once `max_per_run` calls have been made, the next call pauses execution
+    /// with [ExitCode::Paused] at the ecall instead of invoking the handler, so
+    /// that the call is reissued when execution resumes. The count is reset at
+    /// the start of every run. Only fresh calls are counted; a syscall that is
+    /// replayed after a segment split is not counted again.
+    ///
+    /// A run paused this way reports [Unprovable::HostPause].
+    pub fn syscall_rate_limit(&mut self, name: &str, max_per_run: u64) -> &mut Self {
+        self.syscall_rate_limits
+            .insert(name.to_string(), max_per_run);
+        self
+    }
+
+    /// Meter execution with `fuel` units of fuel.
+    ///
+    /// Every instruction consumes one unit, and execution ends with
+    /// [ExitCode::OutOfFuel] once the fuel is exhausted. The guest can refill
+    /// its fuel with the `ADD_FUEL` ecall, but never beyond `fuel`. The fuel is
+    /// restored at the start of every run.
+    pub fn initial_fuel(&mut self, fuel: u64) -> &mut Self {
+        self.initial_fuel = Some(fuel);
+        self
+    }
+
+    /// Stop execution with [ExitCode::FuelExhausted] once the instructions of a
+    /// run have consumed `limit` units of fuel.
+    ///
+    /// Unlike [Self::initial_fuel], this is a fixed budget that the guest
+    /// cannot refill, and it is independent of the cycles charged and of
+    /// segment splitting. Both can be used at once, in which case execution
+    /// stops at whichever runs out first.
+    pub fn fuel_limit(&mut self, limit: u64) -> &mut Self {
+        self.fuel_limit = Some(limit);
+        self
+    }
+
+    /// Charge the given amount of fuel for each instruction of a [MajorType],
+    /// instead of one unit. Types missing from `costs` still consume one unit.
+    ///
+    /// This only affects metering with [Self::initial_fuel] and
+    /// [Self::fuel_limit], not the cycles charged for the instructions.
+    pub fn fuel_costs(&mut self, costs: HashMap<MajorType, u64>) -> &mut Self {
+        self.fuel_costs = costs;
+        self
+    }
+
+    /// Returns the address of the next instruction to execute.
+    pub fn pc(&self) -> u32 {
+        self.pc.0
+    }
+
+    /// Returns the instruction that the last run faulted at, if it failed
+    /// while executing one.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// Returns the value of the register at `idx`.
+    ///
+    /// This is intended for debuggers that inspect the guest between runs,
+    /// e.g. after it has paused.
+    pub fn get_register(&self, idx: usize) -> Result<u32> {
+        if idx >= REG_MAX {
+            bail!("invalid register: x{idx}");
         }
+        self.pager.peek(SYSTEM_START + idx)
+    }
+
+    /// Sets the value of the register at `idx`.
+    ///
+    /// The new value takes effect the next time [Self::run] is called. Since
+    /// this changes the guest state outside of execution, the resulting
+    /// session cannot be proven as a continuation of the previous one.
+    pub fn set_register(&mut self, idx: usize, value: u32) -> Result<()> {
+        if idx >= REG_MAX {
+            bail!("invalid register: x{idx}");
+        }
+        if idx == 0 {
+            bail!("register x0 is hardwired to zero");
+        }
+        self.pager.poke(SYSTEM_START + idx, value);
+        Ok(())
+    }
+
+    /// Returns `len` bytes of guest memory starting at `addr`.
+    ///
+    /// Like [Self::get_register], this is intended for debuggers that inspect
+    /// the guest between runs. Any address outside of guest memory results in
+    /// an error.
+    pub fn read_memory(&self, addr: u32, len: u32) -> Result<Vec<u8>> {
+        let range = Self::check_guest_range(addr, len)?;
+        let mut bytes = Vec::with_capacity(len as usize);
+        for addr in range {
+            let addr = ByteAddr(addr);
+            let word = self.pager.peek(addr.waddr())?;
+            bytes.push(word.to_le_bytes()[addr.0 as usize % WORD_SIZE]);
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes up to `count` instructions starting at `start_pc`, returning the
+    /// address and kind of each.
+    ///
+    /// This stops early at the first word that is not a valid instruction or
+    /// lies outside guest memory, and returns nothing if `start_pc` is not word
+    /// aligned. Like [Self::read_memory], it has no effect on the state of the
+    /// guest.
+    pub fn disassemble(&self, start_pc: u32, count: usize) -> Vec<(u32, InsnKind)> {
+        let fresh;
+        let emu = match &self.emu {
+            Some(emu) => emu,
+            None => {
+                fresh = Emulator::new();
+                &fresh
+            }
+        };
+        let mut insns = Vec::new();
+        let mut pc = ByteAddr(start_pc);
+        while insns.len() < count && pc.is_aligned() {
+            let Some(kind) = Self::check_guest_addr(pc)
+                .and_then(|addr| self.pager.peek(addr.waddr()))
+                .ok()
+                .and_then(|word| emu.decode_kind(word))
+            else {
+                break;
+            };
+            insns.push((pc.0, kind));
+            pc += WORD_SIZE;
+        }
+        insns
+    }
+
+    /// Returns a copy of the memory image as it currently stands, including
+    /// any writes of the segment in progress.
+    ///
+    /// Snapshots taken at different points, e.g. between runs, can be
+    /// compared with [diff_images].
+    pub fn memory_snapshot(&self) -> MemoryImage {
+        self.pager.snapshot(self.pc)
+    }
+
+    /// Writes `data` into guest memory starting at `addr`.
+    ///
+    /// As with [Self::set_register], the new contents take effect the next
+    /// time [Self::run] is called and cannot be proven as a continuation of
+    /// the previous session. Writes made this way bypass the
+    /// [read-only regions](Self::readonly_region), which only apply to the
+    /// guest, so that a debugger can patch e.g. the program text.
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        let range = Self::check_guest_range(addr, data.len().try_into()?)?;
+        let mut words = Vec::with_capacity(data.len() / WORD_SIZE + 2);
+        let mut idx = 0;
+        while idx < data.len() {
+            let addr = ByteAddr(range.start + idx as u32);
+            let waddr = addr.waddr();
+            let mut word = self.pager.peek(waddr)?.to_le_bytes();
+            for byte in &mut word[addr.0 as usize % WORD_SIZE..] {
+                if idx == data.len() {
+                    break;
+                }
+                *byte = data[idx];
+                idx += 1;
+            }
+            words.push((waddr, u32::from_le_bytes(word)));
+        }
+        self.pager.poke_words(words);
+        Ok(())
+    }
+
+    /// Execute the single instruction at [Self::pc] and return what it
+    /// changed.
+    ///
+    /// This is intended for debuggers that step through the guest between
+    /// runs, e.g. after it has paused. The changes are applied to the memory
+    /// image right away, so that the next step or [Self::run] continues from
+    /// them, but as with [Self::set_register], the result cannot be proven as
+    /// a continuation of the previous session.
+    pub fn step_detailed(&mut self) -> Result<StepDelta> {
+        if let Some(ExitCode::Halted(_)) = self.exit_code {
+            bail!("cannot step a guest that has halted");
+        }
+        self.exit_code = None;
+        self.pending.reset(self.pc);
+        let pc = self.pc.0;
+
+        let mut emu = self.emu.take().unwrap_or_default();
+        self.record_events = true;
+        let result = emu.step(self);
+        self.record_events = false;
+        self.emu = Some(emu);
+        if let Err(err) = result {
+            self.pager.undo();
+            self.pending.events.clear();
+            return Err(err);
+        }
+
+        let kind = self.pending.kind.unwrap_or(InsnKind::INVALID);
+        let insn = self.pending.insn;
+        let mut register_writes = Vec::new();
+        let mut memory_writes = Vec::new();
+        for event in &self.pending.events {
+            match event {
+                TraceEvent::RegisterSet { idx, value } => register_writes.push((*idx, *value)),
+                TraceEvent::MemorySet { .. } => memory_writes.push(event.clone()),
+                _ => {}
+            }
+        }
+        self.advance()?;
+
+        // Apply the writes to the image, since the next run starts from a
+        // cleared pager.
+        self.pager.commit(self.pc);
+        self.pager.clear();
+
+        Ok(StepDelta {
+            pc,
+            next_pc: self.pc.0,
+            kind,
+            insn,
+            register_writes,
+            memory_writes,
+            exit_code: self.exit_code,
+        })
+    }
+
+    fn check_guest_range(addr: u32, len: u32) -> Result<Range<u32>> {
+        if len == 0 {
+            return Ok(addr..addr);
+        }
+        let Some(last) = addr.checked_add(len - 1) else {
+            bail!("{:?} + {len} overflows the address space", ByteAddr(addr));
+        };
+        Self::check_guest_addr(ByteAddr(addr))?;
+        Self::check_guest_addr(ByteAddr(last))?;
+        Ok(addr..last + 1)
     }
 
     pub fn run<F: FnMut(Segment) -> Result<()>>(
@@ -184,20 +1185,196 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         max_cycles: Option<u64>,
         mut callback: F,
     ) -> Result<ExecutorResult> {
-        // at least one HaltCycle needs to appear in the body
-        const MIN_HALT_CYCLES: usize = 1;
-        // a final "is_done" PageFault cycle is required when a split occurs
-        const PAGE_FINI_CYCLES: usize = 1;
+        let initial_state = self.pager.image.get_system_state();
+        let mut states = None;
+
+        let segments =
+            self.run_segments(segment_po2, max_cycles, |this, exit_code, po2, index| {
+                let (page_read_cycles, page_write_cycles) = this.pager.page_cycles();
+                let (pre_state, partial_image, post_state) = this.pager.commit(this.pc);
+                states = Some((pre_state.clone(), post_state.clone()));
+                callback(Segment {
+                    partial_image,
+                    pre_state,
+                    post_state,
+                    syscalls: mem::take(&mut this.syscalls),
+                    insn_cycles: this.insn_cycles,
+                    page_read_cycles,
+                    page_write_cycles,
+                    po2,
+                    exit_code,
+                    index,
+                    output_digest: this.output_digest,
+                    loader_cycles: this.loader_cycles,
+                })
+            })?;
+        let (pre_state, post_state) = states.unwrap();
+        let exit_code = self.exit_code.unwrap();
+
+        // NOTE: When a segment ends in a Halted(_) state, it may not update the
+        // post state digest. As a result, it will be the same as the pre_image.
+        // All other exit codes require the post state digest to reflect the
+        // final memory state.
+        //
+        // NOTE: The PC on the the post state is stored "+ 4". See ReceiptClaim
+        // for more detail.
+        let post_state = SystemState {
+            pc: post_state.pc,
+            merkle_root: match exit_code {
+                ExitCode::Halted(_) => pre_state.merkle_root,
+                _ => post_state.merkle_root,
+            },
+        };
+
+        Ok(ExecutorResult {
+            segments,
+            exit_code,
+            post_image: self.pager.image.clone(),
+            user_cycles: self.cycles.user.try_into()?,
+            total_cycles: self.cycles.total.try_into()?,
+            pre_state: initial_state,
+            post_state,
+            output_digest: self.output_digest,
+            opcode_counts: self.opcode_counts.iter().map(|(k, v)| (*k, *v)).collect(),
+            covered_pcs: mem::take(&mut self.covered_pcs),
+            self_modified_pcs: mem::take(&mut self.self_modified_pcs),
+            total_insns: self.total_insns,
+            memory_breakpoint: self.memory_breakpoint,
+            watchpoint: self.watchpoint,
+            breakpoint: self.breakpoint,
+            paging_stats: self.paging_stats,
+            executor_only_ecalls: mem::take(&mut self.executor_only_ecalls),
+            unprovable: mem::take(&mut self.unprovable),
+        })
+    }
+
+    /// Execute exactly like [Self::run], but only count the segments instead
+    /// of materializing them.
+    ///
+    /// This skips building the partial image of each segment and updating the
+    /// merkle tree of the memory image, which makes it cheaper than a full run
+    /// when only the shape of the session is of interest. Because the merkle
+    /// tree is not maintained, the [Executor] should not be used to run again
+    /// afterwards.
+    pub fn dry_run(&mut self, segment_po2: usize, max_cycles: Option<u64>) -> Result<DryRunResult> {
+        let mut segment_po2s = Vec::new();
+        let segments = self.run_segments(segment_po2, max_cycles, |this, _, po2, _| {
+            this.pager.commit_dry(this.pc);
+            this.syscalls.clear();
+            segment_po2s.push(po2);
+            Ok(())
+        })?;
+
+        Ok(DryRunResult {
+            segments,
+            exit_code: self.exit_code.unwrap(),
+            user_cycles: self.cycles.user.try_into()?,
+            total_cycles: self.cycles.total.try_into()?,
+            segment_po2s,
+        })
+    }
+
+    /// Execute a single segment from the current state, stopping at the first
+    /// split, and return the post state of that segment.
+    ///
+    /// This is used to re-execute a [Segment] from its partial image: with the
+    /// same `segment_po2` and syscall results, the post state must match the
+    /// one recorded in the [Segment].
+    pub fn run_segment(&mut self, segment_po2: usize) -> Result<SystemState> {
+        let mut post_state = None;
+        self.run_segments(segment_po2, None, |this, _, _, index| {
+            if index == 0 {
+                let (_, _, state) = this.pager.commit(this.pc);
+                post_state = Some(state);
+                // Stop before executing the next segment.
+                this.exit_code.get_or_insert(ExitCode::SystemSplit);
+            }
+            Ok(())
+        })?;
+        Ok(post_state.unwrap())
+    }
+
+    /// Execute until the guest exits, calling `on_segment` with the exit code,
+    /// po2, and index of each segment as it ends. Returns the number of
+    /// segments.
+    fn run_segments<F>(
+        &mut self,
+        segment_po2: usize,
+        max_cycles: Option<u64>,
+        mut on_segment: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&mut Self, ExitCode, usize, usize) -> Result<()>,
+    {
         // leave room for reserved cycles
-        const RESERVED_CYCLES: usize =
-            INIT_CYCLES + MIN_HALT_CYCLES + PAGE_FINI_CYCLES + FINI_CYCLES + ZK_CYCLES;
-        let segment_limit = (1 << segment_po2) - RESERVED_CYCLES;
+        let reserved_cycles = self.reserved_cycles;
+        let segment_limit = (1usize << segment_po2)
+            .checked_sub(reserved_cycles)
+            .ok_or_else(|| {
+                anyhow!(
+                    "reserved cycles ({reserved_cycles}) exceed the segment size: 2^{segment_po2}"
+                )
+            })?;
+
+        self.segment_limit = segment_limit;
+
+        // Don't pause again on the breakpoint the previous run stopped at.
+        let skip_breakpoint = self.breakpoint == Some(self.pc.0);
 
         self.reset();
 
-        let mut emu = Emulator::new();
+        // Put the emulator back however the run ends, so that its decode cache
+        // survives a failed run.
+        let mut emu = self.emu.take().unwrap_or_default();
+        let result = self.run_split_segments(
+            &mut emu,
+            segment_po2,
+            max_cycles,
+            skip_breakpoint,
+            &mut on_segment,
+        );
+        self.emu = Some(emu);
+        let mut segments = result?;
+
+        let segment_cycles = self.insn_cycles + self.pager.cycles + reserved_cycles;
+        let po2 = log2_ceil(segment_cycles.next_power_of_two()).try_into()?;
+        if po2 > segment_po2 {
+            bail!(ExecutorError::SegmentOverflow {
+                index: segments,
+                cycles: segment_cycles,
+                po2: segment_po2,
+            });
+        }
+        let exit_code = self.exit_code.unwrap();
+
+        self.record_paging();
+        on_segment(self, exit_code, po2, segments)?;
+        segments += 1;
+        self.cycles.total += 1 << po2;
+        self.report_progress(segments);
+
+        Ok(segments)
+    }
+
+    // Execute until the guest exits, calling `on_segment` for each segment that
+    // ends in a split. Returns the number of such segments; the last segment is
+    // left to [Self::run_segments].
+    fn run_split_segments<F>(
+        &mut self,
+        emu: &mut Emulator,
+        segment_po2: usize,
+        max_cycles: Option<u64>,
+        mut skip_breakpoint: bool,
+        on_segment: &mut F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&mut Self, ExitCode, usize, usize) -> Result<()>,
+    {
+        let reserved_cycles = self.reserved_cycles;
+        let segment_limit = self.segment_limit;
         let mut segments = 0;
-        let initial_state = self.pager.image.get_system_state();
+        let start_time = Instant::now();
+        let mut steps = 0usize;
 
         loop {
             if self.exit_code.is_some() {
@@ -206,102 +1383,170 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
             if let Some(max_cycles) = max_cycles {
                 if self.cycles.user >= max_cycles as usize {
-                    bail!("Session limit exceeded");
+                    match self.session_limit_behavior {
+                        SessionLimitBehavior::Error => {
+                            bail!(ExecutorError::SessionLimitExceeded)
+                        }
+                        SessionLimitBehavior::Truncate => {
+                            tracing::debug!("session limit reached at pc: {:?}", self.pc);
+                            self.exit_code = Some(ExitCode::SessionLimit);
+                            break;
+                        }
+                    }
                 }
             }
 
-            emu.step(self)?;
+            steps += 1;
+            if steps % POLL_STEPS == 0 {
+                if let Some(timeout) = self.wall_clock_timeout {
+                    if start_time.elapsed() > timeout {
+                        bail!(ExecutorError::Timeout(timeout));
+                    }
+                }
+                if let Some(flag) = &self.cancel_flag {
+                    if flag.load(Ordering::Relaxed) {
+                        bail!(ExecutorError::Cancelled);
+                    }
+                }
+            }
+
+            if let Some(interval) = self.progress_interval {
+                if steps % interval == 0 {
+                    self.report_progress(segments);
+                }
+            }
+
+            if !mem::take(&mut skip_breakpoint) && self.breakpoints.contains(&self.pc.0) {
+                tracing::debug!("breakpoint hit at pc: {:?}, pausing", self.pc);
+                self.exit_code = Some(ExitCode::Paused(0));
+                self.unprovable.insert(Unprovable::HostPause);
+                self.breakpoint = Some(self.pc.0);
+                break;
+            }
+
+            if let Err(err) = emu.step(self) {
+                let cause = FaultCause::from_error(&err);
+                tracing::debug!("fault at pc: {:?}, cause: {cause:?}", self.pc);
+                self.fault = Some(Fault {
+                    pc: self.pc.0,
+                    cause,
+                });
+                return Err(err);
+            }
 
             let segment_cycles = self.insn_cycles + self.pager.cycles + self.pending.cycles;
-            if segment_cycles < segment_limit {
+            let insn_limit_reached = self
+                .split_every_insns
+                .is_some_and(|max_insns| self.segment_insns >= max_insns);
+            if segment_cycles < segment_limit && !insn_limit_reached && !self.split_requested {
                 self.advance()?;
             } else if self.insn_cycles == 0 {
-                bail!(
-                    "segment limit ({segment_limit}) too small for instruction at pc: {:?}",
-                    self.pc
-                );
+                tracing::debug!("instruction at pc: {:?} does not fit in a segment", self.pc);
+                bail!(ExecutorError::SegmentOverflow {
+                    index: segments,
+                    cycles: segment_cycles + reserved_cycles,
+                    po2: segment_po2,
+                });
             } else {
                 self.pager.undo();
-                let used_cycles = self.insn_cycles + self.pager.cycles + RESERVED_CYCLES;
+                let used_cycles = self.insn_cycles + self.pager.cycles + reserved_cycles;
                 let waste = (1 << segment_po2) - used_cycles;
                 tracing::debug!(
-                    "split: {} + {} + {RESERVED_CYCLES} = {used_cycles}, waste: {waste}, pending: {:?}",
+                    "split: {} + {} + {reserved_cycles} = {used_cycles}, waste: {waste}, pending: {:?}",
                     self.insn_cycles,
                     self.pager.cycles,
                     self.pending
                 );
 
                 // split
-                let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
-                callback(Segment {
-                    partial_image,
-                    pre_state,
-                    post_state,
-                    syscalls: mem::take(&mut self.syscalls),
-                    insn_cycles: self.insn_cycles,
-                    po2: segment_po2,
-                    exit_code: ExitCode::SystemSplit,
-                    index: segments,
-                    output_digest: self.output_digest,
-                })?;
+                self.record_paging();
+                on_segment(self, ExitCode::SystemSplit, segment_po2, segments)?;
+                if let Some(callback) = &mut self.split_callback {
+                    callback(SplitInfo {
+                        index: segments,
+                        split_insn: self.total_insns,
+                        session_cycle: self.cycles.user as u64,
+                    });
+                }
                 segments += 1;
+                if self.max_segments.is_some_and(|max| segments >= max) {
+                    bail!(ExecutorError::TooManySegments);
+                }
+                self.report_progress(segments);
                 self.cycles.total += 1 << segment_po2;
                 self.pager.clear();
                 self.insn_cycles = 0;
+                self.segment_insns = 0;
+                self.split_requested = false;
 
                 // replay the current instruction in a new segment
+                skip_breakpoint = true;
                 self.pending.pc = self.pc;
                 self.pending.cycles = 0;
+                self.pending.breakpoint = None;
+                self.pending.watchpoint = None;
+                self.pending.split = false;
             }
-        }
-
-        let (pre_state, partial_image, post_state) = self.pager.commit(self.pc);
-        let segment_cycles = self.insn_cycles + self.pager.cycles + RESERVED_CYCLES;
-        let po2 = log2_ceil(segment_cycles.next_power_of_two()).try_into()?;
-        let exit_code = self.exit_code.unwrap();
-
-        callback(Segment {
-            partial_image,
-            pre_state: pre_state.clone(),
-            post_state: post_state.clone(),
-            syscalls: mem::take(&mut self.syscalls),
-            insn_cycles: self.insn_cycles,
-            po2,
-            exit_code,
-            index: segments,
-            output_digest: self.output_digest,
-        })?;
-        segments += 1;
-        self.cycles.total += 1 << po2;
-
-        // NOTE: When a segment ends in a Halted(_) state, it may not update the
-        // post state digest. As a result, it will be the same as the pre_image.
-        // All other exit codes require the post state digest to reflect the
-        // final memory state.
-        //
-        // NOTE: The PC on the the post state is stored "+ 4". See ReceiptClaim
-        // for more detail.
-        let post_state = SystemState {
-            pc: post_state.pc,
-            merkle_root: match exit_code {
-                ExitCode::Halted(_) => pre_state.merkle_root,
-                _ => post_state.merkle_root,
-            },
-        };
+        }
 
-        Ok(ExecutorResult {
-            segments,
-            exit_code,
-            post_image: self.pager.image.clone(),
-            user_cycles: self.cycles.user.try_into()?,
-            total_cycles: self.cycles.total.try_into()?,
-            pre_state: initial_state,
-            post_state,
-            output_digest: self.output_digest,
-        })
+        Ok(segments)
+    }
+
+    // Add the paging of the current segment to the totals, before the pager is
+    // cleared for the next one.
+    fn record_paging(&mut self) {
+        let (page_ins, page_outs) = self.pager.page_counts();
+        let (page_read_cycles, page_write_cycles) = self.pager.page_cycles();
+        self.paging_stats.page_ins += page_ins as u64;
+        self.paging_stats.page_outs += page_outs as u64;
+        self.paging_stats.page_read_cycles += page_read_cycles as u64;
+        self.paging_stats.page_write_cycles += page_write_cycles as u64;
+    }
+
+    fn report_progress(&mut self, segments: usize) {
+        if let Some(callback) = &mut self.progress_callback {
+            callback(ProgressInfo {
+                session_cycle: self.cycles.user as u64,
+                segments_completed: segments,
+                pc: self.pc.0,
+            });
+        }
+    }
+
+    // Whether to record the trace events of the current instruction.
+    fn tracing(&self) -> bool {
+        self.record_events || self.detect_self_modifying_code || !self.trace.is_empty()
+    }
+
+    // Flag the current instruction if it was fetched from a word written
+    // earlier in the run, then note the words that it writes.
+    fn check_self_modified(&mut self) {
+        if self.written_words.contains(&self.pc.waddr().0)
+            && !self.self_modified_pcs.contains(&self.pc.0)
+        {
+            tracing::debug!("self-modified instruction at pc: {:?}", self.pc);
+            self.self_modified_pcs.push(self.pc.0);
+        }
+        for event in &self.pending.events {
+            if let TraceEvent::MemorySet { addr, region } = event {
+                let start = addr / WORD_SIZE as u32;
+                let end = (addr + region.len() as u32).div_ceil(WORD_SIZE as u32);
+                self.written_words.extend(start..end);
+            }
+        }
     }
 
     fn advance(&mut self) -> Result<()> {
+        if self.tracing() {
+            let cycle: u32 = self.cycles.user.try_into()?;
+            for (page_idx, dirty) in self.pager.pending_pages() {
+                self.pending.events.insert(match dirty {
+                    false => TraceEvent::PageIn { page_idx, cycle },
+                    true => TraceEvent::PageOut { page_idx, cycle },
+                });
+            }
+        }
+
         for trace in &self.trace {
             trace
                 .borrow_mut()
@@ -316,7 +1561,19 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             }
         }
 
+        let kind = self.pending.kind.take();
+        if let Some(kind) = kind {
+            *self.opcode_counts.entry(kind).or_default() += 1;
+        }
+        if self.track_coverage {
+            self.covered_pcs.insert(self.pc.0);
+        }
+        if self.detect_self_modifying_code {
+            self.check_self_modified();
+        }
         self.pc = self.pending.pc;
+        self.segment_insns += 1;
+        self.total_insns += 1;
         self.insn_cycles += self.pending.cycles;
         self.cycles.user += self.pending.cycles;
         self.pending.cycles = 0;
@@ -324,13 +1581,82 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         if let Some(syscall) = self.pending.syscall.take() {
             self.syscalls.push(syscall);
         }
+        if let Some(info) = self.pending.ecall.take() {
+            if let Some(callback) = &mut self.ecall_callback {
+                callback(info);
+            }
+        }
+        if mem::take(&mut self.pending.split) {
+            tracing::debug!("split requested by the guest at pc: {:?}", self.pc);
+            self.split_requested = true;
+        }
         self.output_digest = self.pending.output_digest.take();
         self.exit_code = self.pending.exit_code.take();
+        if let Some(breakpoint) = self.pending.breakpoint.take() {
+            self.memory_breakpoints.retain(|bp| *bp != breakpoint);
+            if self.exit_code.is_none() {
+                tracing::debug!("memory breakpoint {breakpoint:x?} hit, pausing");
+                self.exit_code = Some(ExitCode::Paused(0));
+                self.unprovable.insert(Unprovable::HostPause);
+                self.memory_breakpoint = Some(breakpoint);
+            }
+        }
+        if let Some(addr) = self.pending.watchpoint.take() {
+            if self.exit_code.is_none() {
+                tracing::debug!("watchpoint on {:?} hit, pausing", ByteAddr(addr));
+                self.exit_code = Some(ExitCode::Paused(0));
+                self.unprovable.insert(Unprovable::HostPause);
+                self.watchpoint = Some(addr);
+            }
+        }
+        let fuel_cost = kind
+            .and_then(|kind| self.fuel_costs.get(&kind.major_type()))
+            .map_or(1, |&cost| cost);
+        if let Some(max_fuel) = self.initial_fuel {
+            let refill = mem::take(&mut self.pending.fuel);
+            self.fuel = self
+                .fuel
+                .saturating_add(refill)
+                .min(max_fuel)
+                .saturating_sub(fuel_cost);
+            if self.fuel == 0 && self.exit_code.is_none() {
+                tracing::debug!("out of fuel at pc: {:?}", self.pending.pc);
+                self.exit_code = Some(ExitCode::OutOfFuel);
+            }
+        }
+        if let Some(limit) = self.fuel_limit {
+            self.fuel_used = self.fuel_used.saturating_add(fuel_cost);
+            if self.fuel_used >= limit && self.exit_code.is_none() {
+                tracing::debug!("fuel limit reached at pc: {:?}", self.pending.pc);
+                self.exit_code = Some(ExitCode::FuelExhausted);
+            }
+        }
         self.pager.commit_step();
 
         Ok(())
     }
 
+    fn guest_call(&mut self, addr: ByteAddr, args: &[u32], return_pc: ByteAddr) -> Result<u32> {
+        for (i, arg) in args.iter().enumerate() {
+            self.store_register(REG_A0 + i, *arg)?;
+        }
+        self.store_register(REG_RA, return_pc.0)?;
+
+        let mut emu = Emulator::new();
+        let mut cycles = 0;
+        while self.pending.pc != return_pc {
+            emu.step(self)?;
+            cycles += mem::take(&mut self.pending.cycles);
+            if cycles > GUEST_CALL_CYCLE_LIMIT {
+                bail!("guest call to {addr:?} exceeded {GUEST_CALL_CYCLE_LIMIT} cycles");
+            }
+            self.pending.events.clear();
+            self.pc = self.pending.pc;
+        }
+
+        self.load_register(REG_A0)
+    }
+
     fn reset(&mut self) {
         self.pager.clear();
         self.exit_code = None;
@@ -339,10 +1665,48 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.pending.reset(self.pc);
         self.cycles.user = 0;
         self.cycles.total = 0;
+        self.paging_stats = PagingStats::default();
+        self.executor_only_ecalls.clear();
+        self.unprovable.clear();
+        self.record_unprovable_config();
+        self.segment_insns = 0;
+        self.total_insns = 0;
+        self.split_requested = false;
+        self.syscall_counts.clear();
+        self.opcode_counts.clear();
+        self.covered_pcs.clear();
+        self.written_words.clear();
+        self.self_modified_pcs.clear();
+        self.memory_breakpoint = None;
+        self.watchpoint = None;
+        self.breakpoint = None;
+        self.fault = None;
+        self.fuel = self.initial_fuel.unwrap_or_default();
+        self.fuel_used = 0;
     }
 }
 
 impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
+    // Record the settings under which the segments of a run no longer match
+    // what the circuit proves.
+    fn record_unprovable_config(&mut self) {
+        if self.cost_model != CycleCostModel::default() {
+            self.unprovable.insert(Unprovable::CycleCostModel);
+        }
+        if !self.insn_costs.is_empty() {
+            self.unprovable.insert(Unprovable::InsnCycleCosts);
+        }
+        if self.loader_cycles != LoaderCycles::default() {
+            self.unprovable.insert(Unprovable::LoaderCycles);
+        }
+        if self.reserved_cycles != RESERVED_CYCLES {
+            self.unprovable.insert(Unprovable::ReservedCycles);
+        }
+        if self.pager.image.info.page_size != PAGE_SIZE as u32 {
+            self.unprovable.insert(Unprovable::PageSize);
+        }
+    }
+
     fn ecall_halt(&mut self) -> Result<bool> {
         let a0 = self.load_register(REG_A0)?;
         let output_ptr = self.load_guest_addr_from_register(REG_A1)?;
@@ -356,9 +1720,15 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.pending.exit_code = match halt_type {
             halt::TERMINATE => Some(ExitCode::Halted(user_exit)),
             halt::PAUSE => Some(ExitCode::Paused(user_exit)),
-            _ => bail!("Illegal halt type: {halt_type}"),
+            _ => bail!(ExecutorError::IllegalHaltType(halt_type)),
         };
-        self.pending.output_digest = Some(output.into());
+        let output = Digest::from(output);
+        if let Some(expected) = self.expected_output_digest {
+            if halt_type == halt::TERMINATE && output != expected {
+                bail!("output digest mismatch: expected {expected}, got {output}");
+            }
+        }
+        self.pending.output_digest = Some(output);
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -379,7 +1749,32 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
         let chunks = align_up(into_guest_len, IO_CHUNK_WORDS) / IO_CHUNK_WORDS;
 
-        let syscall = if let Some(syscall) = &self.pending.syscall {
+        if self.pending.syscall.is_none() {
+            let count = self.syscall_counts.entry(syscall_name.clone()).or_default();
+            if let Some(limit) = self.syscall_rate_limits.get(&syscall_name) {
+                if *count >= *limit {
+                    tracing::debug!("rate limit reached for {syscall_name}, pausing");
+                    self.pending.exit_code = Some(ExitCode::Paused(0));
+                    self.unprovable.insert(Unprovable::HostPause);
+                    return Ok(true);
+                }
+            }
+            *count += 1;
+        }
+
+        if self.tracing() {
+            self.pending.events.insert(TraceEvent::SyscallStart {
+                name: syscall_name.clone(),
+                cycle: self.cycles.user.try_into()?,
+            });
+        }
+
+        let syscall = if let Some(syscall) = self
+            .pending
+            .syscall
+            .as_ref()
+            .filter(|_| self.syscall_replay)
+        {
             tracing::debug!("Replay syscall: {syscall:?}");
             syscall.clone()
         } else {
@@ -390,6 +1785,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                 .syscall(&syscall_name, self, &mut to_guest)?;
 
             let syscall = SyscallRecord {
+                name: syscall_name.clone(),
                 to_guest,
                 regs: (a0, a1),
             };
@@ -408,9 +1804,23 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.store_register(REG_A0, a0)?;
         self.store_register(REG_A1, a1)?;
 
+        // The guest asked to end the segment once this syscall completes.
+        if syscall_name == SYS_SPLIT.as_str() {
+            self.pending.split = true;
+        }
+
+        if self.tracing() {
+            self.pending.events.insert(TraceEvent::SyscallEnd {
+                name: syscall_name,
+                to_guest_words: into_guest_len.try_into()?,
+                a0,
+                a1,
+            });
+        }
+
         tracing::trace!("{syscall:08x?}");
 
-        self.pending.cycles += chunks + 1; // syscallBody + syscallFini
+        self.pending.cycles += chunks + self.cost_model.syscall_overhead; // syscallBody + syscallFini
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -420,10 +1830,74 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         tracing::debug!("[{}] ecall_sha", self.insn_cycles);
         let state_out_ptr = self.load_guest_addr_from_register(REG_A0)?;
         let state_in_ptr = self.load_guest_addr_from_register(REG_A1)?;
-        let mut block1_ptr = self.load_guest_addr_from_register(REG_A2)?;
-        let mut block2_ptr = self.load_guest_addr_from_register(REG_A3)?;
+        let block1_ptr = self.load_guest_addr_from_register(REG_A2)?;
+        let block2_ptr = self.load_guest_addr_from_register(REG_A3)?;
         let count = self.load_register(REG_A4)?;
 
+        self.sha_compress(state_out_ptr, state_in_ptr, block1_ptr, block2_ptr, count)?;
+
+        self.pending.cycles += SHA_INIT + self.cost_model.sha_per_block * count as usize;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn ecall_sha_batch(&mut self) -> Result<bool> {
+        let descriptors_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let count = self.load_register(REG_A1)?;
+        tracing::debug!("[{}] ecall_sha_batch({count})", self.insn_cycles);
+
+        let mut blocks = 0;
+        for i in 0..count as usize {
+            let mut descriptor = [0u32; sha_batch::DESCRIPTOR_WORDS];
+            let ptr = descriptors_ptr + i * sha_batch::DESCRIPTOR_WORDS * WORD_SIZE;
+            self.load_words_from_guest(ptr, &mut descriptor)?;
+            let [state_out_ptr, state_in_ptr, blocks_ptr, block_count] = descriptor;
+            let blocks_ptr = Self::check_guest_addr(ByteAddr(blocks_ptr))?;
+            self.sha_compress(
+                Self::check_guest_addr(ByteAddr(state_out_ptr))?,
+                Self::check_guest_addr(ByteAddr(state_in_ptr))?,
+                blocks_ptr,
+                blocks_ptr + DIGEST_BYTES,
+                block_count,
+            )?;
+            blocks += block_count as usize;
+        }
+
+        self.pending.cycles += SHA_INIT + self.cost_model.sha_per_block * blocks;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    // Compress `count` blocks into the state at `state_in_ptr`, and store the
+    // result at `state_out_ptr`. The two halves of each block are read from
+    // `block1_ptr` and `block2_ptr`, both of which advance by a block at a time.
+    fn sha_compress(
+        &mut self,
+        state_out_ptr: ByteAddr,
+        state_in_ptr: ByteAddr,
+        mut block1_ptr: ByteAddr,
+        mut block2_ptr: ByteAddr,
+        count: u32,
+    ) -> Result<()> {
+        if !block1_ptr.is_aligned() || !block2_ptr.is_aligned() {
+            bail!("ecall_sha: unaligned block pointer: {block1_ptr:?}, {block2_ptr:?}");
+        }
+        // Check the whole region of each half up front, rather than failing
+        // part way through the blocks.
+        if count != 0 {
+            let span = (count - 1)
+                .checked_mul(BLOCK_BYTES as u32)
+                .and_then(|len| len.checked_add(DIGEST_BYTES as u32 - 1));
+            for ptr in [block1_ptr, block2_ptr] {
+                match span.and_then(|span| ptr.0.checked_add(span)) {
+                    Some(last) if is_guest_memory(last) => {}
+                    _ => bail!("ecall_sha: {count} blocks at {ptr:?} extend past guest memory"),
+                }
+            }
+        }
+
         let state_in: [u8; DIGEST_BYTES] = self.load_array_from_guest(state_in_ptr)?;
         let mut state: [u32; DIGEST_WORDS] = bytemuck::cast_slice(&state_in).try_into().unwrap();
         for word in &mut state {
@@ -435,16 +1909,12 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
         for _ in 0..count {
             let (digest1, digest2) = block.split_at_mut(DIGEST_WORDS);
-            for (i, word) in digest1.iter_mut().enumerate() {
-                *word = self.load_u32_from_guest(block1_ptr + (i * WORD_SIZE))?;
-            }
-            for (i, word) in digest2.iter_mut().enumerate() {
-                *word = self.load_u32_from_guest(block2_ptr + (i * WORD_SIZE))?;
-            }
+            self.load_words_from_guest(block1_ptr, digest1)?;
+            self.load_words_from_guest(block2_ptr, digest2)?;
             // tracing::debug!("Compressing block {block:02x?}");
             sha2::compress256(
                 &mut state,
-                &[*GenericArray::from_slice(bytemuck::cast_slice(&block))],
+                slice::from_ref(GenericArray::from_slice(bytemuck::cast_slice(&block))),
             );
 
             block1_ptr += BLOCK_BYTES;
@@ -456,59 +1926,366 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             *word = u32::from_be(*word);
         }
 
-        self.store_region_into_guest(state_out_ptr, bytemuck::cast_slice(&state))?;
+        self.store_region_into_guest(state_out_ptr, bytemuck::cast_slice(&state))
+    }
+
+    fn ecall_sha512(&mut self) -> Result<bool> {
+        tracing::debug!("[{}] ecall_sha512", self.insn_cycles);
+        let state_out_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let state_in_ptr = self.load_guest_addr_from_register(REG_A1)?;
+        let mut block1_ptr = self.load_guest_addr_from_register(REG_A2)?;
+        let mut block2_ptr = self.load_guest_addr_from_register(REG_A3)?;
+        let count = self.load_register(REG_A4)?;
+
+        // Like the SHA-256 state, the state is kept in memory as big-endian
+        // words.
+        let state_in: [u8; sha512::STATE_BYTES] = self.load_array_from_guest(state_in_ptr)?;
+        let mut state: [u64; sha512::STATE_WORDS] = array::from_fn(|i| {
+            u64::from_be_bytes(state_in[i * 8..(i + 1) * 8].try_into().unwrap())
+        });
+
+        let mut block = [0u8; sha512::BLOCK_BYTES];
+        for _ in 0..count {
+            let (half1, half2) = block.split_at_mut(sha512::BLOCK_BYTES / 2);
+            half1.copy_from_slice(
+                &self.load_array_from_guest::<{ sha512::BLOCK_BYTES / 2 }>(block1_ptr)?,
+            );
+            half2.copy_from_slice(
+                &self.load_array_from_guest::<{ sha512::BLOCK_BYTES / 2 }>(block2_ptr)?,
+            );
+            sha2::compress512(&mut state, &[*GenericArray::from_slice(&block)]);
+
+            block1_ptr += sha512::BLOCK_BYTES;
+            block2_ptr += sha512::BLOCK_BYTES;
+        }
+
+        let state_out: Vec<u8> = state.iter().flat_map(|word| word.to_be_bytes()).collect();
+        self.store_region_into_guest(state_out_ptr, &state_out)?;
+
+        self.pending.cycles += SHA_INIT + self.cost_model.sha512_per_block * count as usize;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn ecall_keccak(&mut self) -> Result<bool> {
+        let data_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let len = self.load_register(REG_A1)?;
+        let out_ptr = self.load_guest_addr_from_register(REG_A2)?;
+        tracing::debug!("[{}] ecall_keccak({len})", self.insn_cycles);
+
+        let cycles = keccak_cycles(len as usize);
+        self.check_ecall_cycles("ecall_keccak", cycles)?;
+        let data = self.load_region_from_guest(data_ptr, len)?;
+        let digest = Keccak256::digest(&data);
+        self.store_region_into_guest(out_ptr, &digest)?;
+
+        self.pending.cycles += cycles;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn ecall_poseidon(&mut self) -> Result<bool> {
+        let out_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let in_ptr = self.load_guest_addr_from_register(REG_A1)?;
+        let count = self.load_register(REG_A2)?;
+        tracing::debug!("[{}] ecall_poseidon({count})", self.insn_cycles);
+
+        if !in_ptr.is_aligned() || !out_ptr.is_aligned() {
+            bail!("ecall_poseidon: unaligned pointer: {in_ptr:?}, {out_ptr:?}");
+        }
+        let len = count
+            .checked_mul(WORD_SIZE as u32)
+            .ok_or_else(|| anyhow!("ecall_poseidon: too many elements: {count}"))?;
+        let cycles = poseidon_cycles(count as usize);
+        self.check_ecall_cycles("ecall_poseidon", cycles)?;
+        Self::check_guest_range(in_ptr.0, len)?;
+        let mut words = vec![0u32; count as usize];
+        self.load_words_from_guest(in_ptr, &mut words)?;
+        let elems = words
+            .iter()
+            .map(|&word| {
+                ensure!(
+                    word < poseidon::MODULUS,
+                    "ecall_poseidon: element out of range: {word}"
+                );
+                Ok(BabyBearElem::new(word))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let out = unpadded_hash(elems.iter()).map(|elem| elem.as_u32());
+        self.store_region_into_guest(out_ptr, bytemuck::cast_slice(&out))?;
+
+        self.pending.cycles += cycles;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn ecall_sha_mem(&mut self) -> Result<bool> {
+        let data_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let len = self.load_register(REG_A1)?;
+        let out_ptr = self.load_guest_addr_from_register(REG_A2)?;
+        tracing::debug!("[{}] ecall_sha_mem({len})", self.insn_cycles);
+
+        // Charge the same as compressing the padded message with ecall_sha.
+        let cycles = SHA_INIT + self.cost_model.sha_per_block * sha_blocks(len as usize);
+        self.check_ecall_cycles("ecall_sha_mem", cycles)?;
+        let data = self.load_region_from_guest(data_ptr, len)?;
+        let digest = sha2::Sha256::digest(&data);
+        self.store_region_into_guest(out_ptr, &digest)?;
+
+        self.pending.cycles += cycles;
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn ecall_reduce(&mut self) -> Result<bool> {
+        let op = self.load_register(REG_A0)?;
+        let ptr = self.load_guest_addr_from_register(REG_A1)?;
+        let width = self.load_register(REG_A2)?;
+        let count = self.load_register(REG_A3)?;
+        let modulus =
+            (self.load_register(REG_A5)? as u64) << 32 | self.load_register(REG_A4)? as u64;
+        tracing::debug!(
+            "[{}] ecall_reduce(op: {op}, width: {width}, count: {count})",
+            self.insn_cycles
+        );
+
+        let (words, mask) = match width {
+            reduce::WIDTH_U32 => (1u32, u32::MAX as u64),
+            reduce::WIDTH_U64 => (2, u64::MAX),
+            _ => bail!("ecall_reduce: invalid element width: {width}"),
+        };
+        let mut acc = match op {
+            reduce::OP_SUM | reduce::OP_MAX | reduce::OP_XOR => 0,
+            reduce::OP_PRODUCT if modulus == 0 => 1,
+            reduce::OP_PRODUCT => 1 % modulus,
+            reduce::OP_MIN => mask,
+            _ => bail!("ecall_reduce: unknown op: {op}"),
+        };
+        if !ptr.is_aligned() {
+            bail!("ecall_reduce: unaligned buffer: {ptr:?}");
+        }
+        let total_words = count.checked_mul(words);
+        let bytes = total_words.and_then(|total_words| total_words.checked_mul(WORD_SIZE as u32));
+        let (Some(total_words), Some(bytes)) = (total_words, bytes) else {
+            bail!("ecall_reduce: too many elements: {count}");
+        };
+        let cycles = reduce_cycles(total_words as usize);
+        self.check_ecall_cycles("ecall_reduce", cycles)?;
+        Self::check_guest_range(ptr.0, bytes)?;
+        let mut buf = vec![0u32; total_words as usize];
+        self.load_words_from_guest(ptr, &mut buf)?;
+
+        // Elements are little-endian, so the high word comes last.
+        for elem in buf.chunks_exact(words as usize) {
+            let x = elem
+                .iter()
+                .rev()
+                .fold(0u64, |x, &word| x << 32 | word as u64);
+            acc = match op {
+                reduce::OP_SUM => acc.wrapping_add(x) & mask,
+                reduce::OP_PRODUCT if modulus == 0 => acc.wrapping_mul(x) & mask,
+                reduce::OP_PRODUCT => (acc as u128 * x as u128 % modulus as u128) as u64,
+                reduce::OP_MIN => acc.min(x),
+                reduce::OP_MAX => acc.max(x),
+                reduce::OP_XOR => acc ^ x,
+                _ => unreachable!(),
+            };
+        }
 
-        self.pending.cycles += sha_cycles(count as usize);
+        self.store_register(REG_A0, acc as u32)?;
+        self.store_register(REG_A1, (acc >> 32) as u32)?;
+        self.pending.cycles += cycles;
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
     }
 
-    fn ecall_bigint(&mut self) -> Result<bool> {
+    // Only the BIGINT_EXT ecall, which is `extended`, accepts the ops and widths
+    // that the circuit does not support.
+    fn ecall_bigint(&mut self, extended: bool) -> Result<bool> {
         let op = self.load_register(REG_A1)?;
         let z_ptr = self.load_guest_addr_from_register(REG_A0)?;
         let x_ptr = self.load_guest_addr_from_register(REG_A2)?;
         let y_ptr = self.load_guest_addr_from_register(REG_A3)?;
         let n_ptr = self.load_guest_addr_from_register(REG_A4)?;
 
-        let mut load_bigint_le_bytes = |ptr: ByteAddr| -> Result<[u8; bigint::WIDTH_BYTES]> {
-            let mut arr = [0u32; bigint::WIDTH_WORDS];
-            for (i, word) in arr.iter_mut().enumerate() {
-                *word = self
-                    .load_u32_from_guest(ptr + (i * WORD_SIZE) as u32)?
-                    .to_le();
+        let (op, width) = (op & bigint::OP_MASK, op & !bigint::OP_MASK);
+        match op {
+            bigint::OP_MULTIPLY => {}
+            bigint::OP_ADD | bigint::OP_SUB if extended => {}
+            bigint::OP_ADD | bigint::OP_SUB => {
+                bail!("ecall_bigint: op {op} requires the BIGINT_EXT ecall")
+            }
+            _ => bail!("ecall_bigint: unknown op: {op}"),
+        }
+        if !extended && width != bigint::WIDTH_256 {
+            bail!("ecall_bigint: width {width:#x} requires the BIGINT_EXT ecall");
+        }
+
+        let cycles = match width {
+            bigint::WIDTH_256 => {
+                self.bigint_op::<{ U256::LIMBS }, { U512::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?
             }
-            Ok(bytemuck::cast(arr))
+            bigint::WIDTH_384 => {
+                self.bigint_op::<{ U384::LIMBS }, { U768::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?
+            }
+            bigint::WIDTH_512 => {
+                self.bigint_op::<{ U512::LIMBS }, { U1024::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?
+            }
+            bigint::WIDTH_1024 => self
+                .bigint_op::<{ U1024::LIMBS }, { U2048::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?,
+            bigint::WIDTH_2048 => self
+                .bigint_op::<{ U2048::LIMBS }, { U4096::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?,
+            bigint::WIDTH_4096 => self
+                .bigint_op::<{ U4096::LIMBS }, { U8192::LIMBS }>(op, z_ptr, x_ptr, y_ptr, n_ptr)?,
+            _ => bail!("ecall_bigint: unknown width: {:#x}", width),
         };
 
-        if op != 0 {
-            bail!("ecall_bigint: op must be set to 0");
-        }
+        self.pending.cycles += cycles;
+        self.pending.pc = self.pc + WORD_SIZE;
 
+        Ok(true)
+    }
+
+    // Computes a BigInt `op` on operands of `L` limbs, with `W` (twice `L`)
+    // limbs for the intermediate product, and returns the cycles it costs.
+    fn bigint_op<const L: usize, const W: usize>(
+        &mut self,
+        op: u32,
+        z_ptr: ByteAddr,
+        x_ptr: ByteAddr,
+        y_ptr: ByteAddr,
+        n_ptr: ByteAddr,
+    ) -> Result<usize> {
         // Load inputs.
-        let x = U256::from_le_bytes(load_bigint_le_bytes(x_ptr)?);
-        let y = U256::from_le_bytes(load_bigint_le_bytes(y_ptr)?);
-        let n = U256::from_le_bytes(load_bigint_le_bytes(n_ptr)?);
+        let x = self.load_bigint_from_guest::<L>(x_ptr)?;
+        let y = self.load_bigint_from_guest::<L>(y_ptr)?;
+        let n = self.load_bigint_from_guest::<L>(n_ptr)?;
+
+        // Cycles scale with the number of 256-bit chunks of the operands.
+        let chunks = Uint::<L>::BITS.div_ceil(bigint::WIDTH_BITS);
+
+        // Compute the modular operation, or simply the plain operation if n == 0.
+        let (z, cycles): (Uint<L>, usize) = match op {
+            bigint::OP_MULTIPLY => {
+                let z = if n == Uint::ZERO {
+                    x.checked_mul(&y).unwrap()
+                } else {
+                    let w = x.resize::<W>().wrapping_mul(&y.resize());
+                    let z = w.rem(&NonZero::<Uint<W>>::from_uint(n.resize()));
+                    z.resize()
+                };
+                (z, self.cost_model.bigint_per_op * chunks * chunks)
+            }
+            bigint::OP_ADD => {
+                let z = if n == Uint::ZERO {
+                    x.wrapping_add(&y)
+                } else {
+                    let n_nz = NonZero::from_uint(n);
+                    x.rem(&n_nz).add_mod(&y.rem(&n_nz), &n)
+                };
+                (z, self.cost_model.bigint_add_per_op * chunks)
+            }
+            bigint::OP_SUB => {
+                let z = if n == Uint::ZERO {
+                    x.wrapping_sub(&y)
+                } else {
+                    let n_nz = NonZero::from_uint(n);
+                    x.rem(&n_nz).sub_mod(&y.rem(&n_nz), &n)
+                };
+                (z, self.cost_model.bigint_sub_per_op * chunks)
+            }
+            _ => unreachable!(),
+        };
 
-        // Compute modular multiplication, or simply multiplication if n == 0.
-        let z: U256 = if n == U256::ZERO {
-            x.checked_mul(&y).unwrap()
-        } else {
-            let (w_lo, w_hi) = x.mul_wide(&y);
-            let w = w_hi.concat(&w_lo);
-            let z = w.rem(&NonZero::<U512>::from_uint(n.resize()));
-            z.resize()
+        self.store_bigint_into_guest(z_ptr, &z)?;
+
+        Ok(cycles)
+    }
+
+    fn ecall_bigint_modexp(&mut self) -> Result<bool> {
+        let z_ptr = self.load_guest_addr_from_register(REG_A0)?;
+        let base_ptr = self.load_guest_addr_from_register(REG_A1)?;
+        let exp_ptr = self.load_guest_addr_from_register(REG_A2)?;
+        let n_ptr = self.load_guest_addr_from_register(REG_A3)?;
+
+        let base: U256 = self.load_bigint_from_guest(base_ptr)?;
+        let exp: U256 = self.load_bigint_from_guest(exp_ptr)?;
+        let n: U256 = self.load_bigint_from_guest(n_ptr)?;
+        if n == U256::ZERO {
+            bail!("ecall_bigint_modexp: modulus is zero");
+        }
+
+        let n = NonZero::<U512>::from_uint(n.resize());
+        let mul_mod = |x: &U256, y: &U256| -> U256 {
+            let (w_lo, w_hi) = x.mul_wide(y);
+            w_hi.concat(&w_lo).rem(&n).resize()
         };
 
-        // Store result.
-        for (i, word) in bytemuck::cast::<_, [u32; bigint::WIDTH_WORDS]>(z.to_le_bytes())
-            .into_iter()
-            .enumerate()
-        {
-            self.store_u32_into_guest(z_ptr + (i * WORD_SIZE) as u32, word.to_le())?;
+        // Square and multiply, from the most significant bit of the exponent.
+        let base = mul_mod(&base, &U256::ONE);
+        let mut z = mul_mod(&U256::ONE, &U256::ONE);
+        for i in (0..exp.bits()).rev() {
+            z = mul_mod(&z, &z);
+            if exp.bit_vartime(i) {
+                z = mul_mod(&z, &base);
+            }
+        }
+
+        self.store_bigint_into_guest(z_ptr, &z)?;
+
+        self.pending.cycles += self.cost_model.bigint_per_op * (2 * exp.bits() + 1);
+        self.pending.pc = self.pc + WORD_SIZE;
+
+        Ok(true)
+    }
+
+    fn load_bigint_from_guest<const L: usize>(&mut self, ptr: ByteAddr) -> Result<Uint<L>> {
+        let mut bytes = vec![0u8; Uint::<L>::BYTES];
+        for (i, chunk) in bytes.chunks_exact_mut(WORD_SIZE).enumerate() {
+            let word = self.load_u32_from_guest(ptr + (i * WORD_SIZE) as u32)?;
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        Ok(Uint::from_le_slice(&bytes))
+    }
+
+    fn store_bigint_into_guest<const L: usize>(
+        &mut self,
+        ptr: ByteAddr,
+        value: &Uint<L>,
+    ) -> Result<()> {
+        let bytes: Vec<u8> = value
+            .as_words()
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        for (i, chunk) in bytes.chunks_exact(WORD_SIZE).enumerate() {
+            let word = u32::from_le_bytes(chunk.try_into().unwrap());
+            self.store_u32_into_guest(ptr + (i * WORD_SIZE) as u32, word)?;
         }
+        Ok(())
+    }
+
+    fn ecall_burn_cycles(&mut self) -> Result<bool> {
+        let cycles = self.load_register(REG_A0)?;
+        tracing::debug!("[{}] ecall_burn_cycles({cycles})", self.insn_cycles);
+
+        self.pending.cycles += cycles as usize;
+        self.pending.pc = self.pc + WORD_SIZE;
 
-        self.pending.cycles += BIGINT_CYCLES;
+        Ok(true)
+    }
+
+    fn ecall_add_fuel(&mut self) -> Result<bool> {
+        let amount = self.load_register(REG_A0)?;
+        tracing::debug!("[{}] ecall_add_fuel({amount})", self.insn_cycles);
+
+        self.pending.fuel = amount as u64;
         self.pending.pc = self.pc + WORD_SIZE;
 
         Ok(true)
@@ -531,6 +2308,52 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.load_memory(addr.waddr())
     }
 
+    // Load consecutive words starting at the word containing `addr`, the same
+    // as loading each of them with [Self::load_u32_from_guest].
+    fn load_words_from_guest(&mut self, addr: ByteAddr, words: &mut [u32]) -> Result<()> {
+        let Some(last) = words.len().checked_sub(1) else {
+            return Ok(());
+        };
+        Self::check_guest_addr(addr)?;
+        Self::check_guest_addr(addr + last * WORD_SIZE)?;
+        let addr = addr.waddr();
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = self.pager.load(addr + i);
+        }
+        Ok(())
+    }
+
+    // Load `len` bytes starting at `addr`, a word at a time.
+    fn load_region_from_guest(&mut self, addr: ByteAddr, len: u32) -> Result<Vec<u8>> {
+        let range = Self::check_guest_range(addr.0, len)?;
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let first = ByteAddr(range.start).waddr();
+        let words = (ByteAddr(range.end - 1).waddr().0 - first.0) as usize + 1;
+        let mut region = Vec::with_capacity(words * WORD_SIZE);
+        for i in 0..words {
+            region.extend_from_slice(&self.pager.load(first + i).to_le_bytes());
+        }
+        let offset = range.start as usize % WORD_SIZE;
+        Ok(region[offset..offset + len as usize].to_vec())
+    }
+
+    // An ecall whose cost grows with its input has to fit in a single
+    // segment, so reject it before reading an input that could never be
+    // proven.
+    fn check_ecall_cycles(&self, name: &str, cycles: usize) -> Result<()> {
+        let limit = match self.segment_limit {
+            0 => 1 << MAX_CYCLES_PO2,
+            limit => limit,
+        };
+        ensure!(
+            cycles <= limit,
+            "{name}: needs {cycles} cycles, more than a segment of {limit} cycles allows"
+        );
+        Ok(())
+    }
+
     fn load_array_from_guest<const N: usize>(&mut self, addr: ByteAddr) -> Result<[u8; N]> {
         // Self::check_guest_addr_range(addr, addr + u32::try_from(N)?)?;
         Self::check_guest_addr(addr)?;
@@ -539,11 +2362,10 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
     }
 
     fn load_array<const N: usize>(&mut self, addr: ByteAddr) -> Result<[u8; N]> {
-        let mut bytes = Vec::new();
-        for i in 0..N {
-            bytes.push(self.load_u8(addr + i)?);
+        let mut ret = [0u8; N];
+        for (i, byte) in ret.iter_mut().enumerate() {
+            *byte = self.load_u8(addr + i)?;
         }
-        let ret = array::from_fn(|i| bytes[i]);
         // tracing::trace!("load_array({addr:?}) -> {ret:02x?}");
         Ok(ret)
     }
@@ -557,12 +2379,19 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn peek_string(&mut self, mut addr: ByteAddr) -> Result<String> {
         // tracing::trace!("load_string: 0x{addr:08x}");
+        let start = addr;
         let mut buf = Vec::new();
         loop {
             let bytes = self.peek_u8(addr)?;
             if bytes == 0 {
                 break;
             }
+            if buf.len() == self.max_string_len {
+                bail!(
+                    "string at {start:?} is longer than {} bytes",
+                    self.max_string_len
+                );
+            }
             buf.push(bytes);
             addr += 1u32;
         }
@@ -591,7 +2420,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn store_region(&mut self, addr: ByteAddr, slice: &[u8]) -> Result<()> {
         // tracing::trace!("store_region({addr:?}, {slice:02x?})");
-        if !self.trace.is_empty() {
+        if self.tracing() {
             self.pending.events.insert(TraceEvent::MemorySet {
                 addr: addr.0,
                 region: slice.into(),
@@ -608,19 +2437,88 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn raw_store_memory(&mut self, addr: WordAddr, data: u32) -> Result<()> {
         // tracing::trace!("store_mem({:?}, 0x{data:08x})", addr.baddr());
+        let start = addr.baddr().0;
+        let end = start + WORD_SIZE as u32;
+        if self
+            .readonly_regions
+            .iter()
+            .any(|region| start < region.end && region.start < end)
+        {
+            bail!(
+                "write to read-only region at {:?}, pc: {:?}",
+                addr.baddr(),
+                self.pc
+            );
+        }
+        if self.pending.breakpoint.is_none() {
+            self.pending.breakpoint = self
+                .memory_breakpoints
+                .iter()
+                .find(|(bp_addr, value)| ByteAddr(*bp_addr).waddr() == addr && *value == data)
+                .copied();
+        }
+        if self.watchpoints.contains(&start) {
+            self.pending.watchpoint = Some(start);
+        }
         self.pager.store(addr, data)
     }
-}
 
-impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
-    fn ecall(&mut self) -> Result<bool> {
-        match self.load_register(REG_T0)? {
+    fn dispatch_ecall(&mut self, ecall: u32) -> Result<bool> {
+        if ecall::is_executor_only(ecall) {
+            self.executor_only_ecalls.insert(ecall);
+        }
+        match ecall {
             ecall::HALT => self.ecall_halt(),
             ecall::SOFTWARE => self.ecall_software(),
             ecall::SHA => self.ecall_sha(),
-            ecall::BIGINT => self.ecall_bigint(),
-            ecall => bail!("Unknown ecall {ecall:?}"),
+            ecall::BIGINT => self.ecall_bigint(false),
+            ecall::BURN_CYCLES => self.ecall_burn_cycles(),
+            ecall::ADD_FUEL => self.ecall_add_fuel(),
+            ecall::KECCAK => self.ecall_keccak(),
+            ecall::REDUCE => self.ecall_reduce(),
+            ecall::SHA_MEM => self.ecall_sha_mem(),
+            ecall::SHA512 => self.ecall_sha512(),
+            ecall::SHA_BATCH => self.ecall_sha_batch(),
+            ecall::POSEIDON => self.ecall_poseidon(),
+            ecall::BIGINT_MODEXP => self.ecall_bigint_modexp(),
+            ecall::BIGINT_EXT => self.ecall_bigint(true),
+            ecall => bail!(ExecutorError::UnknownEcall(ecall)),
+        }
+    }
+}
+
+impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
+    fn ecall(&mut self) -> Result<bool> {
+        if self.in_guest_call {
+            bail!(
+                "ecall is not permitted during a guest call, pc: {:?}",
+                self.pc
+            );
+        }
+        let ecall = self.load_register(REG_T0)?;
+        if self.ecall_callback.is_none() {
+            return self.dispatch_ecall(ecall);
+        }
+
+        let start = Instant::now();
+        let result = self.dispatch_ecall(ecall)?;
+        // The report is made once the instruction is committed. An ecall that
+        // is replayed in a new segment after a split keeps the report of its
+        // first dispatch, which did the work of the host.
+        if self.pending.ecall.is_none() {
+            let syscall = self
+                .pending
+                .syscall
+                .as_ref()
+                .filter(|_| ecall == ecall::SOFTWARE)
+                .map(|syscall| syscall.name.clone());
+            self.pending.ecall = Some(EcallInfo {
+                ecall,
+                syscall,
+                duration: start.elapsed(),
+            });
         }
+        Ok(result)
     }
 
     fn mret(&self) -> Result<bool> {
@@ -628,9 +2526,9 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
     }
 
     fn trap(&self, cause: TrapCause) -> Result<bool> {
-        let msg = format!("Trap: {cause:08x?}, pc: {:?}", self.pc);
-        tracing::info!("{msg}");
-        bail!("{msg}");
+        let trap = Trap { cause, pc: self.pc };
+        tracing::info!("{trap}");
+        bail!(trap);
     }
 
     fn check_data_load(&self, addr: ByteAddr) -> bool {
@@ -645,13 +2543,20 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
         is_guest_memory(addr.0)
     }
 
-    fn on_insn_decoded(&self, insn: &Instruction, _decoded: &DecodedInstruction) {
+    fn on_insn_decoded(&mut self, insn: &Instruction, _decoded: &DecodedInstruction) {
         tracing::trace!("{:?}> {:?}", self.pc, insn.kind);
+        self.pending.kind = Some(insn.kind);
     }
 
     fn on_normal_end(&mut self, insn: &Instruction, decoded: &DecodedInstruction) {
         self.pending.insn = decoded.insn;
-        self.pending.cycles += insn.cycles;
+        self.pending.cycles += if self.insn_costs.is_empty() {
+            insn.cycles
+        } else {
+            self.insn_costs
+                .get(&insn.kind.major_type())
+                .map_or(insn.cycles, |&cycles| cycles as usize)
+        };
     }
 
     fn get_pc(&self) -> ByteAddr {
@@ -671,7 +2576,7 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
         if idx != 0 {
             // tracing::trace!("store_reg: x{idx} <= 0x{data:08x}");
             self.pager.store(SYSTEM_START + idx, data)?;
-            if !self.trace.is_empty() {
+            if self.tracing() {
                 self.pending
                     .events
                     .insert(TraceEvent::RegisterSet { idx, value: data });
@@ -688,7 +2593,7 @@ impl<'a, 'b, S: Syscall> EmuContext for Executor<'a, 'b, S> {
 
     fn store_memory(&mut self, addr: WordAddr, data: u32) -> Result<()> {
         // tracing::trace!("store_mem({:?}, 0x{data:08x})", addr.baddr());
-        if !self.trace.is_empty() {
+        if self.tracing() {
             self.pending.events.insert(TraceEvent::MemorySet {
                 addr: addr.baddr().0,
                 region: data.to_le_bytes().to_vec(),
@@ -703,6 +2608,11 @@ impl<'a, 'b, S: Syscall> SyscallContext for Executor<'a, 'b, S> {
         self.cycles.user
     }
 
+    fn get_segment_remaining(&self) -> usize {
+        let used = self.insn_cycles + self.pager.cycles + self.pending.cycles;
+        self.segment_limit.saturating_sub(used)
+    }
+
     fn peek_register(&mut self, idx: usize) -> Result<u32> {
         if idx >= REG_MAX {
             bail!("invalid register: x{idx}");
@@ -722,6 +2632,37 @@ impl<'a, 'b, S: Syscall> SyscallContext for Executor<'a, 'b, S> {
         let byte_offset = addr.0 as usize % WORD_SIZE;
         Ok(bytes[byte_offset])
     }
+
+    fn call_guest(&mut self, addr: ByteAddr, args: &[u32]) -> Result<u32> {
+        if self.in_guest_call {
+            bail!("nested guest calls are not supported");
+        }
+        if args.len() > REG_A7 - REG_A0 + 1 {
+            bail!(
+                "guest call supports at most 8 arguments, got {}",
+                args.len()
+            );
+        }
+        let addr = Self::check_guest_addr(addr)?;
+        tracing::debug!("call_guest({addr:?}, {args:08x?})");
+
+        // Stash the state of the in-progress instruction so that the callee
+        // runs from a clean slate, then roll everything back afterwards.
+        let checkpoint = self.pager.checkpoint();
+        let return_pc = self.pc;
+        let outer = mem::replace(&mut self.pending, PendingState::new(addr));
+        self.pc = addr;
+        self.in_guest_call = true;
+
+        let result = self.guest_call(addr, args, return_pc);
+
+        self.in_guest_call = false;
+        self.pc = return_pc;
+        self.pending = outer;
+        self.pager.undo_to(checkpoint);
+
+        result
+    }
 }
 
 #[tracing::instrument(skip_all)]
@@ -759,3 +2700,44 @@ pub fn execute_elf<S: Syscall>(
     let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
     execute(image, segment_po2, max_cycles, syscall_handler)
 }
+
+/// Returns the addresses of the words of guest memory that differ between
+/// `before` and `after`, in ascending order.
+///
+/// Pages missing from an image are treated as zeroed, and the page table is
+/// not compared, since it changes along with any page.
+///
+/// # Panics
+///
+/// Panics if the images do not share the same page size.
+pub fn diff_images(before: &MemoryImage, after: &MemoryImage) -> Vec<u32> {
+    assert_eq!(
+        before.info.page_size, after.info.page_size,
+        "images with different page sizes cannot be compared"
+    );
+    let page_size = before.info.page_size;
+    let page_idxs: BTreeSet<u32> = before
+        .pages
+        .keys()
+        .chain(after.pages.keys())
+        .copied()
+        .filter(|page_idx| {
+            page_idx * page_size < before.info.page_table_addr.min(after.info.page_table_addr)
+        })
+        .collect();
+
+    let mut addrs = Vec::new();
+    for page_idx in page_idxs {
+        let (old, new) = (before.load_page(page_idx), after.load_page(page_idx));
+        for (i, (old, new)) in old
+            .chunks_exact(WORD_SIZE)
+            .zip(new.chunks_exact(WORD_SIZE))
+            .enumerate()
+        {
+            if old != new {
+                addrs.push(page_idx * page_size + (i * WORD_SIZE) as u32);
+            }
+        }
+    }
+    addrs
+}
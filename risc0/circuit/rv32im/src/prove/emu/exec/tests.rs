@@ -15,20 +15,35 @@
 use std::cell::RefCell;
 
 use anyhow::Result;
+use crypto_bigint::{Encoding as _, NonZero, U1024, U256, U512};
 use risc0_binfmt::{Digestible, ExitCode, MemoryImage};
-use risc0_zkp::core::hash::sha::cpu::Impl as ShaImpl;
+use risc0_core::field::baby_bear::BabyBearElem;
+use risc0_zkp::core::hash::{poseidon::unpadded_hash, sha::cpu::Impl as ShaImpl};
 use risc0_zkvm_platform::{
-    syscall::reg_abi::{REG_A4, REG_A5},
+    memory::GUEST_MAX_MEM,
+    syscall::{
+        bigint, ecall, poseidon, reduce,
+        reg_abi::{REG_A0, REG_A4, REG_A5, REG_MAX, REG_S0, REG_S1},
+        sha512,
+    },
     PAGE_SIZE,
 };
+use sha2::{Digest as _, Sha256, Sha512};
 use test_log::test;
 
-use super::{Syscall, SyscallContext};
+use super::{
+    diff_images, Executor, ExecutorError, Fault, FaultCause, SimpleSession, StepDelta, Syscall,
+    SyscallContext, Unprovable,
+};
 use crate::prove::emu::{
     addr::ByteAddr,
-    exec::DEFAULT_SEGMENT_LIMIT_PO2,
+    exec::{DEFAULT_MAX_STRING_LEN, DEFAULT_SEGMENT_LIMIT_PO2},
+    rv32im::InsnKind,
     testutil::{self, DEFAULT_SESSION_LIMIT},
+    BIGINT_ADD_CYCLES, BIGINT_CYCLES, BIGINT_SUB_CYCLES, KECCAK_CYCLES, POSEIDON_CYCLES,
+    REDUCE_CYCLES, SHA512_BLOCK_CYCLES, SHA_BLOCK_CYCLES,
 };
+use crate::trace::TraceEvent;
 
 #[derive(Default, Clone)]
 struct BasicSyscallState {
@@ -122,3 +137,1302 @@ fn system_split() {
         segments[0].post_state.digest::<ShaImpl>()
     );
 }
+
+#[test]
+fn executor_errors() {
+    let run = |program| {
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let err = super::execute(
+            image,
+            DEFAULT_SEGMENT_LIMIT_PO2,
+            DEFAULT_SESSION_LIMIT,
+            &BasicSyscall::default(),
+        )
+        .err()
+        .unwrap();
+        err.downcast_ref::<ExecutorError>().cloned()
+    };
+
+    assert_eq!(
+        run(testutil::raw_ecall(0x7ff, 0)),
+        Some(ExecutorError::UnknownEcall(0x7ff))
+    );
+    assert_eq!(
+        run(testutil::raw_ecall(ecall::HALT, 2)),
+        Some(ExecutorError::IllegalHaltType(2))
+    );
+
+    // A single instruction that needs more cycles than a segment has can't be
+    // split off into a segment of its own.
+    let image = MemoryImage::new(&testutil::burn_cycles(1 << 15, 1), PAGE_SIZE as u32).unwrap();
+    let err = super::execute(image, 14, DEFAULT_SESSION_LIMIT, &BasicSyscall::default())
+        .err()
+        .unwrap();
+    let Some(&ExecutorError::SegmentOverflow { index, cycles, po2 }) =
+        err.downcast_ref::<ExecutorError>()
+    else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!((index, po2), (1, 14));
+    assert!(cycles > 1 << 15);
+    assert!(err
+        .to_string()
+        .contains("more than the segment limit of 2^14"));
+
+    let image = MemoryImage::new(&testutil::simple_loop(), PAGE_SIZE as u32).unwrap();
+    let err = super::execute(image, 14, Some(1 << 10), &BasicSyscall::default())
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.downcast_ref::<ExecutorError>(),
+        Some(&ExecutorError::SessionLimitExceeded)
+    );
+}
+
+#[test]
+fn burn_cycles() {
+    let run = |cycles, count, po2| {
+        let program = testutil::burn_cycles(cycles, count);
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        super::execute(image, po2, DEFAULT_SESSION_LIMIT, &BasicSyscall::default()).unwrap()
+    };
+
+    let baseline = run(0, 1, DEFAULT_SEGMENT_LIMIT_PO2);
+    let burned = run(1000, 1, DEFAULT_SEGMENT_LIMIT_PO2);
+    assert_eq!(burned.segments.len(), 1);
+    assert_eq!(burned.result.exit_code, ExitCode::Halted(0));
+    assert_eq!(
+        burned.result.user_cycles - baseline.result.user_cycles,
+        1000
+    );
+
+    // Burned cycles count toward the segment limit just like real ones.
+    let baseline = run(0, 4, 14);
+    let burned = run(4000, 4, 14);
+    assert_eq!(baseline.segments.len(), 1);
+    assert!(burned.segments.len() > 1);
+    assert_eq!(burned.segments[0].exit_code, ExitCode::SystemSplit);
+    assert_eq!(burned.result.exit_code, ExitCode::Halted(0));
+    assert_eq!(
+        burned.result.user_cycles - baseline.result.user_cycles,
+        16000
+    );
+}
+
+#[test]
+fn opcode_counts() {
+    let count = 4;
+    let program = testutil::burn_cycles(4000, count);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let session =
+        super::execute(image, 14, DEFAULT_SESSION_LIMIT, &BasicSyscall::default()).unwrap();
+    assert!(session.segments.len() > 1);
+
+    // Every BURN_CYCLES ecall plus the final halt, counted across all segments.
+    let counts = &session.result.opcode_counts;
+    assert_eq!(counts[&InsnKind::EANY], count as u64 + 1);
+    assert_eq!(counts[&InsnKind::LUI], count as u64 + 1);
+    assert_eq!(counts[&InsnKind::ADDI], 2 * count as u64 + 2);
+    assert_eq!(counts.len(), 3);
+}
+
+#[test]
+fn fault() {
+    let run = |program| {
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+        let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()));
+        result.is_err().then(|| exec.fault().unwrap())
+    };
+
+    assert_eq!(run(testutil::basic()), None);
+    assert_eq!(
+        run(testutil::single_insn(0x00000000)),
+        Some(Fault {
+            pc: 0x4000,
+            cause: FaultCause::DecodeError
+        })
+    );
+    assert_eq!(
+        run(testutil::raw_ecall(0x7ff, 0)),
+        Some(Fault {
+            pc: 0x400c,
+            cause: FaultCause::UnknownEcall(0x7ff)
+        })
+    );
+    // sw x0, 0(x0)
+    assert_eq!(
+        run(testutil::single_insn(0x00002023)),
+        Some(Fault {
+            pc: 0x4000,
+            cause: FaultCause::MemoryError(0)
+        })
+    );
+    assert_eq!(
+        run(testutil::raw_ecall(ecall::HALT, 2)),
+        Some(Fault {
+            pc: 0x400c,
+            cause: FaultCause::StepError
+        })
+    );
+}
+
+#[test]
+fn decode_cache() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+
+    // Each of the 6 instructions is decoded once, however often it runs.
+    let insns: u64 = result.opcode_counts.values().sum();
+    assert!(insns > 2000);
+    assert_eq!(exec.emu.as_ref().unwrap().decodes(), 6);
+}
+
+#[test]
+fn decode_cache_self_modifying() {
+    let program = testutil::self_modifying();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+
+    // The patched instruction is decoded afresh, on top of the 12 distinct
+    // instructions.
+    assert_eq!(exec.get_register(REG_S1).unwrap(), 2);
+    assert_eq!(exec.emu.as_ref().unwrap().decodes(), 13);
+}
+
+#[test]
+fn decode_cache_kept_on_error() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, Some(100), |_| Ok(()))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ExecutorError>(),
+        Some(&ExecutorError::SessionLimitExceeded)
+    );
+
+    // The emulator, and the instructions it decoded, outlive the failed run.
+    assert!(exec.emu.as_ref().unwrap().decodes() > 0);
+}
+
+#[test]
+fn registers() {
+    let program = testutil::pause_resume();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Paused(0));
+    assert_eq!(exec.pc(), 0x4018);
+    let sum = 0x1234b000_u32.wrapping_add(0xf387e000);
+    assert_eq!(exec.get_register(1).unwrap(), sum);
+    assert_eq!(exec.get_register(2).unwrap(), 0x1234b000);
+    assert_eq!(exec.get_register(REG_A0).unwrap(), 1);
+
+    // Patch a register while paused and observe its effect after resuming.
+    exec.set_register(2, 5).unwrap();
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(exec.get_register(4).unwrap(), sum.wrapping_add(5));
+
+    assert!(exec.get_register(REG_MAX).is_err());
+    assert!(exec.set_register(REG_MAX, 0).is_err());
+    assert!(exec.set_register(0, 1).is_err());
+}
+
+#[test]
+fn memory() {
+    let program = testutil::pause_resume();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+
+    // The program text is visible in memory.
+    assert_eq!(
+        exec.read_memory(0x4000, 4).unwrap(),
+        0x1234b137_u32.to_le_bytes()
+    );
+
+    // Write an unaligned pattern that straddles a page boundary.
+    let addr = 0x10000 + PAGE_SIZE as u32 - 3;
+    let pattern: Vec<u8> = (1..=10).collect();
+    exec.write_memory(addr, &pattern).unwrap();
+    assert_eq!(exec.read_memory(addr, 10).unwrap(), pattern);
+    assert_eq!(exec.read_memory(addr - 1, 1).unwrap(), [0]);
+    assert_eq!(exec.read_memory(addr + 10, 1).unwrap(), [0]);
+    assert!(exec.read_memory(addr, 0).unwrap().is_empty());
+
+    // Hashing each touched page once leaves the same merkle tree as hashing
+    // every page afresh.
+    let mut rehashed = exec.pager.image.clone();
+    rehashed.hash_pages();
+    assert_eq!(
+        exec.pager.image.compute_root_hash(),
+        rehashed.compute_root_hash()
+    );
+
+    // Patched memory is seen by the guest once it runs.
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Paused(0));
+    assert_eq!(exec.read_memory(addr, 10).unwrap(), pattern);
+
+    assert!(exec.read_memory(0, 4).is_err());
+    assert!(exec.read_memory(GUEST_MAX_MEM as u32 - 2, 4).is_err());
+    assert!(exec.read_memory(u32::MAX, 2).is_err());
+    assert!(exec.write_memory(GUEST_MAX_MEM as u32, &[0]).is_err());
+}
+
+#[test]
+fn memory_snapshot() {
+    let image = MemoryImage::new(&testutil::simple_loop(), PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+
+    let before = exec.memory_snapshot();
+    assert!(diff_images(&before, &exec.memory_snapshot()).is_empty());
+
+    exec.write_memory(0x10000, &0xdeadbeef_u32.to_le_bytes())
+        .unwrap();
+    let after = exec.memory_snapshot();
+    assert_eq!(diff_images(&before, &after), [0x10000]);
+    assert_eq!(diff_images(&after, &before), [0x10000]);
+    assert_ne!(before.compute_root_hash(), after.compute_root_hash());
+}
+
+#[test]
+fn step_detailed() {
+    let addr = testutil::STORE_SEQUENCE_ADDR;
+    let program = testutil::store_sequence();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+
+    exec.step_detailed().unwrap(); // lui t1, 0x8
+    assert_eq!(
+        exec.step_detailed().unwrap(),
+        StepDelta {
+            pc: 0x4004,
+            next_pc: 0x4008,
+            kind: InsnKind::ADDI,
+            insn: 0x00100393,
+            register_writes: vec![(7, 1)],
+            memory_writes: vec![],
+            exit_code: None,
+        }
+    );
+    assert_eq!(
+        exec.step_detailed().unwrap(),
+        StepDelta {
+            pc: 0x4008,
+            next_pc: 0x400c,
+            kind: InsnKind::SW,
+            insn: 0x00732023,
+            register_writes: vec![],
+            memory_writes: vec![TraceEvent::MemorySet {
+                addr,
+                region: 1_u32.to_le_bytes().to_vec(),
+            }],
+            exit_code: None,
+        }
+    );
+    assert_eq!(exec.read_memory(addr, 4).unwrap(), 1_u32.to_le_bytes());
+
+    // A run continues from the stepped state.
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(exec.read_memory(addr, 4).unwrap(), 3_u32.to_le_bytes());
+    assert!(exec.step_detailed().is_err());
+}
+
+#[test]
+fn memory_breakpoint() {
+    let addr = testutil::STORE_SEQUENCE_ADDR;
+    let program = testutil::store_sequence();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.pause_on_memory_value(addr + 4, 2)
+        .pause_on_memory_value(addr, 2);
+
+    // Pause right after the second store.
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Paused(0));
+    assert_eq!(result.memory_breakpoint, Some((addr, 2)));
+    assert!(result.unprovable.contains(&Unprovable::HostPause));
+    assert_eq!(exec.pc(), 0x4014);
+    assert_eq!(exec.read_memory(addr, 4).unwrap(), 2_u32.to_le_bytes());
+
+    // The breakpoint only fires once.
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(result.memory_breakpoint, None);
+    assert!(result.unprovable.is_empty());
+    assert_eq!(exec.read_memory(addr, 4).unwrap(), 3_u32.to_le_bytes());
+}
+
+#[test]
+fn watchpoint() {
+    let addr = testutil::STORE_SEQUENCE_ADDR;
+    let program = testutil::store_sequence();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.add_watchpoint(addr + 2);
+
+    // Each store pauses at the instruction that follows it, with the new
+    // value already visible.
+    for (value, pc) in [(1_u32, 0x400c), (2, 0x4014), (3, 0x401c)] {
+        let result = exec
+            .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap();
+        assert_eq!(result.exit_code, ExitCode::Paused(0));
+        assert_eq!(result.watchpoint, Some(addr));
+        assert!(result.unprovable.contains(&Unprovable::HostPause));
+        assert_eq!(exec.pc(), pc);
+        assert_eq!(exec.read_memory(addr, 4).unwrap(), value.to_le_bytes());
+    }
+
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(result.watchpoint, None);
+}
+
+#[test]
+fn breakpoint() {
+    const LOOP_PC: u32 = 0x4008;
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.add_breakpoint(LOOP_PC);
+
+    // The loop body is reached once per iteration, with a4 counting them.
+    for i in 0..10 {
+        let result = exec
+            .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap();
+        assert_eq!(result.exit_code, ExitCode::Paused(0));
+        assert_eq!(result.breakpoint, Some(LOOP_PC));
+        assert!(result.unprovable.contains(&Unprovable::HostPause));
+        assert_eq!(exec.pc(), LOOP_PC);
+        assert_eq!(exec.get_register(REG_A4).unwrap(), i);
+    }
+
+    exec.remove_breakpoint(LOOP_PC);
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(result.breakpoint, None);
+    assert_eq!(exec.get_register(REG_A4).unwrap(), 1000);
+}
+
+#[test]
+fn total_insns() {
+    let run = |split_every_insns| {
+        let program = testutil::simple_loop();
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+        if let Some(insns) = split_every_insns {
+            exec.split_every_insns(insns);
+        }
+        exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap()
+    };
+
+    // Two instructions of setup, two per iteration of the loop, and two to
+    // halt.
+    const EXPECTED: u64 = 2 + 2 * 1000 + 2;
+    let result = run(None);
+    assert_eq!(result.segments, 1);
+    assert_eq!(result.total_insns, EXPECTED);
+
+    // Instructions replayed after a split are not counted twice.
+    let result = run(Some(100));
+    assert!(result.segments > 1);
+    assert_eq!(result.total_insns, EXPECTED);
+}
+
+#[test]
+fn disassemble() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+
+    // Decoding stops at the zeroed word after the program.
+    assert_eq!(
+        exec.disassemble(program.entry, 10),
+        vec![
+            (0x4000, InsnKind::ADDI),
+            (0x4004, InsnKind::ADDI),
+            (0x4008, InsnKind::ADDI),
+            (0x400c, InsnKind::BLT),
+            (0x4010, InsnKind::LUI),
+            (0x4014, InsnKind::EANY),
+        ]
+    );
+    assert_eq!(exec.disassemble(0x400c, 1), vec![(0x400c, InsnKind::BLT)]);
+    assert_eq!(exec.pc(), program.entry);
+
+    let result = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert_eq!(exec.get_register(REG_A4).unwrap(), 1000);
+}
+
+#[test]
+fn fuel() {
+    let run = |fuel| {
+        let program = testutil::fuel_refill();
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+        exec.initial_fuel(fuel);
+        let result = exec
+            .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap();
+        (result.exit_code, exec.pc())
+    };
+
+    // Not enough fuel to reach the refill.
+    assert_eq!(run(20), (ExitCode::OutOfFuel, 0x4008));
+
+    // The refill is capped at the initial fuel, which is just enough to
+    // complete the second half.
+    assert_eq!(run(26).0, ExitCode::Halted(0));
+
+    // Without the cap, the refill would have been enough to complete.
+    assert_eq!(run(25), (ExitCode::OutOfFuel, 0x4034));
+}
+
+#[test]
+fn fuel_limit() {
+    let run = |limit| {
+        let program = testutil::fuel_refill();
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+        exec.fuel_limit(limit);
+        let result = exec
+            .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap();
+        (result.exit_code, result.total_insns)
+    };
+
+    // The refill does not extend the limit, so execution stops right at it.
+    assert_eq!(run(40), (ExitCode::FuelExhausted, 40));
+
+    // Halting with the last unit of fuel still halts.
+    assert_eq!(run(50), (ExitCode::Halted(0), 50));
+}
+
+fn run_bigint(ecall: u32, op: u32, x: U256, y: U256, n: U256) -> Result<(U256, u64)> {
+    let program = testutil::bigint(ecall, op);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::BIGINT_X, &x.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_Y, &y.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_N, &n.to_le_bytes())?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let z = exec.read_memory(testutil::BIGINT_Z, bigint::WIDTH_BYTES as u32)?;
+    Ok((U256::from_le_slice(&z), result.user_cycles))
+}
+
+#[test]
+fn bigint_ops() {
+    let run = |op, x, y, n| run_bigint(ecall::BIGINT_EXT, op, x, y, n).unwrap().0;
+
+    // Carry across the 256-bit boundary.
+    assert_eq!(
+        run(
+            bigint::OP_ADD,
+            U256::MAX.wrapping_sub(&U256::ONE),
+            U256::from_u8(5),
+            U256::MAX
+        ),
+        U256::from_u8(4)
+    );
+    assert_eq!(
+        run(bigint::OP_ADD, U256::MAX, U256::from_u8(2), U256::ZERO),
+        U256::ONE
+    );
+
+    // Borrow across the 256-bit boundary.
+    assert_eq!(
+        run(bigint::OP_SUB, U256::ONE, U256::from_u8(3), U256::MAX),
+        U256::MAX.wrapping_sub(&U256::from_u8(2))
+    );
+    assert_eq!(
+        run(bigint::OP_SUB, U256::ONE, U256::from_u8(3), U256::ZERO),
+        U256::MAX.wrapping_sub(&U256::ONE)
+    );
+
+    // Inputs are reduced before the operation.
+    assert_eq!(
+        run(
+            bigint::OP_SUB,
+            U256::from_u8(3),
+            U256::from_u8(15),
+            U256::from_u8(7)
+        ),
+        U256::from_u8(2)
+    );
+
+    // Multiplication is the same under both ecalls.
+    let (three, five, seven) = (U256::from_u8(3), U256::from_u8(5), U256::from_u8(7));
+    assert_eq!(
+        run_bigint(ecall::BIGINT, bigint::OP_MULTIPLY, three, five, seven).unwrap(),
+        run_bigint(ecall::BIGINT_EXT, bigint::OP_MULTIPLY, three, five, seven).unwrap()
+    );
+    assert_eq!(run(bigint::OP_MULTIPLY, three, five, seven), U256::ONE);
+
+    // Each op charges its own cycles.
+    let cycles = |op| {
+        run_bigint(ecall::BIGINT_EXT, op, U256::ONE, U256::ONE, U256::ZERO)
+            .unwrap()
+            .1
+    };
+    let base = cycles(bigint::OP_MULTIPLY) - BIGINT_CYCLES as u64;
+    assert_eq!(cycles(bigint::OP_ADD), base + BIGINT_ADD_CYCLES as u64);
+    assert_eq!(cycles(bigint::OP_SUB), base + BIGINT_SUB_CYCLES as u64);
+
+    // The provable BIGINT ecall only multiplies.
+    for op in [bigint::OP_ADD, bigint::OP_SUB] {
+        let err = run_bigint(ecall::BIGINT, op, U256::ONE, U256::ONE, U256::ZERO).unwrap_err();
+        assert!(err.to_string().contains("requires the BIGINT_EXT ecall"));
+    }
+
+    assert!(run_bigint(ecall::BIGINT_EXT, 3, U256::ONE, U256::ONE, U256::ZERO).is_err());
+}
+
+fn run_bigint_512(ecall: u32, op: u32, x: U512, y: U512, n: U512) -> Result<(U512, u64)> {
+    let program = testutil::bigint(ecall, op | bigint::WIDTH_512);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::BIGINT_X, &x.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_Y, &y.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_N, &n.to_le_bytes())?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let z = exec.read_memory(testutil::BIGINT_Z, U512::BYTES as u32)?;
+    Ok((U512::from_le_slice(&z), result.user_cycles))
+}
+
+#[test]
+fn bigint_wide() {
+    // A 512-bit modular multiplication, with a product of nearly 1024 bits.
+    let x = U512::MAX.wrapping_sub(&U512::from_u8(12));
+    let y = U512::MAX.shr_vartime(1);
+    let n = U512::MAX.wrapping_sub(&U512::from_u8(68));
+    let (z, cycles) = run_bigint_512(ecall::BIGINT_EXT, bigint::OP_MULTIPLY, x, y, n).unwrap();
+    let (w_lo, w_hi) = x.mul_wide(&y);
+    let expected = w_hi
+        .concat(&w_lo)
+        .rem(&NonZero::new(n.resize::<{ U1024::LIMBS }>()).unwrap())
+        .resize();
+    assert_eq!(z, expected);
+
+    // The 256-bit path is unchanged by an explicit width, and a 512-bit
+    // multiplication is charged for each pair of 256-bit chunks.
+    let run_256 = |op| run_bigint(ecall::BIGINT, op, U256::ONE, U256::ONE, U256::ZERO).unwrap();
+    let (z_256, cycles_256) = run_256(bigint::OP_MULTIPLY);
+    assert_eq!(
+        run_256(bigint::OP_MULTIPLY | bigint::WIDTH_256),
+        (z_256, cycles_256)
+    );
+    assert_eq!(z_256, U256::ONE);
+    let base = cycles_256 - BIGINT_CYCLES as u64;
+    assert_eq!(cycles, base + 4 * BIGINT_CYCLES as u64);
+
+    // Addition and subtraction at 512 bits carry and borrow across the
+    // 256-bit boundary.
+    let lo: U512 = U256::MAX.resize();
+    assert_eq!(
+        run_bigint_512(ecall::BIGINT_EXT, bigint::OP_ADD, lo, U512::ONE, U512::ZERO)
+            .unwrap()
+            .0,
+        U512::ONE.shl_vartime(256)
+    );
+    assert_eq!(
+        run_bigint_512(
+            ecall::BIGINT_EXT,
+            bigint::OP_SUB,
+            U512::ONE.shl_vartime(256),
+            U512::ONE,
+            U512::ZERO
+        )
+        .unwrap()
+        .0,
+        lo
+    );
+
+    // The provable BIGINT ecall only accepts 256-bit operands.
+    let err = run_bigint_512(ecall::BIGINT, bigint::OP_MULTIPLY, x, y, n).unwrap_err();
+    assert!(err.to_string().contains("requires the BIGINT_EXT ecall"));
+
+    assert!(run_bigint(ecall::BIGINT_EXT, 6 << 8, U256::ONE, U256::ONE, U256::ZERO).is_err());
+}
+
+fn run_bigint_modexp(base: U256, exp: U256, n: U256) -> Result<(U256, u64)> {
+    let program = testutil::bigint_modexp();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::BIGINT_X, &base.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_Y, &exp.to_le_bytes())?;
+    exec.write_memory(testutil::BIGINT_N, &n.to_le_bytes())?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let z = exec.read_memory(testutil::BIGINT_Z, bigint::WIDTH_BYTES as u32)?;
+    Ok((U256::from_le_slice(&z), result.user_cycles))
+}
+
+#[test]
+fn bigint_modexp() {
+    let run = |base, exp, n| {
+        run_bigint_modexp(U256::from_u32(base), U256::from_u32(exp), U256::from_u32(n))
+            .unwrap()
+            .0
+    };
+
+    assert_eq!(run(4, 13, 497), U256::from_u32(445));
+    assert_eq!(run(12345, 1, 100), U256::from_u32(45));
+    assert_eq!(run(12345, 0, 100), U256::ONE);
+    assert_eq!(run(12345, 0, 1), U256::ZERO);
+    assert_eq!(run(0, 0, 7), U256::ONE);
+
+    // Fermat's little theorem, with the 255-bit prime 2^255 - 19.
+    let p = U256::from_be_hex("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed");
+    let exp = p.wrapping_sub(&U256::ONE);
+    assert_eq!(
+        run_bigint_modexp(U256::from_u8(3), exp, p).unwrap().0,
+        U256::ONE
+    );
+    assert_eq!(
+        run_bigint_modexp(U256::MAX, U256::ONE, p).unwrap().0,
+        U256::MAX.rem(&NonZero::new(p).unwrap())
+    );
+
+    // Each bit of the exponent is charged a squaring and a multiplication.
+    let cycles = |exp| {
+        run_bigint_modexp(U256::ONE, U256::from_u32(exp), U256::MAX)
+            .unwrap()
+            .1
+    };
+    assert_eq!(cycles(0b101), cycles(0) + 6 * BIGINT_CYCLES as u64);
+
+    let err = run_bigint_modexp(U256::ONE, U256::ONE, U256::ZERO).unwrap_err();
+    assert!(err.to_string().contains("modulus is zero"), "{err}");
+}
+
+fn run_keccak(data: &[u8]) -> Result<(Vec<u8>, u64)> {
+    let program = testutil::keccak(data.len() as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::KECCAK_DATA, data)?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let digest = exec.read_memory(testutil::KECCAK_OUT, 32)?;
+    Ok((digest, result.user_cycles))
+}
+
+#[test]
+fn keccak() {
+    let vectors: [(&[u8], &str); 3] = [
+        (
+            b"",
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        ),
+        (
+            b"abc",
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        ),
+        (
+            b"The quick brown fox jumps over the lazy dog",
+            "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15",
+        ),
+    ];
+    for (data, expected) in vectors {
+        let (digest, _) = run_keccak(data).unwrap();
+        let digest: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(digest, expected);
+    }
+
+    // Cycles are charged per permutation, and the padding of a full block
+    // requires an extra permutation.
+    let (_, one_block) = run_keccak(&[0xab; 1]).unwrap();
+    let (_, two_blocks) = run_keccak(&[0xab; 136]).unwrap();
+    assert_eq!(two_blocks - one_block, KECCAK_CYCLES as u64);
+}
+
+#[test]
+fn keccak_len_overflow() {
+    let program = testutil::keccak(u32::MAX);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap_err();
+    assert!(err.to_string().contains("ecall_keccak"), "{err}");
+}
+
+// Hash a message that pads out to exactly `calls` calls of the SHA ecall,
+// returning the message, its digest, and the user cycles.
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+fn run_sha(calls: u32) -> Result<(Vec<u8>, Vec<u8>, u64)> {
+    // Leave room in the last block for the 0x80 byte and the length.
+    let len = (calls * testutil::SHA_BLOCKS_PER_CALL) as usize * 64 - 9;
+    let data: Vec<u8> = (0..len).map(|i| (i * 31 + 7) as u8).collect();
+    let mut blocks = data.clone();
+    blocks.push(0x80);
+    blocks.extend_from_slice(&(len as u64 * 8).to_be_bytes());
+
+    let program = testutil::sha(calls);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let state: Vec<u8> = SHA256_IV
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect();
+    exec.write_memory(testutil::SHA_STATE_IN, &state)?;
+    exec.write_memory(testutil::SHA_BLOCKS, &blocks)?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let digest = exec.read_memory(testutil::SHA_STATE_OUT, 32)?;
+    Ok((data, digest, result.user_cycles))
+}
+
+#[test]
+fn sha() {
+    for calls in [1, 2, 3] {
+        let (data, digest, _) = run_sha(calls).unwrap();
+        assert_eq!(digest, Sha256::digest(&data).as_slice(), "calls: {calls}");
+    }
+
+    // Each call is charged the same number of cycles, which covers the blocks
+    // it compresses.
+    let cycles: Vec<u64> = [1, 2, 3]
+        .into_iter()
+        .map(|calls| run_sha(calls).unwrap().2)
+        .collect();
+    assert_eq!(cycles[2] - cycles[1], cycles[1] - cycles[0]);
+    assert!(
+        cycles[1] - cycles[0] > (testutil::SHA_BLOCKS_PER_CALL as usize * SHA_BLOCK_CYCLES) as u64
+    );
+}
+
+#[test]
+fn sha_large() {
+    // About 2 MB, spread over many segments.
+    let (data, digest, _) = run_sha(2000).unwrap();
+    assert!(data.len() > 2_000_000);
+    assert_eq!(digest, Sha256::digest(&data).as_slice());
+}
+
+// Hash each of `messages` with a single SHA_BATCH ecall, returning the
+// digests and the user cycles.
+fn run_sha_batch(messages: &[&[u8]]) -> Result<(Vec<Vec<u8>>, u64)> {
+    const STATE_IN: u32 = 0x9000;
+    const STATE_OUT: u32 = 0xa000;
+    const BLOCKS: u32 = 0x10000;
+
+    let program = testutil::sha_batch(messages.len() as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let state: Vec<u8> = SHA256_IV
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect();
+    exec.write_memory(STATE_IN, &state)?;
+
+    let mut descriptors = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        let blocks = sha_pad(message);
+        let blocks_ptr = BLOCKS + i as u32 * PAGE_SIZE as u32;
+        exec.write_memory(blocks_ptr, &blocks)?;
+        descriptors.extend([
+            STATE_OUT + i as u32 * 32,
+            STATE_IN,
+            blocks_ptr,
+            (blocks.len() / 64) as u32,
+        ]);
+    }
+    exec.write_memory(
+        testutil::SHA_BATCH_DESCRIPTORS,
+        bytemuck::cast_slice(&descriptors),
+    )?;
+
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let digests = (0..messages.len() as u32)
+        .map(|i| exec.read_memory(STATE_OUT + i * 32, 32))
+        .collect::<Result<_>>()?;
+    Ok((digests, result.user_cycles))
+}
+
+// Pad `message` to a whole number of SHA-256 blocks.
+fn sha_pad(message: &[u8]) -> Vec<u8> {
+    let len = (message.len() + 9).next_multiple_of(64);
+    let mut blocks = message.to_vec();
+    blocks.push(0x80);
+    blocks.resize(len - 8, 0);
+    blocks.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    blocks
+}
+
+#[test]
+fn sha_batch() {
+    let messages: [&[u8]; 3] = [b"", b"abc", &[0x5a; 100]];
+    let (digests, cycles) = run_sha_batch(&messages).unwrap();
+    for (message, digest) in messages.iter().zip(digests) {
+        assert_eq!(digest, Sha256::digest(message).as_slice());
+    }
+
+    // Cycles are charged per block across all descriptors, of which the last
+    // two messages have three.
+    let (_, first_only) = run_sha_batch(&messages[..1]).unwrap();
+    assert_eq!(cycles - first_only, 3 * SHA_BLOCK_CYCLES as u64);
+}
+
+#[test]
+fn sha_invalid_blocks() {
+    let run = |blocks_ptr: u32, count: u32| {
+        let program = testutil::sha_batch(1);
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+        let descriptor = [0x9000, 0xa000, blocks_ptr, count];
+        exec.write_memory(
+            testutil::SHA_BATCH_DESCRIPTORS,
+            bytemuck::cast_slice(&descriptor),
+        )
+        .unwrap();
+        let err = exec
+            .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .unwrap_err();
+        assert_eq!(exec.fault().unwrap().cause, FaultCause::StepError);
+        err.to_string()
+    };
+
+    let err = run(0x10001, 1);
+    assert!(err.contains("unaligned block pointer"), "{err}");
+
+    // The first block is in guest memory, but the second is not.
+    let err = run(GUEST_MAX_MEM as u32 - 64, 2);
+    assert!(err.contains("extend past guest memory"), "{err}");
+}
+
+fn run_poseidon(elems: &[u32]) -> Result<(Vec<u32>, u64)> {
+    let program = testutil::poseidon(elems.len() as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::POSEIDON_IN, bytemuck::cast_slice(elems))?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let out = exec.read_memory(testutil::POSEIDON_OUT, poseidon::OUT_WORDS as u32 * 4)?;
+    Ok((bytemuck::pod_collect_to_vec(&out), result.user_cycles))
+}
+
+#[test]
+fn poseidon() {
+    // A single permutation of 0..16, from the risc0_zkp test vectors.
+    let elems: Vec<u32> = (0..poseidon::RATE as u32).collect();
+    let (out, one_chunk) = run_poseidon(&elems).unwrap();
+    assert_eq!(
+        out,
+        [
+            165799421, 446443103, 1242624592, 791266679, 1939888497, 1437820613, 893076101,
+            95764709
+        ]
+    );
+
+    for len in [0, 1, 17, 40] {
+        let elems: Vec<u32> = (0..len).map(|i| i * 7919 + 3).collect();
+        let (out, _) = run_poseidon(&elems).unwrap();
+        let expected = unpadded_hash(
+            elems
+                .iter()
+                .map(|&x| BabyBearElem::new(x))
+                .collect::<Vec<_>>()
+                .iter(),
+        );
+        assert_eq!(out, expected.map(|elem| elem.as_u32()), "len: {len}");
+    }
+
+    // Cycles are charged per permutation.
+    let (_, two_chunks) = run_poseidon(&[1; poseidon::RATE + 1]).unwrap();
+    assert_eq!(two_chunks - one_chunk, POSEIDON_CYCLES as u64);
+
+    let err = run_poseidon(&[poseidon::MODULUS]).err().unwrap();
+    assert!(err.to_string().contains("element out of range"), "{err}");
+}
+
+#[test]
+fn poseidon_count_overflow() {
+    let program = testutil::poseidon(u32::MAX);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap_err();
+    assert!(err.to_string().contains("too many elements"), "{err}");
+}
+
+fn run_sha_mem(data: &[u8]) -> Result<(Vec<u8>, u64)> {
+    let program = testutil::sha_mem(data.len() as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    exec.write_memory(testutil::SHA_MEM_DATA, data)?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let digest = exec.read_memory(testutil::SHA_MEM_OUT, 32)?;
+    Ok((digest, result.user_cycles))
+}
+
+#[test]
+fn sha_mem() {
+    // Include lengths on either side of the padding boundaries, where the
+    // padding spills into an extra block.
+    for len in [0, 1, 3, 55, 56, 63, 64, 119, 120, 200, 1000] {
+        let data: Vec<u8> = (0..len).map(|i| (i * 31 + 7) as u8).collect();
+        let (digest, _) = run_sha_mem(&data).unwrap();
+        assert_eq!(digest, Sha256::digest(&data).as_slice(), "len: {len}");
+    }
+
+    // Cycles are charged per padded block, like the SHA ecall.
+    let (_, one_block) = run_sha_mem(&[0xab; 55]).unwrap();
+    let (_, two_blocks) = run_sha_mem(&[0xab; 56]).unwrap();
+    let (_, three_blocks) = run_sha_mem(&[0xab; 120]).unwrap();
+    assert_eq!(two_blocks - one_block, SHA_BLOCK_CYCLES as u64);
+    assert_eq!(three_blocks - two_blocks, SHA_BLOCK_CYCLES as u64);
+}
+
+#[test]
+fn sha_mem_len_overflow() {
+    let program = testutil::sha_mem(u32::MAX);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap_err();
+    assert!(err.to_string().contains("ecall_sha_mem"), "{err}");
+}
+
+// Hash `data` with the SHA512 ecall, padding it on the host.
+fn run_sha512(data: &[u8]) -> Result<(Vec<u8>, u64)> {
+    const IV: [u64; sha512::STATE_WORDS] = [
+        0x6a09e667f3bcc908,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ];
+
+    // Append a 1 bit, zeros, and the 128-bit length in bits.
+    let mut blocks = data.to_vec();
+    blocks.push(0x80);
+    while blocks.len() % sha512::BLOCK_BYTES != sha512::BLOCK_BYTES - 16 {
+        blocks.push(0);
+    }
+    blocks.extend_from_slice(&(data.len() as u128 * 8).to_be_bytes());
+    let count = blocks.len() / sha512::BLOCK_BYTES;
+
+    let program = testutil::sha512(count as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let state: Vec<u8> = IV.iter().flat_map(|word| word.to_be_bytes()).collect();
+    exec.write_memory(testutil::SHA512_STATE_IN, &state)?;
+    exec.write_memory(testutil::SHA512_BLOCKS, &blocks)?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let digest = exec.read_memory(testutil::SHA512_STATE_OUT, sha512::STATE_BYTES as u32)?;
+    Ok((digest, result.user_cycles))
+}
+
+#[test]
+fn sha512() {
+    let vectors: [(&[u8], &str); 3] = [
+        (
+            b"",
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+             47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+        ),
+        (
+            b"abc",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ),
+        (
+            // Spans two blocks once padded.
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+              hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+        ),
+    ];
+    for (data, expected) in vectors {
+        let (digest, _) = run_sha512(data).unwrap();
+        let digest: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(digest, expected);
+    }
+
+    let data: Vec<u8> = (0..1000).map(|i| (i * 31 + 7) as u8).collect();
+    let (digest, _) = run_sha512(&data).unwrap();
+    assert_eq!(digest, Sha512::digest(&data).as_slice());
+
+    // Cycles are charged per 1024-bit block.
+    let (_, one_block) = run_sha512(&[0xab; 111]).unwrap();
+    let (_, two_blocks) = run_sha512(&[0xab; 112]).unwrap();
+    assert_eq!(two_blocks - one_block, SHA512_BLOCK_CYCLES as u64);
+}
+
+fn run_reduce(op: u32, width: u32, elems: &[u64], modulus: u32) -> Result<(u64, u64)> {
+    let program = testutil::reduce(op, width, elems.len() as u32, modulus);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let bytes: Vec<u8> = elems
+        .iter()
+        .flat_map(|x| x.to_le_bytes()[..width as usize].to_vec())
+        .collect();
+    exec.write_memory(testutil::REDUCE_BUF, &bytes)?;
+    let result = exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))?;
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    let lo = exec.get_register(REG_S0)? as u64;
+    let hi = exec.get_register(REG_S1)? as u64;
+    Ok((hi << 32 | lo, result.user_cycles))
+}
+
+#[test]
+fn reduce_ops() {
+    const MODULUS: u32 = 2039;
+
+    for (width, mask) in [
+        (reduce::WIDTH_U32, u32::MAX as u64),
+        (reduce::WIDTH_U64, u64::MAX),
+    ] {
+        for count in [0, 1, 7, 300] {
+            let elems: Vec<u64> = (0..count as u64)
+                .map(|i| (i + 1).wrapping_mul(0x9e3779b97f4a7c15) & mask)
+                .collect();
+            let expected = [
+                (
+                    reduce::OP_SUM,
+                    elems.iter().fold(0, |a: u64, x| a.wrapping_add(*x) & mask),
+                ),
+                (
+                    reduce::OP_PRODUCT,
+                    elems.iter().fold(1, |a: u64, x| {
+                        (a as u128 * *x as u128 % MODULUS as u128) as u64
+                    }),
+                ),
+                (reduce::OP_MIN, elems.iter().copied().fold(mask, u64::min)),
+                (reduce::OP_MAX, elems.iter().copied().fold(0, u64::max)),
+                (reduce::OP_XOR, elems.iter().fold(0, |a, x| a ^ x)),
+            ];
+            for (op, expected) in expected {
+                let (actual, _) = run_reduce(op, width, &elems, MODULUS).unwrap();
+                assert_eq!(actual, expected, "op: {op}, width: {width}, count: {count}");
+            }
+        }
+    }
+
+    // Cycles are charged per word reduced.
+    let (_, one) = run_reduce(reduce::OP_SUM, reduce::WIDTH_U64, &[1], 0).unwrap();
+    let (_, many) = run_reduce(reduce::OP_SUM, reduce::WIDTH_U64, &[1; 101], 0).unwrap();
+    assert_eq!(many - one, 200 * REDUCE_CYCLES as u64);
+
+    assert!(run_reduce(5, reduce::WIDTH_U32, &[1], 0).is_err());
+
+    // A count whose size overflows is rejected rather than truncated.
+    let program = testutil::reduce(reduce::OP_SUM, reduce::WIDTH_U64, u32::MAX, 0);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let mut exec = Executor::new(image, &testutil::NullSyscall, vec![]);
+    let err = exec
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .unwrap_err();
+    assert!(err.to_string().contains("too many elements"), "{err}");
+    assert!(run_reduce(reduce::OP_SUM, 2, &[1], 0).is_err());
+}
+
+struct FixedSyscall(u32);
+
+impl Syscall for FixedSyscall {
+    fn syscall(
+        &self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _guest_buf: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((self.0, 0))
+    }
+}
+
+struct GuestCallSyscall;
+
+impl Syscall for GuestCallSyscall {
+    fn syscall(
+        &self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _guest_buf: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let sum = ctx.call_guest(ByteAddr(testutil::GUEST_CALL_FN), &[20, 22])?;
+        Ok((sum, 0))
+    }
+}
+
+fn run_guest_call<S: Syscall>(handler: &S) -> SimpleSession {
+    let program = testutil::guest_call();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    super::execute(
+        image,
+        DEFAULT_SEGMENT_LIMIT_PO2,
+        DEFAULT_SESSION_LIMIT,
+        handler,
+    )
+    .unwrap()
+}
+
+#[test]
+fn call_guest() {
+    let session = run_guest_call(&GuestCallSyscall);
+    assert_eq!(session.result.exit_code, ExitCode::Halted(0));
+    let segment = session.segments.first().unwrap();
+    assert_eq!(segment.syscalls.len(), 1);
+    assert_eq!(segment.syscalls[0].regs, (42, 0));
+
+    // The guest call leaves no trace beyond its result: the session is
+    // indistinguishable from one where the host returned the value directly.
+    let expected = run_guest_call(&FixedSyscall(42));
+    assert_eq!(session.result.user_cycles, expected.result.user_cycles);
+    assert_eq!(
+        segment.post_state.digest::<ShaImpl>(),
+        expected.segments[0].post_state.digest::<ShaImpl>()
+    );
+}
+
+#[derive(Default)]
+struct CountingSyscall(RefCell<usize>);
+
+impl Syscall for CountingSyscall {
+    fn syscall(
+        &self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _guest_buf: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        *self.0.borrow_mut() += 1;
+        Ok((0, 0))
+    }
+}
+
+#[test]
+fn max_string_len() {
+    let run = |program, max_string_len| {
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let handler = CountingSyscall::default();
+        let mut exec = Executor::new(image, &handler, vec![]);
+        if let Some(len) = max_string_len {
+            exec.max_string_len(len);
+        }
+        exec.run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+            .map(|result| result.exit_code)
+    };
+
+    // The syscall name "foo" fits exactly, but not in a byte less.
+    assert_eq!(
+        run(testutil::software_syscalls(0, 1), Some(3)).unwrap(),
+        ExitCode::Halted(0)
+    );
+    let err = run(testutil::software_syscalls(0, 1), Some(2)).unwrap_err();
+    assert!(err.to_string().contains("longer than 2 bytes"), "{err}");
+
+    // A name that is never terminated fails at the default limit, rather than
+    // scanning on through memory.
+    let mut program = testutil::software_syscalls(0, 1);
+    program
+        .image
+        .extend((0..0x1000).map(|i| (0x8000 + 4 * i, 0x61616161)));
+    let err = run(program, None).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains(&format!("longer than {DEFAULT_MAX_STRING_LEN} bytes")),
+        "{err}"
+    );
+}
+
+#[test]
+fn syscall_replay() {
+    const COUNT: usize = 50;
+    let run = |replay| {
+        let program = testutil::software_syscalls(2000, COUNT as u32);
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let handler = CountingSyscall::default();
+        let mut exec = Executor::new(image, &handler, vec![]);
+        exec.syscall_replay(replay);
+        let result = exec.run(15, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+        assert_eq!(result.exit_code, ExitCode::Halted(0));
+        assert!(result.segments > 1);
+        let calls = *handler.0.borrow();
+        (calls, result.segments, result.user_cycles)
+    };
+
+    // With replay, each syscall is handled exactly once.
+    let (calls, segments, user_cycles) = run(true);
+    assert_eq!(calls, COUNT);
+
+    // Without replay, a syscall that is split across segments is handled
+    // again when it is re-executed.
+    let (raw_calls, raw_segments, raw_user_cycles) = run(false);
+    assert!(raw_calls > COUNT);
+    assert!(raw_calls < COUNT + raw_segments);
+    assert_eq!(raw_segments, segments);
+    assert_eq!(raw_user_cycles, user_cycles);
+}
+
+#[test]
+fn ecall_callback() {
+    const COUNT: usize = 50;
+    let program = testutil::software_syscalls(2000, COUNT as u32);
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let handler = CountingSyscall::default();
+    let ecalls = RefCell::new(Vec::new());
+    let mut exec = Executor::new(image, &handler, vec![]);
+    exec.ecall_callback(|info| ecalls.borrow_mut().push(info));
+    let result = exec.run(15, DEFAULT_SESSION_LIMIT, |_| Ok(())).unwrap();
+    assert_eq!(result.exit_code, ExitCode::Halted(0));
+    assert!(result.segments > 1);
+    drop(exec);
+
+    // Syscalls replayed after a split are still reported once each.
+    let ecalls = ecalls.into_inner();
+    assert_eq!(ecalls.len(), COUNT + 1);
+    assert!(ecalls[..COUNT]
+        .iter()
+        .all(|info| info.ecall == ecall::SOFTWARE && info.syscall.as_deref() == Some("foo")));
+    assert_eq!(ecalls[COUNT].ecall, ecall::HALT);
+    assert_eq!(ecalls[COUNT].syscall, None);
+}
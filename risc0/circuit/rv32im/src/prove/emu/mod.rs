@@ -20,7 +20,10 @@ pub mod preflight;
 pub mod rv32im;
 pub mod testutil;
 
-use risc0_zkvm_platform::{memory::SYSTEM, syscall::DIGEST_WORDS};
+use risc0_zkvm_platform::{
+    memory::SYSTEM,
+    syscall::{poseidon, DIGEST_WORDS},
+};
 
 use self::addr::{ByteAddr, WordAddr};
 
@@ -34,7 +37,56 @@ const SHA_MAIN_FINI: usize = 4;
 /// Number of cycles required to complete a BigInt operation.
 const BIGINT_CYCLES: usize = 9;
 
-/// The number of cycles required to compress a SHA-256 block.
-const fn sha_cycles(count: usize) -> usize {
-    SHA_INIT + (SHA_LOAD + SHA_MAIN_MIX + SHA_MAIN_FINI) * count
+/// Number of cycles charged for a BigInt addition.
+const BIGINT_ADD_CYCLES: usize = 5;
+
+/// Number of cycles charged for a BigInt subtraction.
+const BIGINT_SUB_CYCLES: usize = 5;
+
+/// Number of cycles charged per Keccak-f[1600] permutation.
+const KECCAK_CYCLES: usize = 24;
+
+/// Number of bytes absorbed by each Keccak-256 permutation.
+const KECCAK_RATE: usize = 136;
+
+/// The number of cycles required to hash `len` bytes with Keccak-256.
+const fn keccak_cycles(len: usize) -> usize {
+    // The final block is always padded, so there is at least one permutation.
+    KECCAK_CYCLES * (len / KECCAK_RATE + 1)
+}
+
+/// Number of cycles charged per Poseidon permutation, one per round: 4 full
+/// rounds on either side of 21 partial rounds.
+const POSEIDON_CYCLES: usize = 2 * 4 + 21;
+
+/// The number of cycles required to hash `count` elements with Poseidon.
+const fn poseidon_cycles(count: usize) -> usize {
+    // A partial chunk, or an empty input, is permuted once more.
+    POSEIDON_CYCLES
+        * if count == 0 {
+            1
+        } else {
+            count.div_ceil(poseidon::RATE)
+        }
+}
+
+/// Number of cycles charged per word reduced by the REDUCE ecall.
+const REDUCE_CYCLES: usize = 1;
+
+/// The number of cycles required to reduce `words` words of elements.
+const fn reduce_cycles(words: usize) -> usize {
+    REDUCE_CYCLES * words
+}
+
+/// Number of cycles required to compress a SHA-256 block.
+const SHA_BLOCK_CYCLES: usize = SHA_LOAD + SHA_MAIN_MIX + SHA_MAIN_FINI;
+
+/// Number of cycles required to compress a SHA-512 block, which is twice the
+/// size of a SHA-256 block and is processed in 64-bit words.
+const SHA512_BLOCK_CYCLES: usize = 2 * SHA_BLOCK_CYCLES;
+
+/// The number of blocks in a SHA-256 message of `len` bytes once padded.
+const fn sha_blocks(len: usize) -> usize {
+    // The padding adds a 1 bit and a 64-bit length, so 9 bytes at least.
+    (len + 9).div_ceil(64)
 }
@@ -12,10 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{
-    collections::{BTreeMap, BTreeSet, HashMap},
-    mem::take,
-};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use anyhow::Result;
 use risc0_binfmt::{MemoryImage, SystemState};
@@ -26,9 +23,6 @@ use super::addr::{ByteAddr, WordAddr};
 
 pub const PAGE_WORDS: usize = PAGE_SIZE / WORD_SIZE;
 
-/// The number of blocks that fit within a single page.
-const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_BYTES;
-
 const SHA_INIT: usize = 5;
 const SHA_LOAD: usize = 16;
 const SHA_MAIN: usize = 52;
@@ -66,12 +60,6 @@ pub struct PagedMemory {
     pending_actions: Vec<Action>,
 }
 
-impl WordAddr {
-    fn page_idx(&self) -> u32 {
-        self.0 / PAGE_WORDS as u32
-    }
-}
-
 impl PagedMemory {
     pub fn new(image: MemoryImage) -> Self {
         Self {
@@ -91,7 +79,7 @@ impl PagedMemory {
     }
 
     pub fn peek(&self, addr: WordAddr) -> Result<u32> {
-        let page_idx = addr.page_idx();
+        let page_idx = self.page_idx(addr);
         if let Some(cache) = self.page_cache.get(&page_idx) {
             Ok(cache.load(addr))
         } else {
@@ -100,7 +88,7 @@ impl PagedMemory {
     }
 
     pub fn load(&mut self, addr: WordAddr) -> u32 {
-        let page_idx = addr.page_idx();
+        let page_idx = self.page_idx(addr);
         // tracing::trace!("load: {addr:?}, page: 0x{page_idx:05x}");
         if self.page_states.get(&page_idx).is_none() {
             self.load_page(page_idx);
@@ -109,7 +97,7 @@ impl PagedMemory {
     }
 
     pub fn store(&mut self, addr: WordAddr, data: u32) -> Result<()> {
-        let page_idx = addr.page_idx();
+        let page_idx = self.page_idx(addr);
         // tracing::trace!("store: {addr:?}, page: 0x{page_idx:05x}, data: 0x{data:08x}");
         let state = if let Some(state) = self.page_states.get(&page_idx) {
             *state
@@ -169,8 +157,50 @@ impl PagedMemory {
         (pre_state, image, post_state)
     }
 
+    /// Like [Self::commit], but only apply dirty pages to the image, without
+    /// building a partial image or updating the merkle tree.
+    pub fn commit_dry(&mut self, pc: ByteAddr) {
+        for (page_idx, page_state) in &self.page_states {
+            if *page_state == PageState::Dirty {
+                let page = self.page_cache.get(page_idx).unwrap();
+                self.image.pages.insert(*page_idx, page.0.clone());
+            }
+        }
+        self.image.pc = pc.0;
+    }
+
+    /// Returns a copy of the image with the pages written so far applied, and
+    /// its merkle tree updated, leaving this [PagedMemory] unchanged.
+    pub fn snapshot(&self, pc: ByteAddr) -> MemoryImage {
+        let mut image = self.image.clone();
+        for (page_idx, page_state) in &self.page_states {
+            if *page_state == PageState::Dirty {
+                let page = self.page_cache.get(page_idx).unwrap();
+                image.pages.insert(*page_idx, page.0.clone());
+            }
+        }
+        for (page_idx, page_state) in &self.page_states {
+            if *page_state == PageState::Dirty {
+                image.update_page(*page_idx);
+            }
+        }
+        image.pc = pc.0;
+        image
+    }
+
     pub fn undo(&mut self) {
-        let pending_actions = take(&mut self.pending_actions);
+        self.undo_to(0);
+    }
+
+    /// Returns a marker that can later be passed to [Self::undo_to] to roll
+    /// back every action recorded after this point.
+    pub fn checkpoint(&self) -> usize {
+        self.pending_actions.len()
+    }
+
+    /// Roll back all pending actions recorded since `checkpoint`.
+    pub fn undo_to(&mut self, checkpoint: usize) {
+        let pending_actions = self.pending_actions.split_off(checkpoint);
         for action in pending_actions.iter().rev() {
             tracing::debug!("undo: {action:08x?})");
             match action {
@@ -187,8 +217,9 @@ impl PagedMemory {
                     self.cycles -= cycles;
                 }
                 Action::Store(addr, data) => {
+                    let page_idx = self.page_idx(*addr);
                     self.page_cache
-                        .get_mut(&addr.page_idx())
+                        .get_mut(&page_idx)
                         .unwrap()
                         .store(*addr, *data);
                 }
@@ -196,6 +227,51 @@ impl PagedMemory {
         }
     }
 
+    /// Write a word directly into the committed image, outside of any segment,
+    /// and refresh the merkle path from its page up to the root.
+    pub fn poke(&mut self, addr: WordAddr, data: u32) {
+        self.poke_words([(addr, data)]);
+    }
+
+    /// Write words directly into the committed image like [Self::poke], but
+    /// refresh the merkle path of each page they touch only once.
+    pub fn poke_words(&mut self, words: impl IntoIterator<Item = (WordAddr, u32)>) {
+        let mut pages = BTreeSet::new();
+        for (addr, data) in words {
+            let byte_addr: ByteAddr = addr.into();
+            self.image
+                .store_region_in_page(byte_addr.0, &data.to_le_bytes());
+            let page_idx = self.page_idx(addr);
+            if let Some(page) = self.page_cache.get_mut(&page_idx) {
+                page.store(addr, data);
+            }
+            pages.insert(page_idx);
+        }
+
+        // A page always has a lower index than the page holding its entry, so
+        // visiting them in order hashes every page before its parent.
+        while let Some(page_idx) = pages.pop_first() {
+            if page_idx < self.image.info.root_idx {
+                self.image.update_page(page_idx);
+                let entry_addr = self.image.info.get_page_entry_addr(page_idx);
+                pages.insert(self.image.info.get_page_index(entry_addr));
+            }
+        }
+    }
+
+    /// Returns the pages read in and marked dirty since the last
+    /// [Self::commit_step], in order, as the index of the page and whether it
+    /// was marked dirty.
+    pub fn pending_pages(&self) -> impl Iterator<Item = (u32, bool)> + '_ {
+        self.pending_actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::PageRead(page_idx, _) => Some((*page_idx, false)),
+                Action::PageWrite(page_idx, _, _) => Some((*page_idx, true)),
+                Action::Store(..) => None,
+            })
+    }
+
     pub fn commit_step(&mut self) {
         self.pending_actions.clear();
     }
@@ -207,6 +283,34 @@ impl PagedMemory {
         self.cycles = 0;
     }
 
+    /// Returns the cycles spent on page reads and on page writes, which add
+    /// up to [Self::cycles].
+    pub fn page_cycles(&self) -> (usize, usize) {
+        let mut reads = 0;
+        let mut writes = 0;
+        for (page_idx, state) in &self.page_states {
+            let page_cycles = self.cycles_for_page(*page_idx);
+            // Every page is read before it can become dirty.
+            reads += page_cycles;
+            if *state == PageState::Dirty {
+                writes += page_cycles;
+            }
+        }
+        (reads, writes)
+    }
+
+    /// Returns the number of pages read and the number of pages written,
+    /// including the pages of the merkle tree above them.
+    pub fn page_counts(&self) -> (usize, usize) {
+        let reads = self.page_states.len();
+        let writes = self
+            .page_states
+            .values()
+            .filter(|state| **state == PageState::Dirty)
+            .count();
+        (reads, writes)
+    }
+
     pub fn get_faults(&self) -> PageFaults {
         let mut faults = PageFaults::default();
         for (page_idx, page_state) in &self.page_states {
@@ -218,6 +322,10 @@ impl PagedMemory {
         faults
     }
 
+    fn page_idx(&self, addr: WordAddr) -> u32 {
+        self.image.info.get_page_index(ByteAddr::from(addr).0)
+    }
+
     fn load_page(&mut self, page_idx: u32) {
         tracing::trace!("load_page: 0x{page_idx:05x}");
         let page = self.image.load_page(page_idx);
@@ -247,15 +355,18 @@ impl PagedMemory {
         }
     }
 
-    fn page_changed(&mut self, page_idx: u32, state: PageState) {
+    fn cycles_for_page(&self, page_idx: u32) -> usize {
         let info = &self.image.info;
-
-        let page_cycles = if page_idx == info.root_idx {
+        if page_idx == info.root_idx {
             let num_root_entries = info.num_root_entries as usize;
             cycles_per_page(num_root_entries / 2)
         } else {
-            cycles_per_page(BLOCKS_PER_PAGE)
-        };
+            cycles_per_page(info.page_size as usize / BLOCK_BYTES)
+        }
+    }
+
+    fn page_changed(&mut self, page_idx: u32, state: PageState) {
+        let page_cycles = self.cycles_for_page(page_idx);
 
         tracing::trace!("page_changed(0x{page_idx:05x}, {state:?}) <= {page_cycles}");
         self.cycles += page_cycles;
@@ -270,8 +381,12 @@ impl PagedMemory {
 }
 
 impl Page {
+    fn words(&self) -> usize {
+        self.0.len() / WORD_SIZE
+    }
+
     fn load(&self, addr: WordAddr) -> u32 {
-        let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
+        let word_addr = addr.0 as usize % self.words();
         let byte_addr = word_addr * WORD_SIZE;
         let mut bytes = [0u8; WORD_SIZE];
         bytes.clone_from_slice(&self.0[byte_addr..byte_addr + WORD_SIZE]);
@@ -281,7 +396,7 @@ impl Page {
     }
 
     fn store(&mut self, addr: WordAddr, data: u32) {
-        let word_addr = (addr.0 % PAGE_WORDS as u32) as usize;
+        let word_addr = addr.0 as usize % self.words();
         let byte_addr = word_addr * WORD_SIZE;
         // tracing::trace!("store({addr:?}, 0x{data:08x})");
         self.0[byte_addr..byte_addr + WORD_SIZE].clone_from_slice(&data.to_le_bytes());
@@ -41,6 +41,7 @@ use risc0_zkvm_platform::{
 use sha2::digest::generic_array::GenericArray;
 
 use super::{
+    exec::ExecutorError,
     mux::{Major, TopMux},
     pager::{PagedMemory, PAGE_WORDS},
     rv32im::{DecodedInstruction, EmuContext, Emulator, InsnKind, Instruction, TrapCause},
@@ -708,7 +709,7 @@ impl EmuContext for Preflight {
             ecall::SOFTWARE => self.ecall_software(),
             ecall::SHA => self.ecall_sha(),
             ecall::BIGINT => self.ecall_bigint(),
-            ecall => bail!("Unknown ecall {ecall:?}"),
+            ecall => bail!(ExecutorError::UnknownEcall(ecall)),
         }
     }
 
@@ -720,7 +721,7 @@ impl EmuContext for Preflight {
         bail!("Trap: {cause:08x?}");
     }
 
-    fn on_insn_decoded(&self, insn: &Instruction, _decoded: &DecodedInstruction) {
+    fn on_insn_decoded(&mut self, insn: &Instruction, _decoded: &DecodedInstruction) {
         tracing::trace!("{:?}> {:?}", self.pc, insn.kind);
     }
 
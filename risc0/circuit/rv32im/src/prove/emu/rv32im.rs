@@ -28,7 +28,7 @@ pub trait EmuContext {
     fn trap(&self, cause: TrapCause) -> Result<bool>;
 
     // Callback when instructions are decoded
-    fn on_insn_decoded(&self, kind: &Instruction, decoded: &DecodedInstruction);
+    fn on_insn_decoded(&mut self, kind: &Instruction, decoded: &DecodedInstruction);
 
     // Callback when instructions end normally
     fn on_normal_end(&mut self, insn: &Instruction, decoded: &DecodedInstruction);
@@ -67,20 +67,38 @@ pub trait EmuContext {
     }
 }
 
+// The number of entries in the decode cache of the [Emulator], which must be a
+// power of two.
+const DECODE_CACHE_SIZE: usize = 1 << 12;
+
 pub struct Emulator {
     table: FastDecodeTable,
+
+    // Direct-mapped cache of decoded instructions, indexed by pc. An entry is
+    // only used if both its pc and its instruction word match the ones being
+    // executed, so stores over the program text invalidate it.
+    decode_cache: Vec<Option<CachedDecode>>,
+
+    // The number of instructions that missed the decode cache.
+    decodes: u64,
+}
+
+struct CachedDecode {
+    pc: ByteAddr,
+    decoded: DecodedInstruction,
+    insn: Instruction,
 }
 
 #[derive(Debug)]
 pub enum TrapCause {
-    InstructionAddressMisaligned,
-    InstructionAccessFault,
+    InstructionAddressMisaligned(ByteAddr),
+    InstructionAccessFault(ByteAddr),
     IllegalInstruction(u32),
     Breakpoint,
-    LoadAddressMisaligned,
-    LoadAccessFault,
+    LoadAddressMisaligned(ByteAddr),
+    LoadAccessFault(ByteAddr),
     StoreAddressMisaligned(ByteAddr),
-    StoreAccessFault,
+    StoreAccessFault(ByteAddr),
     EnvironmentCallFromUserMode,
 }
 
@@ -105,7 +123,7 @@ enum InsnCategory {
     Invalid,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum InsnKind {
     INVALID,
     ADD,
@@ -157,6 +175,49 @@ pub enum InsnKind {
     MRET,
 }
 
+/// The classes of instructions that are charged the same number of cycles,
+/// see [InsnKind::major_type].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MajorType {
+    /// Arithmetic, logic, shift and comparison instructions, and `lui`/`auipc`.
+    Alu,
+    /// The `mul` family.
+    Multiply,
+    /// The `div` and `rem` families.
+    Divide,
+    /// Conditional branches.
+    Branch,
+    /// `jal` and `jalr`.
+    Jump,
+    /// Memory loads.
+    Load,
+    /// Memory stores.
+    Store,
+    /// `ecall` and `mret`.
+    System,
+    /// Instructions that failed to decode.
+    Invalid,
+}
+
+impl InsnKind {
+    /// Returns the class of this instruction.
+    pub fn major_type(self) -> MajorType {
+        match self {
+            Self::INVALID => MajorType::Invalid,
+            Self::MUL | Self::MULH | Self::MULHSU | Self::MULHU => MajorType::Multiply,
+            Self::DIV | Self::DIVU | Self::REM | Self::REMU => MajorType::Divide,
+            Self::BEQ | Self::BNE | Self::BLT | Self::BGE | Self::BLTU | Self::BGEU => {
+                MajorType::Branch
+            }
+            Self::JAL | Self::JALR => MajorType::Jump,
+            Self::LB | Self::LH | Self::LW | Self::LBU | Self::LHU => MajorType::Load,
+            Self::SB | Self::SH | Self::SW => MajorType::Store,
+            Self::EANY | Self::MRET => MajorType::System,
+            _ => MajorType::Alu,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Instruction {
     pub kind: InsnKind,
@@ -345,18 +406,62 @@ impl FastDecodeTable {
     }
 }
 
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Emulator {
     pub fn new() -> Self {
         Self {
             table: FastDecodeTable::new(),
+            decode_cache: (0..DECODE_CACHE_SIZE).map(|_| None).collect(),
+            decodes: 0,
         }
     }
 
+    /// Returns the number of instructions that were decoded rather than
+    /// served from the decode cache.
+    pub fn decodes(&self) -> u64 {
+        self.decodes
+    }
+
+    /// Returns the kind of instruction encoded by `word`, or `None` if it is
+    /// not a valid instruction. This bypasses the decode cache.
+    pub fn decode_kind(&self, word: u32) -> Option<InsnKind> {
+        if word & 0x03 != 0x03 {
+            return None;
+        }
+        let kind = self.table.lookup(&DecodedInstruction::new(word)).kind;
+        (kind != InsnKind::INVALID).then_some(kind)
+    }
+
+    fn decode(&mut self, pc: ByteAddr, word: u32) -> (DecodedInstruction, Instruction) {
+        let idx = pc.waddr().0 as usize & (DECODE_CACHE_SIZE - 1);
+        let slot = &mut self.decode_cache[idx];
+        if let Some(entry) = slot {
+            if entry.pc == pc && entry.decoded.insn == word {
+                return (entry.decoded.clone(), entry.insn);
+            }
+        }
+
+        self.decodes += 1;
+        let decoded = DecodedInstruction::new(word);
+        let insn = self.table.lookup(&decoded);
+        *slot = Some(CachedDecode {
+            pc,
+            decoded: decoded.clone(),
+            insn,
+        });
+        (decoded, insn)
+    }
+
     pub fn step<C: EmuContext>(&mut self, ctx: &mut C) -> Result<()> {
         let pc = ctx.get_pc();
 
         if !ctx.check_insn_load(pc) {
-            ctx.trap(TrapCause::InstructionAccessFault)?;
+            ctx.trap(TrapCause::InstructionAccessFault(pc))?;
             return Ok(());
         }
 
@@ -366,8 +471,7 @@ impl Emulator {
             return Ok(());
         }
 
-        let decoded = DecodedInstruction::new(word);
-        let insn = self.table.lookup(&decoded);
+        let (decoded, insn) = self.decode(pc, word);
         ctx.on_insn_decoded(&insn, &decoded);
 
         if match insn.category {
@@ -499,7 +603,7 @@ impl Emulator {
             _ => unreachable!(),
         };
         if !new_pc.is_aligned() {
-            return ctx.trap(TrapCause::InstructionAddressMisaligned);
+            return ctx.trap(TrapCause::InstructionAddressMisaligned(new_pc));
         }
         ctx.store_register(rd as usize, out)?;
         ctx.set_pc(new_pc);
@@ -516,7 +620,7 @@ impl Emulator {
         let _rs2 = ctx.load_register(decoded.rs2 as usize)?;
         let addr = ByteAddr(rs1.wrapping_add(decoded.imm_i()));
         if !ctx.check_data_load(addr) {
-            return ctx.trap(TrapCause::LoadAccessFault);
+            return ctx.trap(TrapCause::LoadAccessFault(addr));
         }
         let data = ctx.load_memory(addr.waddr())?;
         let shift = 8 * (addr.0 & 3);
@@ -530,7 +634,7 @@ impl Emulator {
             }
             InsnKind::LH => {
                 if addr.0 & 0x01 != 0 {
-                    return ctx.trap(TrapCause::LoadAddressMisaligned);
+                    return ctx.trap(TrapCause::LoadAddressMisaligned(addr));
                 }
                 let mut out = (data >> shift) & 0xffff;
                 if out & 0x8000 != 0 {
@@ -540,14 +644,14 @@ impl Emulator {
             }
             InsnKind::LW => {
                 if addr.0 & 0x03 != 0 {
-                    return ctx.trap(TrapCause::LoadAddressMisaligned);
+                    return ctx.trap(TrapCause::LoadAddressMisaligned(addr));
                 }
                 data
             }
             InsnKind::LBU => (data >> shift) & 0xff,
             InsnKind::LHU => {
                 if addr.0 & 0x01 != 0 {
-                    return ctx.trap(TrapCause::LoadAddressMisaligned);
+                    return ctx.trap(TrapCause::LoadAddressMisaligned(addr));
                 }
                 (data >> shift) & 0xffff
             }
@@ -569,7 +673,7 @@ impl Emulator {
         let addr = ByteAddr(rs1.wrapping_add(decoded.imm_s()));
         let shift = 8 * (addr.0 & 3);
         if !ctx.check_data_store(addr) {
-            return ctx.trap(TrapCause::StoreAccessFault);
+            return ctx.trap(TrapCause::StoreAccessFault(addr));
         }
         let mut data = ctx.load_memory(addr.waddr())?;
         match kind {
@@ -93,3 +93,446 @@ pub fn large_text() -> Program {
     image.insert(pc, 0x00000073); // ecall(halt)
     Program { entry, image }
 }
+
+/// A program that issues a single ecall with the given `t0` and `a0`, each of
+/// which must fit in an immediate.
+pub fn raw_ecall(t0: u32, a0: u32) -> Program {
+    assert!(t0 < 0x800 && a0 < 0x800);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, (t0 << 20) | 0x00000293), // li t0, t0
+            (0x4004, (a0 << 20) | 0x00000513), // li a0, a0
+            (0x4008, 0x000045b7),              // lui a1, 0x4
+            (0x400c, 0x00000073),              // ecall
+        ]),
+    }
+}
+
+/// A program that runs the single instruction `insn` and then halts.
+pub fn single_insn(insn: u32) -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, insn),
+            (0x4004, 0x000045b7), // lui a1, 0x4
+            (0x4008, 0x00000073), // ecall(halt)
+        ]),
+    }
+}
+
+/// A program that computes a sum, pauses, and then after resuming adds `x2`
+/// to that sum before halting.
+pub fn pause_resume() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x1234b137), // lui x2, 0x1234b000
+            (0x4004, 0xf387e1b7), // lui x3, 0xf387e000
+            (0x4008, 0x003100b3), // add x1, x2, x3
+            (0x400c, 0x00100513), // li a0, 1
+            (0x4010, 0x000045b7), // lui a1, 0x4
+            (0x4014, 0x00000073), // ecall(pause)
+            (0x4018, 0x00208233), // add x4, x1, x2
+            (0x401c, 0x00000513), // li a0, 0
+            (0x4020, 0x00000073), // ecall(halt)
+        ]),
+    }
+}
+
+/// A program that executes `li a0, 1`, patches that instruction into
+/// `li a0, 2`, and executes it again. The last value of `a0` is left in `s1`.
+pub fn self_modifying() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00200413), // li s0, 2
+            (0x4004, 0x00100513), // loop: li a0, 1
+            (0x4008, 0x00004337), // lui t1, 0x4
+            (0x400c, 0x04032383), // lw t2, 0x40(t1)
+            (0x4010, 0x00732223), // sw t2, 4(t1)
+            (0x4014, 0xfff40413), // addi s0, s0, -1
+            (0x4018, 0xfe0416e3), // bnez s0, loop
+            (0x401c, 0x00050493), // mv s1, a0
+            (0x4020, 0x00000293), // li t0, 0
+            (0x4024, 0x00000513), // li a0, 0
+            (0x4028, 0x000045b7), // lui a1, 0x4
+            (0x402c, 0x00000073), // ecall(halt)
+            (0x4040, 0x00200513), // li a0, 2
+        ]),
+    }
+}
+
+/// The address written by [store_sequence].
+pub const STORE_SEQUENCE_ADDR: u32 = 0x8000;
+
+/// A program that stores 1, 2, and then 3 to [STORE_SEQUENCE_ADDR] before
+/// halting.
+pub fn store_sequence() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00008337), // lui t1, 0x8
+            (0x4004, 0x00100393), // li t2, 1
+            (0x4008, 0x00732023), // sw t2, 0(t1)
+            (0x400c, 0x00200393), // li t2, 2
+            (0x4010, 0x00732023), // sw t2, 0(t1)
+            (0x4014, 0x00300393), // li t2, 3
+            (0x4018, 0x00732023), // sw t2, 0(t1)
+            (0x401c, 0x00000293), // li t0, 0
+            (0x4020, 0x00000513), // li a0, 0
+            (0x4024, 0x000045b7), // lui a1, 0x4
+            (0x4028, 0x00000073), // ecall(halt)
+        ]),
+    }
+}
+
+/// A program that runs 25 instructions, refills 64 units of fuel, and then
+/// runs another 25 instructions before halting.
+pub fn fuel_refill() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00000713), // li a4, 0
+            (0x4004, 0x00a00793), // li a5, 10
+            (0x4008, 0x00170713), // addi a4, a4, 1
+            (0x400c, 0xfef74ee3), // blt a4, a5, 0x4008
+            (0x4010, 0x00600293), // li t0, 6
+            (0x4014, 0x04000513), // li a0, 64
+            (0x4018, 0x00000073), // ecall(add_fuel)
+            (0x401c, 0x00000713), // li a4, 0
+            (0x4020, 0x00170713), // addi a4, a4, 1
+            (0x4024, 0xfef74ee3), // blt a4, a5, 0x4020
+            (0x4028, 0x00000293), // li t0, 0
+            (0x402c, 0x00000513), // li a0, 0
+            (0x4030, 0x000045b7), // lui a1, 0x4
+            (0x4034, 0x00000073), // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the input and output of the KECCAK ecall in [keccak].
+pub const KECCAK_DATA: u32 = 0x8000;
+pub const KECCAK_OUT: u32 = 0x9000;
+
+/// A program that hashes `len` bytes with the KECCAK ecall and then halts.
+/// The input is expected to be written to memory beforehand. A `len` of
+/// `u32::MAX` passes -1 to the ecall.
+pub fn keccak(len: u32) -> Program {
+    assert!(len < 0x800 || len == u32::MAX);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00700293),               // li t0, 7
+            (0x4004, 0x00008537),               // lui a0, 0x8
+            (0x4008, (len << 20) | 0x00000593), // li a1, len
+            (0x400c, 0x00009637),               // lui a2, 0x9
+            (0x4010, 0x00000073),               // ecall(keccak)
+            (0x4014, 0x00000293),               // li t0, 0
+            (0x4018, 0x00000513),               // li a0, 0
+            (0x401c, 0x000045b7),               // lui a1, 0x4
+            (0x4020, 0x00000073),               // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the input state, output state, and blocks of the SHA
+/// ecalls in [sha].
+pub const SHA_STATE_IN: u32 = 0x8000;
+pub const SHA_STATE_OUT: u32 = 0x9000;
+pub const SHA_BLOCKS: u32 = 0x10000;
+
+/// The number of blocks compressed by each SHA ecall in [sha].
+pub const SHA_BLOCKS_PER_CALL: u32 = 16;
+
+/// A program that compresses consecutive blocks with `calls` SHA ecalls of
+/// [SHA_BLOCKS_PER_CALL] blocks each, chaining the state from one call to the
+/// next, and then halts. The input state and blocks are expected to be
+/// written to memory beforehand.
+pub fn sha(calls: u32) -> Program {
+    assert!(calls > 0 && calls < 0x800);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00009537),                 // lui a0, 0x9
+            (0x4004, 0x000085b7),                 // lui a1, 0x8
+            (0x4008, 0x00010637),                 // lui a2, 0x10
+            (0x400c, (calls << 20) | 0x00000413), // li s0, calls
+            (0x4010, 0x00300293),                 // loop: li t0, 3
+            (0x4014, 0x02060693),                 // addi a3, a2, 32
+            (0x4018, 0x01000713),                 // li a4, 16
+            (0x401c, 0x00000073),                 // ecall(sha)
+            (0x4020, 0x00050593),                 // mv a1, a0
+            (0x4024, 0x40060613),                 // addi a2, a2, 1024
+            (0x4028, 0xfff40413),                 // addi s0, s0, -1
+            (0x402c, 0xfe0412e3),                 // bnez s0, loop
+            (0x4030, 0x00000293),                 // li t0, 0
+            (0x4034, 0x00000513),                 // li a0, 0
+            (0x4038, 0x000045b7),                 // lui a1, 0x4
+            (0x403c, 0x00000073),                 // ecall(halt)
+        ]),
+    }
+}
+
+/// The address of the descriptors of the SHA_BATCH ecall in [sha_batch].
+pub const SHA_BATCH_DESCRIPTORS: u32 = 0x8000;
+
+/// A program that compresses `count` descriptors with the SHA_BATCH ecall and
+/// then halts. The descriptors, and the states and blocks they point to, are
+/// expected to be written to memory beforehand.
+pub fn sha_batch(count: u32) -> Program {
+    assert!(count < 0x800);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00b00293),                 // li t0, 11
+            (0x4004, 0x00008537),                 // lui a0, 0x8
+            (0x4008, (count << 20) | 0x00000593), // li a1, count
+            (0x400c, 0x00000073),                 // ecall(sha_batch)
+            (0x4010, 0x00000293),                 // li t0, 0
+            (0x4014, 0x00000513),                 // li a0, 0
+            (0x4018, 0x000045b7),                 // lui a1, 0x4
+            (0x401c, 0x00000073),                 // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the input state, output state, and blocks of the SHA512
+/// ecall in [sha512].
+pub const SHA512_STATE_IN: u32 = 0x8000;
+pub const SHA512_STATE_OUT: u32 = 0x9000;
+pub const SHA512_BLOCKS: u32 = 0xa000;
+
+/// A program that compresses `count` blocks with the SHA512 ecall and then
+/// halts. The input state and blocks are expected to be written to memory
+/// beforehand.
+pub fn sha512(count: u32) -> Program {
+    assert!(count < 0x20);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00a00293),                 // li t0, 10
+            (0x4004, 0x00009537),                 // lui a0, 0x9
+            (0x4008, 0x000085b7),                 // lui a1, 0x8
+            (0x400c, 0x0000a637),                 // lui a2, 0xa
+            (0x4010, 0x04060693),                 // addi a3, a2, 64
+            (0x4014, (count << 20) | 0x00000713), // li a4, count
+            (0x4018, 0x00000073),                 // ecall(sha512)
+            (0x401c, 0x00000293),                 // li t0, 0
+            (0x4020, 0x00000513),                 // li a0, 0
+            (0x4024, 0x000045b7),                 // lui a1, 0x4
+            (0x4028, 0x00000073),                 // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the input and output of the SHA_MEM ecall in [sha_mem].
+pub const SHA_MEM_DATA: u32 = 0x8000;
+pub const SHA_MEM_OUT: u32 = 0x9000;
+
+/// A program that hashes `len` bytes with the SHA_MEM ecall and then halts.
+/// The input is expected to be written to memory beforehand. A `len` of
+/// `u32::MAX` passes -1 to the ecall.
+pub fn sha_mem(len: u32) -> Program {
+    assert!(len < 0x800 || len == u32::MAX);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00900293),               // li t0, 9
+            (0x4004, 0x00008537),               // lui a0, 0x8
+            (0x4008, (len << 20) | 0x00000593), // li a1, len
+            (0x400c, 0x00009637),               // lui a2, 0x9
+            (0x4010, 0x00000073),               // ecall(sha_mem)
+            (0x4014, 0x00000293),               // li t0, 0
+            (0x4018, 0x00000513),               // li a0, 0
+            (0x401c, 0x000045b7),               // lui a1, 0x4
+            (0x4020, 0x00000073),               // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the input elements and the output of [poseidon].
+pub const POSEIDON_IN: u32 = 0x8000;
+pub const POSEIDON_OUT: u32 = 0x9000;
+
+/// A program that hashes `count` elements with the POSEIDON ecall and then
+/// halts. The input is expected to be written to memory beforehand. A
+/// `count` of `u32::MAX` passes -1 to the ecall.
+pub fn poseidon(count: u32) -> Program {
+    assert!(count < 0x800 || count == u32::MAX);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00c00293),                 // li t0, 12
+            (0x4004, 0x00009537),                 // lui a0, 0x9
+            (0x4008, 0x000085b7),                 // lui a1, 0x8
+            (0x400c, (count << 20) | 0x00000613), // li a2, count
+            (0x4010, 0x00000073),                 // ecall(poseidon)
+            (0x4014, 0x00000293),                 // li t0, 0
+            (0x4018, 0x00000513),                 // li a0, 0
+            (0x401c, 0x000045b7),                 // lui a1, 0x4
+            (0x4020, 0x00000073),                 // ecall(halt)
+        ]),
+    }
+}
+
+/// The address of the buffer reduced by [reduce].
+pub const REDUCE_BUF: u32 = 0x8000;
+
+/// A program that issues a single REDUCE ecall over `count` elements at
+/// [REDUCE_BUF], moves the result into `s0` (low) and `s1` (high), and halts.
+/// The elements are expected to be written to memory beforehand. A `count` of
+/// `u32::MAX` passes -1 to the ecall.
+pub fn reduce(op: u32, width: u32, count: u32, modulus: u32) -> Program {
+    assert!((count < 0x800 || count == u32::MAX) && modulus < 0x800);
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00800293),                   // li t0, 8
+            (0x4004, (op << 20) | 0x00000513),      // li a0, op
+            (0x4008, 0x000085b7),                   // lui a1, 0x8
+            (0x400c, (width << 20) | 0x00000613),   // li a2, width
+            (0x4010, (count << 20) | 0x00000693),   // li a3, count
+            (0x4014, (modulus << 20) | 0x00000713), // li a4, modulus
+            (0x4018, 0x00000793),                   // li a5, 0
+            (0x401c, 0x00000073),                   // ecall(reduce)
+            (0x4020, 0x00050413),                   // mv s0, a0
+            (0x4024, 0x00058493),                   // mv s1, a1
+            (0x4028, 0x00000293),                   // li t0, 0
+            (0x402c, 0x00000513),                   // li a0, 0
+            (0x4030, 0x000045b7),                   // lui a1, 0x4
+            (0x4034, 0x00000073),                   // ecall(halt)
+        ]),
+    }
+}
+
+/// The addresses of the output and inputs of the BIGINT ecall in [bigint].
+pub const BIGINT_Z: u32 = 0x8000;
+pub const BIGINT_X: u32 = 0x8100;
+pub const BIGINT_Y: u32 = 0x8200;
+pub const BIGINT_N: u32 = 0x8300;
+
+/// A program that issues a single BIGINT or BIGINT_EXT `ecall` with the given
+/// `op` and then halts. The inputs are expected to be written to memory
+/// beforehand.
+pub fn bigint(ecall: u32, op: u32) -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, (ecall << 20) | 0x00000293), // li t0, ecall
+            (0x4004, 0x00008537),                 // lui a0, 0x8
+            (0x4008, (op << 20) | 0x00000593),    // li a1, op
+            (0x400c, 0x00008637),                 // lui a2, 0x8
+            (0x4010, 0x10060613),                 // addi a2, a2, 0x100
+            (0x4014, 0x000086b7),                 // lui a3, 0x8
+            (0x4018, 0x20068693),                 // addi a3, a3, 0x200
+            (0x401c, 0x00008737),                 // lui a4, 0x8
+            (0x4020, 0x30070713),                 // addi a4, a4, 0x300
+            (0x4024, 0x00000073),                 // ecall(bigint)
+            (0x4028, 0x00000293),                 // li t0, 0
+            (0x402c, 0x00000513),                 // li a0, 0
+            (0x4030, 0x000045b7),                 // lui a1, 0x4
+            (0x4034, 0x00000073),                 // ecall(halt)
+        ]),
+    }
+}
+
+/// A program that issues a single BIGINT_MODEXP ecall, raising [BIGINT_X] to
+/// the power of [BIGINT_Y] modulo [BIGINT_N] into [BIGINT_Z], and then halts.
+pub fn bigint_modexp() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00d00293), // li t0, 13
+            (0x4004, 0x00008537), // lui a0, 0x8
+            (0x4008, 0x000085b7), // lui a1, 0x8
+            (0x400c, 0x10058593), // addi a1, a1, 0x100
+            (0x4010, 0x00008637), // lui a2, 0x8
+            (0x4014, 0x20060613), // addi a2, a2, 0x200
+            (0x4018, 0x000086b7), // lui a3, 0x8
+            (0x401c, 0x30068693), // addi a3, a3, 0x300
+            (0x4020, 0x00000073), // ecall(bigint_modexp)
+            (0x4024, 0x00000293), // li t0, 0
+            (0x4028, 0x00000513), // li a0, 0
+            (0x402c, 0x000045b7), // lui a1, 0x4
+            (0x4030, 0x00000073), // ecall(halt)
+        ]),
+    }
+}
+
+/// A program that issues `count` BURN_CYCLES ecalls of `cycles` each.
+pub fn burn_cycles(cycles: u32, count: usize) -> Program {
+    // Split `cycles` into a sign-adjusted upper/lower pair for `lui`/`addi`.
+    let hi = cycles.wrapping_add(0x800) >> 12;
+    let lo = cycles.wrapping_sub(hi << 12) & 0xfff;
+
+    let entry = 0x4000;
+    let mut image = BTreeMap::new();
+    let mut pc = entry;
+    let mut push = |insn: u32| {
+        image.insert(pc, insn);
+        pc += WORD_SIZE as u32;
+    };
+    for _ in 0..count {
+        push((hi << 12) | 0x00000537); // lui a0, hi
+        push((lo << 20) | 0x00050513); // addi a0, a0, lo
+        push(0x00500293); // li t0, 5
+        push(0x00000073); // ecall(burn_cycles)
+    }
+    push(0x00000293); // li t0, 0
+    push(0x00000513); // li a0, 0
+    push(0x000045b7); // lui a1, 0x4
+    push(0x00000073); // ecall(halt)
+    Program { entry, image }
+}
+
+/// A program that issues `count` software syscalls named "foo" in a loop,
+/// each of which transfers `words` words from the host into guest memory, and
+/// then halts.
+pub fn software_syscalls(words: u32, count: u32) -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00008637),                 // lui a2, 0x8
+            (0x4004, (count << 20) | 0x00000413), // li s0, count
+            (0x4008, 0x00200293),                 // loop: li t0, 2
+            (0x400c, 0x00010537),                 // lui a0, 0x10
+            (0x4010, (words << 20) | 0x00000593), // li a1, words
+            (0x4014, 0x00000073),                 // ecall(software)
+            (0x4018, 0xfff40413),                 // addi s0, s0, -1
+            (0x401c, 0xfe0416e3),                 // bnez s0, loop
+            (0x4020, 0x00000293),                 // li t0, 0
+            (0x4024, 0x00000513),                 // li a0, 0
+            (0x4028, 0x000045b7),                 // lui a1, 0x4
+            (0x402c, 0x00000073),                 // ecall(halt)
+            (0x8000, 0x006f6f66),                 // "foo\0"
+        ]),
+    }
+}
+
+/// The address of the guest function in [guest_call].
+pub const GUEST_CALL_FN: u32 = 0x4100;
+
+/// A program that issues a single software syscall named "foo" and then
+/// halts. It also contains a function at [GUEST_CALL_FN] that returns the sum
+/// of its two arguments, for use by a host syscall handler.
+pub fn guest_call() -> Program {
+    Program {
+        entry: 0x4000,
+        image: BTreeMap::from([
+            (0x4000, 0x00200293), // li t0, 2
+            (0x4004, 0x00000513), // li a0, 0
+            (0x4008, 0x00000593), // li a1, 0
+            (0x400c, 0x00004637), // lui a2, 0x4
+            (0x4010, 0x20060613), // addi a2, a2, 0x200
+            (0x4014, 0x00000073), // ecall(software)
+            (0x4018, 0x00000293), // li t0, 0
+            (0x401c, 0x00000513), // li a0, 0
+            (0x4020, 0x000045b7), // lui a1, 0x4
+            (0x4024, 0x00000073), // ecall(halt)
+            (0x4100, 0x00b50533), // add a0, a0, a1
+            (0x4104, 0x00008067), // ret
+            (0x4200, 0x006f6f66), // "foo\0"
+        ]),
+    }
+}
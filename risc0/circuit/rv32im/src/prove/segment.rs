@@ -22,10 +22,11 @@ use risc0_zkp::{
 use risc0_zkvm_platform::WORD_SIZE;
 use serde::{Deserialize, Serialize};
 
-use crate::CircuitImpl;
+use crate::{prove::emu::exec::LoaderCycles, CircuitImpl};
 
 #[derive(Clone, Dbg, Serialize, Deserialize)]
 pub struct SyscallRecord {
+    pub name: String,
     pub to_guest: Vec<u32>,
     pub regs: (u32, u32),
 }
@@ -39,10 +40,13 @@ pub struct Segment {
     #[dbg(placeholder = "...")]
     pub syscalls: Vec<SyscallRecord>,
     pub insn_cycles: usize,
+    pub page_read_cycles: usize,
+    pub page_write_cycles: usize,
     pub po2: usize,
     pub exit_code: ExitCode,
     pub index: usize,
     pub output_digest: Option<Digest>,
+    pub loader_cycles: LoaderCycles,
 }
 
 impl Segment {
@@ -14,12 +14,17 @@
 
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 /// An event traced from the running VM.
+///
+/// The events produced by a single instruction are delivered in the order in
+/// which the variants are declared, so that a [TraceEvent::SyscallStart] and
+/// [TraceEvent::SyscallEnd] pair brackets the register and memory writes
+/// performed by the syscall.
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum TraceEvent {
     /// An instruction has started at the given program counter
@@ -33,6 +38,14 @@ pub enum TraceEvent {
         insn: u32,
     },
 
+    /// A syscall has started
+    SyscallStart {
+        /// Name of the syscall
+        name: String,
+        /// Cycle number at which the syscall started
+        cycle: u32,
+    },
+
     /// A register has been set
     RegisterSet {
         /// Register ID (0-16)
@@ -48,6 +61,35 @@ pub enum TraceEvent {
         /// Data that's been written
         region: Vec<u8>,
     },
+
+    /// A syscall has completed
+    SyscallEnd {
+        /// Name of the syscall
+        name: String,
+        /// Number of words written back to the guest
+        to_guest_words: u32,
+        /// Value returned in register a0
+        a0: u32,
+        /// Value returned in register a1
+        a1: u32,
+    },
+
+    /// A page has been read in, the first time it was touched in the segment
+    PageIn {
+        /// Index of the page, including the pages of the merkle tree
+        page_idx: u32,
+        /// Cycle number of the instruction that touched the page
+        cycle: u32,
+    },
+
+    /// A page has been modified for the first time in the segment, and will be
+    /// written back when the segment ends
+    PageOut {
+        /// Index of the page, including the pages of the merkle tree
+        page_idx: u32,
+        /// Cycle number of the instruction that modified the page
+        cycle: u32,
+    },
 }
 
 /// A callback used to collect [TraceEvent]s.
@@ -71,6 +113,18 @@ impl core::fmt::Debug for TraceEvent {
             Self::MemorySet { addr, region } => {
                 write!(f, "MemorySet(0x{addr:08X}, {region:#04X?})")
             }
+            Self::SyscallStart { name, cycle } => write!(f, "SyscallStart({name}, {cycle})"),
+            Self::SyscallEnd {
+                name,
+                to_guest_words,
+                a0,
+                a1,
+            } => write!(
+                f,
+                "SyscallEnd({name}, {to_guest_words}, 0x{a0:08X}, 0x{a1:08X})"
+            ),
+            Self::PageIn { page_idx, cycle } => write!(f, "PageIn(0x{page_idx:05X}, {cycle})"),
+            Self::PageOut { page_idx, cycle } => write!(f, "PageOut(0x{page_idx:05X}, {cycle})"),
         }
     }
 }
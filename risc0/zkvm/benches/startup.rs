@@ -0,0 +1,38 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `startup` compares the cost of constructing an executor from an ELF binary
+//! with that of constructing one from a [PreparedProgram].
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use risc0_zkvm::{ExecutorEnv, ExecutorImpl, PreparedProgram};
+use risc0_zkvm_methods::MULTI_TEST_ELF;
+
+fn startup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("startup");
+
+    group.bench_function("from_elf", |b| {
+        b.iter(|| ExecutorImpl::from_elf(ExecutorEnv::default(), MULTI_TEST_ELF).unwrap())
+    });
+
+    let prepared = PreparedProgram::new(MULTI_TEST_ELF).unwrap();
+    group.bench_function("from_prepared", |b| {
+        b.iter(|| ExecutorImpl::from_prepared(ExecutorEnv::default(), &prepared).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
@@ -23,7 +23,7 @@ use alloc::{
     alloc::{alloc_zeroed, Layout},
     format, vec,
 };
-use core::arch::asm;
+use core::{arch::asm, ptr::null_mut};
 
 use getrandom::getrandom;
 use risc0_zkp::core::hash::sha::testutil::test_sha_impl;
@@ -36,8 +36,11 @@ use risc0_zkvm_methods::multi_test::{MultiTestSpec, SYS_MULTI_TEST};
 use risc0_zkvm_platform::{
     fileno,
     memory::{self, SYSTEM},
-    syscall::{bigint, sys_bigint, sys_log, sys_read, sys_read_words, sys_write},
-    PAGE_SIZE,
+    syscall::{
+        bigint, poseidon, sys_bigint, sys_cycle_count_u64, sys_getenv, sys_log, sys_poseidon,
+        sys_read, sys_read_channel, sys_read_words, sys_write,
+    },
+    PAGE_SIZE, WORD_SIZE,
 };
 
 risc0_zkvm::entry!(main);
@@ -54,6 +57,37 @@ fn profile_test_func2() {
     unsafe { asm!("nop") }
 }
 
+#[inline(never)]
+#[no_mangle]
+fn call_stack_outer() -> u32 {
+    core::hint::black_box(call_stack_middle()) + 1
+}
+
+#[inline(never)]
+#[no_mangle]
+fn call_stack_middle() -> u32 {
+    core::hint::black_box(call_stack_inner()) + 1
+}
+
+#[inline(never)]
+#[no_mangle]
+fn call_stack_inner() -> u32 {
+    // Load from an address outside of guest memory, which faults.
+    let value: u32;
+    unsafe { asm!("lw {}, 0({})", out(reg) value, in(reg) 0) };
+    value
+}
+
+#[inline(never)]
+#[no_mangle]
+fn hot_loop(iters: u32) -> u32 {
+    let mut acc = 0_u32;
+    for i in 0..iters {
+        acc = core::hint::black_box(acc.wrapping_mul(31).wrapping_add(i));
+    }
+    acc
+}
+
 fn main() {
     let impl_select: MultiTestSpec = env::read();
     match impl_select {
@@ -101,6 +135,7 @@ fn main() {
         MultiTestSpec::Panic => {
             panic!("MultiTestSpec::Panic invoked");
         }
+        MultiTestSpec::Abort(msg) => risc0_zkvm::guest::abort(&msg),
         MultiTestSpec::Fault => unsafe {
             asm!("sw x0, 1(x0)");
         },
@@ -329,5 +364,128 @@ fn main() {
                 assert_eq!(*value, 0);
             }
         }
+        MultiTestSpec::SegmentPo2 => {
+            env::commit(&env::segment_po2());
+        }
+        MultiTestSpec::CallStackFault => {
+            call_stack_outer();
+        }
+        MultiTestSpec::GetEnv(name) => {
+            let len = unsafe { sys_getenv(null_mut(), 0, name.as_ptr(), name.len()) };
+            let value = (len != usize::MAX).then(|| {
+                let mut words = vec![0u32; len.div_ceil(WORD_SIZE)];
+                if len > 0 {
+                    unsafe {
+                        sys_getenv(words.as_mut_ptr(), words.len(), name.as_ptr(), name.len())
+                    };
+                }
+                bytemuck::cast_slice::<u32, u8>(&words)[..len].to_vec()
+            });
+            env::commit(&value);
+        }
+        MultiTestSpec::PauseCommit(value) => {
+            env::pause(0);
+            env::commit(&value);
+        }
+        MultiTestSpec::HotLoop { iters } => {
+            env::commit(&hot_loop(iters));
+        }
+        MultiTestSpec::JournalAndStderr { journal, stderr } => {
+            env::commit_slice(&journal);
+            unsafe { sys_write(fileno::STDERR, stderr.as_ptr(), stderr.len()) }
+        }
+        MultiTestSpec::Split(count) => {
+            for _ in 0..count {
+                env::split();
+            }
+        }
+        MultiTestSpec::CopyFd {
+            read_fd,
+            write_fd,
+            nbytes,
+        } => {
+            let mut buf = vec![0u8; nbytes as usize];
+            let nread = unsafe { sys_read(read_fd, buf.as_mut_ptr(), buf.len()) };
+            unsafe { sys_write(write_fd, buf.as_ptr(), nread) }
+            env::commit(&(nread as u32));
+        }
+        MultiTestSpec::Poseidon(elems) => {
+            let mut out = [0u32; poseidon::OUT_WORDS];
+            unsafe { sys_poseidon(&mut out, elems.as_ptr(), elems.len()) };
+            env::commit(&out);
+        }
+        MultiTestSpec::CommitFrames(frames) => {
+            for frame in frames {
+                env::commit_frame(&frame);
+            }
+        }
+        MultiTestSpec::CycleCount => {
+            let first = sys_cycle_count_u64();
+            let second = sys_cycle_count_u64();
+            env::commit(&(first, second));
+        }
+        MultiTestSpec::LogLevels { level, messages } => {
+            env::set_log_level(level);
+            for (level, msg) in messages {
+                env::log_at(level, &msg);
+            }
+        }
+        MultiTestSpec::ReadChannels { reads } => {
+            for (channel, nbytes) in reads {
+                let mut buf = vec![0u8; nbytes as usize];
+                let nread = unsafe { sys_read_channel(channel, buf.as_mut_ptr(), buf.len()) };
+                env::commit_slice(&buf[..nread]);
+            }
+        }
+        MultiTestSpec::SegmentRemaining { threshold } => {
+            // Start from a fresh segment, so that the budget only shrinks
+            // until the loop ends.
+            env::split();
+            let first = env::segment_cycles_remaining();
+            let mut last = first;
+            while last >= threshold {
+                last = env::segment_cycles_remaining();
+            }
+            env::split();
+            let after_split = env::segment_cycles_remaining();
+            env::commit(&(first, last, after_split));
+        }
+        MultiTestSpec::Clock => {
+            let nanos = env::clock_nanos();
+            env::commit(&(nanos, nanos / 1_000_000_000 % 86_400));
+        }
+        MultiTestSpec::IsReplay => {
+            let nanos = env::clock_nanos();
+            env::commit(&(nanos, env::is_replay()));
+        }
+        MultiTestSpec::Branch { taken } => {
+            if core::hint::black_box(taken) {
+                env::commit(&0xbeefu32);
+            } else {
+                env::log("not taken");
+            }
+        }
+        MultiTestSpec::SelfModify => {
+            // `nop; ret`, patched into `li a0, 42; ret`.
+            static mut CODE: [u32; 2] = [0x00000013, 0x00008067];
+            let (addr, value) = unsafe {
+                let code = core::ptr::addr_of_mut!(CODE) as *mut u32;
+                code.write_volatile(0x02a00513);
+                let f: extern "C" fn() -> u32 = core::mem::transmute(code);
+                (code as u32, f())
+            };
+            env::commit(&(addr, value));
+        }
+        MultiTestSpec::Yield { reason } => {
+            env::commit(&1u32);
+            env::yield_to_host(reason);
+            env::commit(&2u32);
+        }
+        MultiTestSpec::JournalChunks { chunks } => {
+            for chunk in chunks {
+                env::commit_slice(&chunk);
+                env::split();
+            }
+        }
     }
 }
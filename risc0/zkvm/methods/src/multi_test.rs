@@ -15,7 +15,7 @@
 // Definitions for test selection codes used by the "multi_test" test.
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 use risc0_zkvm::{declare_syscall, sha::Digest};
 use risc0_zkvm_platform::syscall::bigint;
@@ -36,6 +36,8 @@ pub enum MultiTestSpec {
     EventTrace,
     Profiler,
     Panic,
+    /// Abort with the given message
+    Abort(String),
     Fault,
     Halt(u8),
     PauseContinue(u8),
@@ -89,6 +91,77 @@ pub enum MultiTestSpec {
     TooManySha,
     AlignedAlloc,
     AllocZeroed,
+    SegmentPo2,
+    CallStackFault,
+    /// Look up an environment variable with `sys_getenv`, and commit its value
+    /// as an `Option<Vec<u8>>`
+    GetEnv(String),
+    /// Pause, then commit the given value once resumed
+    PauseCommit(u32),
+    HotLoop {
+        /// The number of iterations of the loop in `hot_loop`
+        iters: u32,
+    },
+    /// Commit `journal` to the journal, and write `stderr` to stderr
+    JournalAndStderr {
+        journal: Vec<u8>,
+        stderr: Vec<u8>,
+    },
+    /// Request a segment split the given number of times
+    Split(u32),
+    /// Read up to `nbytes` from `read_fd` with a single `sys_read`, write the
+    /// bytes read to `write_fd`, and commit how many there were as a `u32`
+    CopyFd {
+        read_fd: u32,
+        write_fd: u32,
+        nbytes: u32,
+    },
+    /// Hash the given baby bear field elements with the POSEIDON ecall, and
+    /// commit the output
+    Poseidon(Vec<u32>),
+    /// Send each of the given messages to the host with `env::commit_frame`
+    CommitFrames(Vec<Vec<u8>>),
+    /// Read the cycle count with `sys_cycle_count_u64` twice, and commit both
+    /// readings
+    CycleCount,
+    /// Set the log level to `level`, then log each of `messages` at its level
+    LogLevels {
+        level: u32,
+        messages: Vec<(u32, String)>,
+    },
+    /// For each `(channel, nbytes)`, read up to `nbytes` from the input channel
+    /// and commit the bytes read
+    ReadChannels {
+        reads: Vec<(u32, u32)>,
+    },
+    /// Commit a value derived from the time returned by sys_clock
+    Clock,
+    /// Split, spin until fewer than `threshold` cycles remain in the new
+    /// segment, then split again, and commit the remaining cycles read along
+    /// the way
+    SegmentRemaining {
+        threshold: u64,
+    },
+    /// Commit whether the host is replaying recorded syscalls, after a
+    /// syscall that is recorded
+    IsReplay,
+    /// Commit a value on one side of a conditional branch on `taken`, and log
+    /// a message on the other
+    Branch {
+        taken: bool,
+    },
+    /// Commit each of `chunks` to the journal, splitting the segment after
+    /// each one
+    JournalChunks {
+        chunks: Vec<Vec<u8>>,
+    },
+    /// Patch an instruction of a function in writable memory, call it, and
+    /// commit its address and the value it returns
+    SelfModify,
+    /// Commit 1, yield to the host with `reason`, and commit 2 once resumed
+    Yield {
+        reason: u8,
+    },
 }
 
 declare_syscall!(pub SYS_MULTI_TEST);
@@ -18,18 +18,234 @@ use core::{cmp::min, ptr::null_mut};
 
 use crate::WORD_SIZE;
 
+/// The ecall numbers, passed in `t0`.
+///
+/// Only [HALT](ecall::HALT), [INPUT](ecall::INPUT),
+/// [SOFTWARE](ecall::SOFTWARE), [SHA](ecall::SHA), and [BIGINT](ecall::BIGINT)
+/// are supported by the circuit. The others are only modeled by the executor:
+/// see [is_executor_only](ecall::is_executor_only). A session that uses any of
+/// them cannot be proven, and the prover rejects it up front.
 pub mod ecall {
     pub const HALT: u32 = 0;
     pub const INPUT: u32 = 1;
     pub const SOFTWARE: u32 = 2;
     pub const SHA: u32 = 3;
     pub const BIGINT: u32 = 4;
+
+    /// Charge a guest-specified number of cycles without doing any work.
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const BURN_CYCLES: u32 = 5;
+
+    /// Add fuel to the current session, up to the amount it started with.
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const ADD_FUEL: u32 = 6;
+
+    /// Compute the Keccak-256 digest of a region of guest memory.
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const KECCAK: u32 = 7;
+
+    /// Reduce a buffer of guest memory to a single value. See [super::reduce].
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const REDUCE: u32 = 8;
+
+    /// Compute the SHA-256 digest of a region of guest memory, including
+    /// padding.
+    ///
+    /// This is charged the same number of cycles as compressing the padded
+    /// message with [SHA]. Executor-only, see [the module docs](self).
+    pub const SHA_MEM: u32 = 9;
+
+    /// Compress blocks into a SHA-512 state. See [super::sha512].
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const SHA512: u32 = 10;
+
+    /// Compress several independent SHA-256 states in one call. See
+    /// [super::sha_batch].
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const SHA_BATCH: u32 = 11;
+
+    /// Hash baby bear field elements with Poseidon. See [super::poseidon].
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const POSEIDON: u32 = 12;
+
+    /// Compute `base^exponent mod N` over 256-bit integers. See
+    /// [sys_bigint_modexp](super::sys_bigint_modexp).
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const BIGINT_MODEXP: u32 = 13;
+
+    /// Compute a BigInt operation with the same registers as [BIGINT], but
+    /// accepting the ops and widths that [BIGINT] does not, e.g.
+    /// [OP_ADD](super::bigint::OP_ADD) or
+    /// [WIDTH_512](super::bigint::WIDTH_512). See
+    /// [sys_bigint_wide](super::sys_bigint_wide).
+    ///
+    /// Executor-only, see [the module docs](self).
+    pub const BIGINT_EXT: u32 = 14;
+
+    /// Whether `ecall` is only modeled by the executor, so that a session that
+    /// uses it cannot be proven.
+    pub const fn is_executor_only(ecall: u32) -> bool {
+        matches!(
+            ecall,
+            BURN_CYCLES
+                | ADD_FUEL
+                | KECCAK
+                | REDUCE
+                | SHA_MEM
+                | SHA512
+                | SHA_BATCH
+                | POSEIDON
+                | BIGINT_MODEXP
+                | BIGINT_EXT
+        )
+    }
+}
+
+/// Sizes used by the SHA512 ecall.
+///
+/// The register convention mirrors the SHA ecall:
+/// * `a0`: a pointer to the output state
+/// * `a1`: a pointer to the input state
+/// * `a2`: a pointer to the first half of the first block
+/// * `a3`: a pointer to the second half of the first block
+/// * `a4`: the number of blocks to compress
+///
+/// The state is stored as big-endian 64-bit words. Both block pointers advance
+/// by [BLOCK_BYTES](sha512::BLOCK_BYTES) after each block.
+pub mod sha512 {
+    /// The number of 64-bit words in the state.
+    pub const STATE_WORDS: usize = 8;
+
+    /// The size of the state in bytes.
+    pub const STATE_BYTES: usize = STATE_WORDS * 8;
+
+    /// The size of a block in bytes.
+    pub const BLOCK_BYTES: usize = 128;
+}
+
+/// Layout of the descriptors read by the SHA_BATCH ecall.
+///
+/// The register convention is:
+/// * `a0`: a word-aligned pointer to an array of descriptors
+/// * `a1`: the number of descriptors
+///
+/// Each descriptor is [DESCRIPTOR_WORDS](sha_batch::DESCRIPTOR_WORDS) words:
+/// 1. a pointer to the output state
+/// 2. a pointer to the input state
+/// 3. a pointer to the blocks, which are contiguous
+/// 4. the number of blocks to compress
+///
+/// Each descriptor is processed like a SHA ecall over its blocks,
+/// independently of the others, and in order, so a later descriptor observes
+/// the output state of an earlier one.
+pub mod sha_batch {
+    /// The number of words in a descriptor.
+    pub const DESCRIPTOR_WORDS: usize = 4;
+}
+
+/// Parameters of the POSEIDON ecall.
+///
+/// The register convention is:
+/// * `a0`: a word-aligned pointer to the output, which is
+///   [OUT_WORDS](poseidon::OUT_WORDS) words
+/// * `a1`: a word-aligned pointer to the input elements
+/// * `a2`: the number of input elements
+///
+/// Elements are stored one per word in canonical form, i.e. as integers less
+/// than [MODULUS](poseidon::MODULUS); the ecall fails on any other value.
+///
+/// The input is absorbed into a zeroed sponge of [RATE](poseidon::RATE) plus
+/// [CAPACITY](poseidon::CAPACITY) cells, [RATE](poseidon::RATE) elements at a
+/// time, each followed by a permutation. A final partial chunk, or an empty
+/// input, is permuted once more. The output is the first
+/// [OUT_WORDS](poseidon::OUT_WORDS) cells of the state. No padding or length
+/// is absorbed, so only inputs of the same length are collision resistant.
+/// This matches `risc0_zkp::core::hash::poseidon::unpadded_hash`.
+pub mod poseidon {
+    /// The modulus of the baby bear field, `15 * 2^27 + 1`.
+    pub const MODULUS: u32 = 15 * (1 << 27) + 1;
+
+    /// The number of elements absorbed per permutation.
+    pub const RATE: usize = 16;
+
+    /// The number of cells of the state that input is never absorbed into.
+    pub const CAPACITY: usize = 8;
+
+    /// The number of elements written to the output.
+    pub const OUT_WORDS: usize = 8;
+}
+
+/// Status codes returned in `a1` by [nr::SYS_GETENV].
+pub mod getenv {
+    /// The variable is set, and `a0` is the length of its value.
+    pub const FOUND: u32 = 0;
+
+    /// The variable is not set, and `a0` is `u32::MAX`, as it was before this
+    /// status was reported.
+    pub const NOT_FOUND: u32 = 1;
+}
+
+/// Operations and element widths supported by the REDUCE ecall.
+///
+/// The register convention is:
+/// * `a0`: the operation, one of the `OP_*` constants
+/// * `a1`: a word-aligned pointer to the elements
+/// * `a2`: the element width, one of the `WIDTH_*` constants
+/// * `a3`: the number of elements
+/// * `a4`, `a5`: the low and high words of the modulus `N` for [OP_PRODUCT]
+///
+/// The result is returned in `a0` (low word) and `a1` (high word). Reducing
+/// an empty buffer returns the identity element of the operation.
+pub mod reduce {
+    /// Wrapping sum of the elements.
+    pub const OP_SUM: u32 = 0;
+
+    /// Product of the elements modulo `N`, or wrapping product if `N == 0`.
+    pub const OP_PRODUCT: u32 = 1;
+
+    /// Minimum of the elements.
+    pub const OP_MIN: u32 = 2;
+
+    /// Maximum of the elements.
+    pub const OP_MAX: u32 = 3;
+
+    /// Bitwise xor of the elements.
+    pub const OP_XOR: u32 = 4;
+
+    /// Elements are little-endian `u32`s.
+    pub const WIDTH_U32: u32 = 4;
+
+    /// Elements are little-endian `u64`s.
+    pub const WIDTH_U64: u32 = 8;
+}
+
+/// Verbosity levels of the messages logged with [sys_log_at], from least to
+/// most verbose. See [nr::SYS_LOG_LEVEL].
+pub mod log_level {
+    /// As a threshold, drop all messages.
+    pub const OFF: u32 = 0;
+    pub const ERROR: u32 = 1;
+    pub const WARN: u32 = 2;
+    pub const INFO: u32 = 3;
+    pub const DEBUG: u32 = 4;
+    pub const TRACE: u32 = 5;
 }
 
 pub mod halt {
     pub const TERMINATE: u32 = 0;
     pub const PAUSE: u32 = 1;
     pub const SPLIT: u32 = 2;
+
+    /// The user exit code with which [super::sys_panic] halts the guest.
+    pub const PANIC_EXIT_CODE: u8 = 101;
 }
 
 pub mod reg_abi {
@@ -84,6 +300,47 @@ pub const MAX_SHA_COMPRESS_BLOCKS: usize = 1000;
 pub mod bigint {
     pub const OP_MULTIPLY: u32 = 0;
 
+    /// Modular addition, `z = x + y mod N`, or wrapping addition if `N == 0`.
+    ///
+    /// Only accepted by the executor-only
+    /// [BIGINT_EXT](super::ecall::BIGINT_EXT) ecall.
+    pub const OP_ADD: u32 = 1;
+
+    /// Modular subtraction, `z = x - y mod N`, or wrapping subtraction if
+    /// `N == 0`.
+    ///
+    /// Only accepted by the executor-only
+    /// [BIGINT_EXT](super::ecall::BIGINT_EXT) ecall.
+    pub const OP_SUB: u32 = 2;
+
+    /// Bits of the BigInt `op` that select the operation. The bits above them
+    /// select the operand width, e.g. `OP_MULTIPLY | WIDTH_512`.
+    pub const OP_MASK: u32 = 0xff;
+
+    /// Operands of [WIDTH_BITS] bits, the width used when no width is
+    /// selected.
+    pub const WIDTH_256: u32 = 0;
+
+    /// Operands of 384 bits.
+    ///
+    /// Widths other than [WIDTH_256] are only accepted by the executor-only
+    /// [BIGINT_EXT](super::ecall::BIGINT_EXT) ecall. The executor charges a multiplication once for
+    /// each pair of 256-bit chunks of the operands and an addition or
+    /// subtraction once for each chunk.
+    pub const WIDTH_384: u32 = 1 << 8;
+
+    /// Operands of 512 bits. See [WIDTH_384].
+    pub const WIDTH_512: u32 = 2 << 8;
+
+    /// Operands of 1024 bits. See [WIDTH_384].
+    pub const WIDTH_1024: u32 = 3 << 8;
+
+    /// Operands of 2048 bits. See [WIDTH_384].
+    pub const WIDTH_2048: u32 = 4 << 8;
+
+    /// Operands of 4096 bits. See [WIDTH_384].
+    pub const WIDTH_4096: u32 = 5 << 8;
+
     /// BigInt width, in bits, handled by the BigInt accelerator circuit.
     pub const WIDTH_BITS: usize = 256;
 
@@ -126,14 +383,22 @@ macro_rules! declare_syscall {
 }
 
 pub mod nr {
+    declare_syscall!(pub SYS_COMMIT);
     declare_syscall!(pub SYS_CYCLE_COUNT);
     declare_syscall!(pub SYS_GETENV);
+    declare_syscall!(pub SYS_IS_REPLAY);
     declare_syscall!(pub SYS_ARGC);
     declare_syscall!(pub SYS_ARGV);
+    declare_syscall!(pub SYS_CLOCK);
     declare_syscall!(pub SYS_LOG);
+    declare_syscall!(pub SYS_LOG_LEVEL);
     declare_syscall!(pub SYS_PANIC);
     declare_syscall!(pub SYS_RANDOM);
     declare_syscall!(pub SYS_READ);
+    declare_syscall!(pub SYS_READ_CHANNEL);
+    declare_syscall!(pub SYS_SEGMENT_PO2);
+    declare_syscall!(pub SYS_SEGMENT_REMAINING);
+    declare_syscall!(pub SYS_SPLIT);
     declare_syscall!(pub SYS_WRITE);
     declare_syscall!(pub SYS_VERIFY);
     declare_syscall!(pub SYS_VERIFY_INTEGRITY);
@@ -378,6 +643,220 @@ pub unsafe extern "C" fn sys_bigint(
     );
 }
 
+/// Compute a BigInt `op` into `result` on operands wider than 256 bits, as
+/// selected by the width bits of `op`, e.g. `bigint::OP_MULTIPLY |
+/// bigint::WIDTH_512`.
+///
+/// This issues the [BIGINT_EXT](ecall::BIGINT_EXT) ecall, which also accepts
+/// [OP_ADD](bigint::OP_ADD) and [OP_SUB](bigint::OP_SUB) at any width,
+/// including 256 bits. Executor-only, see [ecall]; use [sys_bigint] to
+/// multiply 256-bit operands in a provable session.
+///
+/// # Safety
+///
+/// `result`, `x`, `y`, and `modulus` must be aligned and dereferenceable for
+/// the selected width.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_bigint_wide(
+    result: *mut u32,
+    op: u32,
+    x: *const u32,
+    y: *const u32,
+    modulus: *const u32,
+) {
+    ecall_4(
+        ecall::BIGINT_EXT,
+        result as u32,
+        op,
+        x as u32,
+        y as u32,
+        modulus as u32,
+    );
+}
+
+/// Compute `base^exponent mod modulus` into `result`, as little-endian 256-bit
+/// integers.
+///
+/// The modulus must not be zero. This is charged the cycles of a BigInt
+/// multiplication for each squaring and multiplication: two for every bit of
+/// the exponent, and one to reduce the base. Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `result`, `base`, `exponent`, and `modulus` must be aligned and
+/// dereferenceable.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_bigint_modexp(
+    result: *mut [u32; bigint::WIDTH_WORDS],
+    base: *const [u32; bigint::WIDTH_WORDS],
+    exponent: *const [u32; bigint::WIDTH_WORDS],
+    modulus: *const [u32; bigint::WIDTH_WORDS],
+) {
+    ecall_4(
+        ecall::BIGINT_MODEXP,
+        result as u32,
+        base as u32,
+        exponent as u32,
+        modulus as u32,
+        0,
+    );
+}
+
+/// Charge `cycles` additional cycles to the current session without executing
+/// anything, e.g. to model the latency of an operation in cycle-budget terms.
+///
+/// Burned cycles count toward segment splits exactly like real cycles.
+/// Executor-only, see [ecall].
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_burn_cycles(cycles: u32) {
+    ecall_1(ecall::BURN_CYCLES, cycles, 0);
+}
+
+/// Compute the Keccak-256 digest of `len` bytes at `data` into `out`.
+///
+/// Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `data` must be dereferenceable for `len` bytes and `out` must be
+/// dereferenceable.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_keccak256(data: *const u8, len: usize, out: *mut [u8; DIGEST_BYTES]) {
+    ecall_4(ecall::KECCAK, data as u32, len as u32, out as u32, 0, 0);
+}
+
+/// Compress `count` consecutive 128-byte blocks at `buf` into the SHA-512
+/// state `in_state`, writing the result to `out_state`.
+///
+/// Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `out_state` and `in_state` must be dereferenceable, and `buf` must be
+/// dereferenceable for `count` blocks.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_sha512_buffer(
+    out_state: *mut [u8; sha512::STATE_BYTES],
+    in_state: *const [u8; sha512::STATE_BYTES],
+    buf: *const u8,
+    count: u32,
+) {
+    ecall_4(
+        ecall::SHA512,
+        out_state as u32,
+        in_state as u32,
+        buf as u32,
+        buf.add(sha512::BLOCK_BYTES / 2) as u32,
+        count,
+    );
+}
+
+/// Compress each of the `count` descriptors at `descriptors`, as described in
+/// [sha_batch].
+///
+/// Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `descriptors` must be aligned and dereferenceable for `count` descriptors,
+/// and the states and blocks they point to must be aligned and
+/// dereferenceable.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_sha_batch(
+    descriptors: *const [u32; sha_batch::DESCRIPTOR_WORDS],
+    count: usize,
+) {
+    ecall_4(ecall::SHA_BATCH, descriptors as u32, count as u32, 0, 0, 0);
+}
+
+/// Hash `count` baby bear field elements at `data` with Poseidon into `out`.
+///
+/// See [poseidon] for the parameters of the sponge. Executor-only, see
+/// [ecall].
+///
+/// # Safety
+///
+/// `data` must be aligned and dereferenceable for `count` words and `out`
+/// must be aligned and dereferenceable.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_poseidon(
+    out: *mut [u32; poseidon::OUT_WORDS],
+    data: *const u32,
+    count: usize,
+) {
+    ecall_4(ecall::POSEIDON, out as u32, data as u32, count as u32, 0, 0);
+}
+
+/// Compute the SHA-256 digest of `len` bytes at `data` into `out`.
+///
+/// Unlike [sys_sha_buffer], the message does not need to be padded by the
+/// caller. Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `data` must be dereferenceable for `len` bytes and `out` must be
+/// dereferenceable.
+#[inline(always)]
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_sha_mem(data: *const u8, len: usize, out: *mut [u8; DIGEST_BYTES]) {
+    ecall_4(ecall::SHA_MEM, data as u32, len as u32, out as u32, 0, 0);
+}
+
+/// Reduce `count` elements of the given `width` at `ptr` with `op`, as
+/// described in [reduce].
+///
+/// Executor-only, see [ecall].
+///
+/// # Safety
+///
+/// `ptr` must be aligned and dereferenceable for `count` elements.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_reduce(
+    op: u32,
+    ptr: *const u32,
+    width: u32,
+    count: usize,
+    modulus: u64,
+) -> u64 {
+    #[cfg(target_os = "zkvm")]
+    {
+        let lo: u32;
+        let hi: u32;
+        asm!(
+            "ecall",
+            in("t0") ecall::REDUCE,
+            inlateout("a0") op => lo,
+            inlateout("a1") ptr => hi,
+            in("a2") width,
+            in("a3") count,
+            in("a4") modulus as u32,
+            in("a5") (modulus >> 32) as u32,
+        );
+        ((hi as u64) << 32) | lo as u64
+    }
+    #[cfg(not(target_os = "zkvm"))]
+    {
+        core::hint::black_box((op, ptr, width, count, modulus));
+        unimplemented!()
+    }
+}
+
+/// Refill `amount` units of fuel, when the host meters execution with fuel.
+///
+/// Fuel cannot be refilled beyond the initial amount configured by the host,
+/// and this is a no-op when execution is not metered. Executor-only, see
+/// [ecall].
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_add_fuel(amount: u32) {
+    ecall_1(ecall::ADD_FUEL, amount, 0);
+}
+
 /// # Safety
 ///
 /// `recv_buf` must be aligned and dereferenceable.
@@ -386,17 +865,16 @@ pub unsafe extern "C" fn sys_rand(recv_buf: *mut u32, words: usize) {
     syscall_0(nr::SYS_RANDOM, recv_buf, words);
 }
 
+/// Report the panic message at `msg_ptr` to the host, and then halt with
+/// [halt::PANIC_EXIT_CODE] and no output.
+///
 /// # Safety
 ///
 /// `msg_ptr` must be aligned and dereferenceable.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_panic(msg_ptr: *const u8, len: usize) -> ! {
     syscall_2(nr::SYS_PANIC, null_mut(), 0, msg_ptr as u32, len as u32);
-
-    // As a fallback for non-compliant hosts, issue an illegal instruction.
-    #[cfg(target_os = "zkvm")]
-    asm!("sw x0, 1(x0)");
-    unreachable!()
+    sys_halt(halt::PANIC_EXIT_CODE, &[0; DIGEST_WORDS])
 }
 
 /// # Safety
@@ -404,15 +882,120 @@ pub unsafe extern "C" fn sys_panic(msg_ptr: *const u8, len: usize) -> ! {
 /// `msg_ptr` must be aligned and dereferenceable.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_log(msg_ptr: *const u8, len: usize) {
-    syscall_2(nr::SYS_LOG, null_mut(), 0, msg_ptr as u32, len as u32);
+    sys_log_at(log_level::INFO, msg_ptr, len)
 }
 
+/// Log the message at `msg_ptr` at the given [log_level].
+///
+/// The host drops the message if it is more verbose than the threshold set
+/// with [sys_log_level].
+///
+/// # Safety
+///
+/// `msg_ptr` must be aligned and dereferenceable.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_log_at(level: u32, msg_ptr: *const u8, len: usize) {
+    syscall_3(
+        nr::SYS_LOG,
+        null_mut(),
+        0,
+        msg_ptr as u32,
+        len as u32,
+        level,
+    );
+}
+
+/// Set the most verbose [log_level] of the messages the host keeps for the
+/// rest of the session, and return the previous one.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_log_level(level: u32) -> u32 {
+    let Return(prev, _) = unsafe { syscall_1(nr::SYS_LOG_LEVEL, null_mut(), 0, level) };
+    prev
+}
+
+/// Send the `len` bytes at `buf` to the host as a single message, which the
+/// host can observe before the guest halts.
+///
+/// Unlike the journal, each call is kept as a separate message, and the
+/// messages are not part of the receipt.
+///
+/// # Safety
+///
+/// `buf` must be dereferenceable for `len` bytes.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_commit(buf: *const u8, len: usize) {
+    syscall_2(nr::SYS_COMMIT, null_mut(), 0, buf as u32, len as u32);
+}
+
+/// Returns the number of user cycles executed so far in the session,
+/// truncated to 32 bits. See [sys_cycle_count_u64].
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub extern "C" fn sys_cycle_count() -> usize {
     let Return(a0, _) = unsafe { syscall_0(nr::SYS_CYCLE_COUNT, null_mut(), 0) };
     a0 as usize
 }
 
+/// Returns the number of user cycles executed so far in the session.
+///
+/// The host returns the low word in `a0` and the high word in `a1`. The count
+/// excludes the cycles of this syscall itself, so two consecutive reads differ
+/// by at least the cost of the first one.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_cycle_count_u64() -> u64 {
+    let Return(lo, hi) = unsafe { syscall_0(nr::SYS_CYCLE_COUNT, null_mut(), 0) };
+    (hi as u64) << 32 | lo as u64
+}
+
+/// Returns the time configured by the host, in nanoseconds since the Unix
+/// epoch.
+///
+/// This is not the wall-clock time: the host returns the same fixed value on
+/// every call, so that the guest stays deterministic. It is returned in `a0`
+/// (low word) and `a1` (high word).
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_clock() -> u64 {
+    let Return(lo, hi) = unsafe { syscall_0(nr::SYS_CLOCK, null_mut(), 0) };
+    (hi as u64) << 32 | lo as u64
+}
+
+/// Returns the segment limit configured by the host, as a power of 2 cycles.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_segment_po2() -> u32 {
+    let Return(a0, _) = unsafe { syscall_0(nr::SYS_SEGMENT_PO2, null_mut(), 0) };
+    a0
+}
+
+/// Returns the number of cycles left in the current segment before the host
+/// splits it, or zero if it is already full.
+///
+/// The value is approximate: the host reads it partway through this syscall,
+/// which itself consumes cycles, and paging can add cycles that depend on the
+/// memory touched later. The host returns the low word in `a0` and the high
+/// word in `a1`.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_segment_remaining() -> u64 {
+    let Return(lo, hi) = unsafe { syscall_0(nr::SYS_SEGMENT_REMAINING, null_mut(), 0) };
+    (hi as u64) << 32 | lo as u64
+}
+
+/// Returns 1 if the host is replaying recorded syscalls, and 0 if it is
+/// running live.
+///
+/// The host answers this syscall itself: it is never recorded, nor taken from
+/// a recording.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_is_replay() -> u32 {
+    let Return(a0, _) = unsafe { syscall_0(nr::SYS_IS_REPLAY, null_mut(), 0) };
+    a0
+}
+
+/// Ends the current segment at the next instruction, as if the segment limit
+/// had been reached, and continues execution in a new segment.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub extern "C" fn sys_split() {
+    unsafe { syscall_0(nr::SYS_SPLIT, null_mut(), 0) };
+}
+
 /// Reads the given number of bytes into the given buffer, posix-style.  Returns
 /// the number of bytes actually read.  On end of file, returns 0.
 ///
@@ -427,6 +1010,26 @@ pub extern "C" fn sys_cycle_count() -> usize {
 /// `recv_ptr` must be aligned and dereferenceable.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_read(fd: u32, recv_ptr: *mut u8, nread: usize) -> usize {
+    read_bytes(nr::SYS_READ, fd, recv_ptr, nread)
+}
+
+/// Reads the given number of bytes from the host input channel `channel` into
+/// the given buffer, with the same semantics as [sys_read].
+///
+/// Each channel is an independent stream with its own read position, so
+/// reading from one channel never consumes the input of another.
+///
+/// # Safety
+///
+/// `recv_ptr` must be aligned and dereferenceable.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_read_channel(channel: u32, recv_ptr: *mut u8, nread: usize) -> usize {
+    read_bytes(nr::SYS_READ_CHANNEL, channel, recv_ptr, nread)
+}
+
+// Implements a byte-aligned read from `fd` on top of a word-aligned read
+// syscall such as SYS_READ.
+unsafe fn read_bytes(syscall: SyscallName, fd: u32, recv_ptr: *mut u8, nread: usize) -> usize {
     // The SYS_READ system call can do a given number of word-aligned reads
     // efficiently. The semantics of the system call are:
     //
@@ -460,7 +1063,7 @@ pub unsafe extern "C" fn sys_read(fd: u32, recv_ptr: *mut u8, nread: usize) -> u
         let unaligned_at_start = min(nread, WORD_SIZE - ptr_offset);
         // Read unaligned bytes into "firstword".
         let Return(nread_first, firstword) =
-            syscall_2(nr::SYS_READ, null_mut(), 0, fd, unaligned_at_start as u32);
+            syscall_2(syscall, null_mut(), 0, fd, unaligned_at_start as u32);
         debug_assert_eq!(nread_first as usize, unaligned_at_start);
 
         // Align up to a word boundary to do the main copy.
@@ -474,8 +1077,13 @@ pub unsafe extern "C" fn sys_read(fd: u32, recv_ptr: *mut u8, nread: usize) -> u
 
     // Copy in all of the word-aligned data
     let main_words = main_requested / WORD_SIZE;
-    let (nread_main, lastword) =
-        sys_read_internal(fd, main_ptr as *mut u32, main_words, main_requested);
+    let (nread_main, lastword) = sys_read_internal(
+        syscall,
+        fd,
+        main_ptr as *mut u32,
+        main_words,
+        main_requested,
+    );
     debug_assert!(nread_main <= main_requested);
     let read_words = nread_main / WORD_SIZE;
 
@@ -512,10 +1120,16 @@ pub unsafe extern "C" fn sys_read(fd: u32, recv_ptr: *mut u8, nread: usize) -> u
 /// `nwords' size.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_read_words(fd: u32, recv_ptr: *mut u32, nwords: usize) -> usize {
-    sys_read_internal(fd, recv_ptr, nwords, nwords * WORD_SIZE).0
+    sys_read_internal(nr::SYS_READ, fd, recv_ptr, nwords, nwords * WORD_SIZE).0
 }
 
-fn sys_read_internal(fd: u32, recv_ptr: *mut u32, nwords: usize, nbytes: usize) -> (usize, u32) {
+fn sys_read_internal(
+    syscall: SyscallName,
+    fd: u32,
+    recv_ptr: *mut u32,
+    nwords: usize,
+    nbytes: usize,
+) -> (usize, u32) {
     let mut nwords_remain = nwords;
     let mut nbytes_remain = nbytes;
     let mut nread_total_bytes = 0;
@@ -529,7 +1143,7 @@ fn sys_read_internal(fd: u32, recv_ptr: *mut u32, nwords: usize, nbytes: usize)
         let chunk_len = min(nbytes_remain, MAX_BUF_BYTES) as u32;
         let Return(nread_bytes, last_word) = unsafe {
             syscall_2(
-                nr::SYS_READ,
+                syscall,
                 recv_ptr,
                 min(nwords_remain, MAX_BUF_WORDS),
                 fd,
@@ -578,7 +1192,8 @@ pub unsafe extern "C" fn sys_write(fd: u32, write_ptr: *const u8, nbytes: usize)
 /// out_nwords).
 ///
 /// Returns the length of the value, in bytes, or usize::MAX if the variable is
-/// not set.
+/// not set. The value is an arbitrary byte string, which is not necessarily
+/// valid UTF-8.
 ///
 /// This is normally called twice to read an environment variable:
 /// Once to get the length of the value, and once to fill in allocated
@@ -597,14 +1212,16 @@ pub unsafe extern "C" fn sys_getenv(
     varname: *const u8,
     varname_len: usize,
 ) -> usize {
-    let Return(a0, _) = syscall_2(
+    let Return(a0, a1) = syscall_2(
         nr::SYS_GETENV,
         out_words,
         out_nwords,
         varname as u32,
         varname_len as u32,
     );
-    if a0 == u32::MAX {
+    // Hosts that predate the status in `a1` only report a missing variable
+    // with `a0`.
+    if a1 == getenv::NOT_FOUND || a0 == u32::MAX {
         usize::MAX
     } else {
         a0 as usize
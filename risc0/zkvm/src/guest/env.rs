@@ -74,8 +74,10 @@ use bytemuck::Pod;
 use risc0_zkvm_platform::{
     align_up, fileno,
     syscall::{
-        self, sys_alloc_words, sys_cycle_count, sys_halt, sys_log, sys_pause, sys_read,
-        sys_read_words, sys_verify, sys_verify_integrity, sys_write, syscall_2, SyscallName,
+        self, sys_alloc_words, sys_clock, sys_commit, sys_cycle_count, sys_halt, sys_is_replay,
+        sys_log, sys_log_at, sys_log_level, sys_pause, sys_read, sys_read_words, sys_segment_po2,
+        sys_segment_remaining, sys_split, sys_verify, sys_verify_integrity, sys_write, syscall_2,
+        SyscallName,
     },
     WORD_SIZE,
 };
@@ -543,6 +545,91 @@ pub fn cycle_count() -> usize {
     sys_cycle_count()
 }
 
+/// Return the time configured by the host, in nanoseconds since the Unix
+/// epoch.
+///
+/// The host returns a fixed value, zero unless it was set with
+/// `ExecutorEnvBuilder::clock`, so the time does not advance during execution.
+///
+/// WARNING: The time is provided by the host and is not checked by the zkVM
+/// circuit.
+pub fn clock_nanos() -> u64 {
+    sys_clock()
+}
+
+/// Return the segment limit configured by the host, as a power of 2 cycles.
+///
+/// A guest can use this to size its work so that it lines up with segment
+/// boundaries, i.e. chunks of at most `2^po2` cycles. Doing so couples the
+/// guest's behavior to a setting chosen by the host: the same guest run with
+/// a different segment limit may take a different path.
+///
+/// WARNING: The segment limit is provided by the host and is not checked by
+/// the zkVM circuit.
+pub fn segment_po2() -> u32 {
+    sys_segment_po2()
+}
+
+/// Return the approximate number of cycles left in the current segment before
+/// the host splits it.
+///
+/// A guest can use this to [split] early rather than have a unit of work
+/// straddle a segment boundary. The value is approximate, since reading it
+/// takes cycles and paging in memory adds more.
+///
+/// WARNING: The value is provided by the host and is not checked by the zkVM
+/// circuit.
+pub fn segment_cycles_remaining() -> u64 {
+    sys_segment_remaining()
+}
+
+/// Return true if the host is replaying syscalls recorded in an earlier run,
+/// rather than answering them live.
+///
+/// A guest can use this to skip work that only makes sense live, e.g.
+/// assertions on non-deterministic host input. Anything it does differently
+/// changes its journal, so the replayed session no longer matches the recorded
+/// one.
+///
+/// WARNING: The replay state is provided by the host and is not checked by the
+/// zkVM circuit.
+pub fn is_replay() -> bool {
+    sys_is_replay() != 0
+}
+
+/// End the current segment and continue execution in a new one.
+///
+/// Segments are normally split when they reach the segment limit. Placing a
+/// boundary deliberately, e.g. before an expensive computation, can balance
+/// the proving work across segments. Each split adds the fixed overhead of a
+/// segment, so this should be used sparingly.
+pub fn split() {
+    sys_split()
+}
+
+/// Pause execution and hand control back to the host, which sees
+/// [ExitCode::Paused] with `reason` as its code.
+///
+/// Execution continues from this call when the host resumes the executor.
+/// This is a [pause]: the output is committed at the yield, so the journal
+/// written before it belongs to the paused session, and the resumed session
+/// starts a new one. Both sessions can be proven.
+pub fn yield_to_host(reason: u8) {
+    pause(reason)
+}
+
+/// Send `bytes` to the host as a single message, which the host can observe
+/// before the guest halts, e.g. to stream intermediate results.
+///
+/// Each call is kept as a separate message in the session, unlike data
+/// written to the journal, which is a single continuous stream.
+///
+/// WARNING: These messages are not part of the journal, so they are not
+/// committed to by the receipt.
+pub fn commit_frame(bytes: &[u8]) {
+    unsafe { sys_commit(bytes.as_ptr(), bytes.len()) }
+}
+
 /// Print a message to the debug console.
 pub fn log(msg: &str) {
     let msg = msg.as_bytes();
@@ -551,6 +638,24 @@ pub fn log(msg: &str) {
     }
 }
 
+/// Print a message to the debug console at the given
+/// [log_level](risc0_zkvm_platform::syscall::log_level).
+///
+/// Messages logged with [log] are at the info level.
+pub fn log_at(level: u32, msg: &str) {
+    let msg = msg.as_bytes();
+    unsafe {
+        sys_log_at(level, msg.as_ptr(), msg.len());
+    }
+}
+
+/// Set the most verbose level of the messages the host keeps for the rest of
+/// the session, e.g. to turn on debug messages once a condition is met.
+/// Returns the previous level.
+pub fn set_log_level(level: u32) -> u32 {
+    sys_log_level(level)
+}
+
 /// Return a writer for STDOUT.
 pub fn stdout() -> FdWriter<impl for<'a> Fn(&'a [u8])> {
     FdWriter::new(fileno::STDOUT, |_| {})
@@ -104,8 +104,7 @@ fn _fault() -> ! {
 
 /// Aborts the guest with the given message.
 pub fn abort(msg: &str) -> ! {
-    // SAFETY: A compliant host should fault when it receives this syscall.
-    // sys_panic will issue an invalid instruction for non-compliant hosts.
+    // SAFETY: sys_panic reports the message to the host and then halts.
     unsafe {
         sys_panic(msg.as_ptr(), msg.len());
     }
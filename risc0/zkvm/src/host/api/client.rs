@@ -14,7 +14,7 @@
 
 use std::path::Path;
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use bytes::Bytes;
 use prost::Message;
 
@@ -394,7 +394,15 @@ impl Client {
     ) -> Result<pb::api::ExecutorEnv> {
         Ok(pb::api::ExecutorEnv {
             binary: Some(binary),
-            env_vars: env.env_vars.clone(),
+            env_vars: env
+                .env_vars
+                .iter()
+                .map(|(name, val)| {
+                    let val = String::from_utf8(val.clone())
+                        .with_context(|| format!("env var {name} is not valid UTF-8"))?;
+                    Ok((name.clone(), val))
+                })
+                .collect::<Result<_>>()?,
             args: env.args.clone(),
             slice_ios: env.slice_io.borrow().inner.keys().cloned().collect(),
             read_fds: env.posix_io.borrow().read_fds.keys().cloned().collect(),
@@ -492,6 +500,7 @@ impl Client {
                                         .exit_code
                                         .ok_or(malformed_err())?
                                         .try_into()?,
+                                    panic_message: session.panic_message,
                                 }),
                                 None => Err(malformed_err()),
                             }
@@ -118,6 +118,36 @@ impl From<TraceEvent> for pb::api::TraceEvent {
                     },
                 )),
             },
+            TraceEvent::SyscallStart { name, cycle } => Self {
+                kind: Some(pb::api::trace_event::Kind::SyscallStart(
+                    pb::api::trace_event::SyscallStart { name, cycle },
+                )),
+            },
+            TraceEvent::SyscallEnd {
+                name,
+                to_guest_words,
+                a0,
+                a1,
+            } => Self {
+                kind: Some(pb::api::trace_event::Kind::SyscallEnd(
+                    pb::api::trace_event::SyscallEnd {
+                        name,
+                        to_guest_words,
+                        a0,
+                        a1,
+                    },
+                )),
+            },
+            TraceEvent::PageIn { page_idx, cycle } => Self {
+                kind: Some(pb::api::trace_event::Kind::PageIn(
+                    pb::api::trace_event::PageIn { page_idx, cycle },
+                )),
+            },
+            TraceEvent::PageOut { page_idx, cycle } => Self {
+                kind: Some(pb::api::trace_event::Kind::PageOut(
+                    pb::api::trace_event::PageOut { page_idx, cycle },
+                )),
+            },
         }
     }
 }
@@ -140,6 +170,24 @@ impl TryFrom<pb::api::TraceEvent> for TraceEvent {
                 addr: event.addr,
                 region: event.region,
             },
+            pb::api::trace_event::Kind::SyscallStart(event) => TraceEvent::SyscallStart {
+                name: event.name,
+                cycle: event.cycle,
+            },
+            pb::api::trace_event::Kind::SyscallEnd(event) => TraceEvent::SyscallEnd {
+                name: event.name,
+                to_guest_words: event.to_guest_words,
+                a0: event.a0,
+                a1: event.a1,
+            },
+            pb::api::trace_event::Kind::PageIn(event) => TraceEvent::PageIn {
+                page_idx: event.page_idx,
+                cycle: event.cycle,
+            },
+            pb::api::trace_event::Kind::PageOut(event) => TraceEvent::PageOut {
+                page_idx: event.page_idx,
+                cycle: event.cycle,
+            },
         })
     }
 }
@@ -150,6 +198,8 @@ impl From<ExitCode> for pb::base::ExitCode {
             kind: Some(match value {
                 ExitCode::SystemSplit => pb::base::exit_code::Kind::SystemSplit(()),
                 ExitCode::SessionLimit => pb::base::exit_code::Kind::SessionLimit(()),
+                ExitCode::OutOfFuel => pb::base::exit_code::Kind::OutOfFuel(()),
+                ExitCode::FuelExhausted => pb::base::exit_code::Kind::FuelExhausted(()),
                 ExitCode::Paused(code) => pb::base::exit_code::Kind::Paused(code),
                 ExitCode::Halted(code) => pb::base::exit_code::Kind::Halted(code),
             }),
@@ -166,6 +216,8 @@ impl TryFrom<pb::base::ExitCode> for ExitCode {
             pb::base::exit_code::Kind::Paused(code) => Self::Paused(code),
             pb::base::exit_code::Kind::SystemSplit(_) => Self::SystemSplit,
             pb::base::exit_code::Kind::SessionLimit(_) => Self::SessionLimit,
+            pb::base::exit_code::Kind::OutOfFuel(_) => Self::OutOfFuel,
+            pb::base::exit_code::Kind::FuelExhausted(_) => Self::FuelExhausted,
         })
     }
 }
@@ -300,6 +300,13 @@ pub struct SessionInfo {
 
     /// The [ExitCode] of the session.
     pub exit_code: ExitCode,
+
+    /// The message the guest panicked with, if it did.
+    ///
+    /// A panicking guest halts with
+    /// [PANIC_EXIT_CODE](risc0_zkvm_platform::syscall::halt::PANIC_EXIT_CODE),
+    /// and this is the only place its message is reported.
+    pub panic_message: Option<String>,
 }
 
 /// Provides information about a segment of execution.
@@ -311,6 +311,7 @@ impl Server {
                                 segments: session.segments.len().try_into()?,
                                 journal: session.journal.unwrap_or_default().bytes,
                                 exit_code: Some(session.exit_code.into()),
+                                panic_message: session.panic_message,
                             }),
                         },
                     )),
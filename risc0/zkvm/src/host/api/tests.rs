@@ -23,13 +23,14 @@ use risc0_zkvm_methods::{
     multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
     MULTI_TEST_PATH,
 };
+use risc0_zkvm_platform::syscall::halt::PANIC_EXIT_CODE;
 use tempfile::{tempdir, TempDir};
 use test_log::test;
 
 use super::{Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnection};
 use crate::{
-    recursion::SuccinctReceipt, ApiClient, ApiServer, ExecutorEnv, InnerReceipt, ProverOpts,
-    Receipt, SegmentReceipt, SessionInfo, VerifierContext,
+    recursion::SuccinctReceipt, ApiClient, ApiServer, ExecutorEnv, ExitCode, InnerReceipt,
+    ProverOpts, Receipt, SegmentReceipt, SessionInfo, VerifierContext,
 };
 
 struct TestClientConnector {
@@ -308,13 +309,29 @@ fn lift_resolve() {
 }
 
 #[test]
-#[should_panic(expected = "MultiTestSpec::Panic invoked")]
-fn guest_error_forwarding() {
+fn guest_panic_forwarding() {
     let env = ExecutorEnv::builder()
         .write(&MultiTestSpec::Panic)
         .unwrap()
         .build()
         .unwrap();
     let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    let session = TestClient::new().execute(env, binary);
+    assert_eq!(session.exit_code, ExitCode::Halted(PANIC_EXIT_CODE as u32));
+    assert!(session
+        .panic_message
+        .unwrap()
+        .contains("MultiTestSpec::Panic invoked"));
+}
+
+#[test]
+#[should_panic(expected = "StoreAccessFault")]
+fn guest_error_forwarding() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Fault)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
     TestClient::new().execute(env, binary);
 }
@@ -19,14 +19,17 @@ use std::{
     collections::HashMap,
     io::{BufRead, BufReader, Cursor, Read, Write},
     mem,
+    ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
-    sync::Arc,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
 
 use anyhow::Result;
 use bytemuck::Pod;
 use bytes::Bytes;
+use rand_chacha::{rand_core::SeedableRng as _, ChaCha20Rng};
 use risc0_zkvm_platform::{self, fileno};
 use serde::Serialize;
 use tempfile::TempDir;
@@ -37,6 +40,7 @@ use crate::{
         slice_io::{slice_io_from_fn, SliceIo, SliceIoTable},
     },
     serde::to_vec,
+    sha::Digest,
     Assumption, TraceCallback,
 };
 
@@ -69,17 +73,64 @@ pub enum SegmentPath {
 /// guest environment is set up prior to guest program execution.
 #[derive(Default)]
 pub struct ExecutorEnv<'a> {
-    pub(crate) env_vars: HashMap<String, String>,
+    pub(crate) env_vars: HashMap<String, Vec<u8>>,
     pub(crate) args: Vec<String>,
+    pub(crate) rng: Option<Rc<RefCell<ChaCha20Rng>>>,
+    pub(crate) clock_nanos: u64,
     pub(crate) segment_limit_po2: Option<u32>,
+    pub(crate) split_every_insns: Option<u32>,
     pub(crate) session_limit: Option<u64>,
+    pub(crate) max_segments: Option<u32>,
+    pub(crate) reserved_cycles: Option<u32>,
+    pub(crate) max_string_len: Option<u32>,
+    pub(crate) memory_size: Option<u32>,
+    pub(crate) page_size: Option<u32>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
     pub(crate) slice_io: Rc<RefCell<SliceIoTable<'a>>>,
     pub(crate) input: Vec<u8>,
+    pub(crate) input_channels: HashMap<u32, Vec<u8>>,
     pub(crate) trace: Vec<Rc<RefCell<dyn TraceCallback + 'a>>>,
     pub(crate) assumptions: Rc<RefCell<Assumptions>>,
+    pub(crate) panic_message: Rc<RefCell<Option<String>>>,
+    pub(crate) commits: Rc<RefCell<Vec<Vec<u8>>>>,
     pub(crate) segment_path: Option<SegmentPath>,
     pub(crate) pprof_out: Option<PathBuf>,
+    pub(crate) readonly_regions: Vec<Range<u32>>,
+    pub(crate) syscall_rate_limits: HashMap<String, u64>,
+    pub(crate) initial_fuel: Option<u64>,
+    pub(crate) fuel_limit: Option<u64>,
+    pub(crate) memory_breakpoints: Vec<(u32, u32)>,
+    pub(crate) disable_syscall_replay: bool,
+    pub(crate) disable_journal_capture: bool,
+    pub(crate) journal_writer: Option<Rc<RefCell<dyn Write + 'a>>>,
+    pub(crate) record_syscalls: bool,
+    #[cfg(feature = "prove")]
+    pub(crate) replay: Option<Vec<crate::SyscallRecord>>,
+    pub(crate) capture_call_stack: bool,
+    pub(crate) profile_basic_blocks: bool,
+    pub(crate) profile_lines: bool,
+    pub(crate) track_coverage: bool,
+    pub(crate) detect_self_modifying_code: bool,
+    pub(crate) expected_output_digest: Option<Digest>,
+    pub(crate) wall_clock_timeout: Option<Duration>,
+    pub(crate) cancel_flag: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "prove")]
+    pub(crate) cycle_cost_model: crate::CycleCostModel,
+    #[cfg(feature = "prove")]
+    pub(crate) loader_cycles: crate::LoaderCycles,
+    #[cfg(feature = "prove")]
+    pub(crate) insn_cycle_costs: HashMap<crate::MajorType, u32>,
+    #[cfg(feature = "prove")]
+    pub(crate) fuel_costs: HashMap<crate::MajorType, u64>,
+    #[cfg(feature = "prove")]
+    pub(crate) session_limit_behavior: crate::SessionLimitBehavior,
+    #[cfg(feature = "prove")]
+    pub(crate) progress_callback: Option<Rc<RefCell<dyn FnMut(crate::ProgressInfo) + 'a>>>,
+    pub(crate) progress_interval: Option<usize>,
+    #[cfg(feature = "prove")]
+    pub(crate) split_callback: Option<Rc<RefCell<dyn FnMut(crate::SplitInfo) + 'a>>>,
+    #[cfg(feature = "prove")]
+    pub(crate) ecall_callback: Option<Rc<RefCell<dyn FnMut(crate::EcallInfo) + 'a>>>,
 }
 
 impl<'a> ExecutorEnv<'a> {
@@ -121,6 +172,13 @@ impl<'a> ExecutorEnvBuilder<'a> {
                 .with_read_fd(fileno::STDIN, reader);
         }
 
+        for (index, data) in inner.input_channels.iter() {
+            inner
+                .posix_io
+                .borrow_mut()
+                .with_read_channel(*index, Cursor::new(data.clone()));
+        }
+
         if inner.pprof_out.is_none() {
             if let Ok(env_var) = std::env::var("RISC0_PPROF_OUT") {
                 inner.pprof_out = Some(env_var.into());
@@ -139,6 +197,28 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Split segments every `insns` instructions, in addition to the split at
+    /// the [segment limit](Self::segment_limit_po2).
+    ///
+    /// A segment ends at whichever limit is reached first, so the segment
+    /// boundaries of a guest no longer depend on the cycle cost of its
+    /// instructions alone. This is useful for reproducible benchmarking.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .split_every_insns(100_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn split_every_insns(&mut self, insns: u32) -> &mut Self {
+        self.inner.split_every_insns = Some(insns);
+        self
+    }
+
     /// Set a session limit, specified in number of cycles.
     ///
     /// # Example
@@ -156,6 +236,186 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Choose what happens when the [session limit](Self::session_limit) is
+    /// reached.
+    ///
+    /// By default, execution fails with
+    /// [ExecutorError::SessionLimitExceeded](crate::ExecutorError::SessionLimitExceeded).
+    /// With [SessionLimitBehavior::Truncate](crate::SessionLimitBehavior::Truncate),
+    /// it instead returns a [Session](crate::Session) with an exit code of
+    /// [ExitCode::SessionLimit](crate::ExitCode::SessionLimit) and the segments
+    /// produced so far.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, SessionLimitBehavior};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .session_limit(Some(32 * 1024 * 1024))
+    ///     .session_limit_behavior(SessionLimitBehavior::Truncate)
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn session_limit_behavior(&mut self, behavior: crate::SessionLimitBehavior) -> &mut Self {
+        self.inner.session_limit_behavior = behavior;
+        self
+    }
+
+    /// Limit the number of segments produced by a run.
+    ///
+    /// Execution fails with [ExecutorError::TooManySegments] once it would need
+    /// more segments than this, which bounds the memory used by a guest that
+    /// never halts regardless of its cycle count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .max_segments(1024)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExecutorError::TooManySegments]: crate::ExecutorError::TooManySegments
+    pub fn max_segments(&mut self, max_segments: u32) -> &mut Self {
+        self.inner.max_segments = Some(max_segments);
+        self
+    }
+
+    /// Set the size in bytes of the guest memory the ELF is loaded into.
+    ///
+    /// Defaults to [GUEST_MAX_MEM](risc0_zkvm_platform::memory::GUEST_MAX_MEM).
+    /// It must be a multiple of the [page size](Self::page_size) and may not
+    /// exceed the default, since the page table sits right above it.
+    /// Loading an ELF with segments outside of this memory fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .memory_size(64 * 1024 * 1024)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn memory_size(&mut self, bytes: u32) -> &mut Self {
+        self.inner.memory_size = Some(bytes);
+        self
+    }
+
+    /// Set the size in bytes of the pages that memory is paged in and out by.
+    ///
+    /// Defaults to [PAGE_SIZE](risc0_zkvm_platform::PAGE_SIZE) and must be a
+    /// power of two. Sessions executed with any other page size can be
+    /// inspected but not proven, because the circuit pages by the default:
+    /// they report [Unprovable::PageSize](crate::Unprovable).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .page_size(4096)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn page_size(&mut self, bytes: u32) -> &mut Self {
+        self.inner.page_size = Some(bytes);
+        self
+    }
+
+    /// Meter execution with the given amount of fuel.
+    ///
+    /// Every instruction consumes one unit of fuel unless configured otherwise
+    /// with [Self::fuel_costs], and execution ends with [ExitCode::OutOfFuel]
+    /// once it runs out. The guest can refill its fuel
+    /// with `sys_add_fuel`, but never beyond the initial amount. Fuel is only
+    /// metered by the executor, so a session that runs out of fuel cannot be
+    /// proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .initial_fuel(1_000_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExitCode::OutOfFuel]: crate::ExitCode::OutOfFuel
+    pub fn initial_fuel(&mut self, fuel: u64) -> &mut Self {
+        self.inner.initial_fuel = Some(fuel);
+        self
+    }
+
+    /// Stop execution once the guest has consumed `limit` units of fuel.
+    ///
+    /// Every instruction consumes one unit of fuel unless configured otherwise
+    /// with [Self::fuel_costs]. Once the limit is reached, execution ends with
+    /// [ExitCode::FuelExhausted] and the session holds the segments executed
+    /// up to that point. Unlike [Self::initial_fuel], the limit is a fixed
+    /// budget that the guest cannot refill, and it is independent of the
+    /// cycle model and of segment splitting. A session that exhausts its fuel
+    /// cannot be proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .fuel_limit(1_000_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExitCode::FuelExhausted]: crate::ExitCode::FuelExhausted
+    pub fn fuel_limit(&mut self, limit: u64) -> &mut Self {
+        self.inner.fuel_limit = Some(limit);
+        self
+    }
+
+    /// Charge the given amount of fuel for every instruction of each
+    /// [MajorType](crate::MajorType) in `costs`, instead of one unit.
+    ///
+    /// Fuel is an abstract budget for metering guests, independent of the
+    /// cycles charged for each instruction, so this has no effect on segment
+    /// splitting or on whether the session can be proven. It only applies
+    /// together with [Self::initial_fuel] or [Self::fuel_limit].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, MajorType};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .initial_fuel(1_000_000)
+    ///     .fuel_costs([(MajorType::Divide, 10)].into())
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn fuel_costs(&mut self, costs: HashMap<crate::MajorType, u64>) -> &mut Self {
+        self.inner.fuel_costs = costs;
+        self
+    }
+
     /// Add environment variables to the guest environment.
     ///
     /// # Example
@@ -174,12 +434,21 @@ impl<'a> ExecutorEnvBuilder<'a> {
     ///     .unwrap();
     /// ```
     pub fn env_vars(&mut self, vars: HashMap<String, String>) -> &mut Self {
-        self.inner.env_vars = vars;
+        self.inner.env_vars = vars
+            .into_iter()
+            .map(|(name, val)| (name, val.into_bytes()))
+            .collect();
         self
     }
 
     /// Add an argument array to the guest environment.
     ///
+    /// The guest reads the number of arguments with the `sys_argc` syscall,
+    /// and each argument by index with `sys_argv`, which returns the length of
+    /// the argument in bytes and copies as much of its UTF-8 encoding as fits
+    /// into the buffer provided by the guest. `std::env::args` in the guest is
+    /// built on these.
+    ///
     /// # Example
     /// ```
     /// # use risc0_zkvm::ExecutorEnv;
@@ -194,8 +463,36 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add arguments to the guest environment from any strings, e.g. string
+    /// slices. See [Self::args].
+    ///
+    /// # Example
+    /// ```
+    /// # use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .args_str(["grep", "-c", "foo", "-"])
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn args_str<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.inner
+            .args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
     /// Add an environment variable to the guest environment.
     ///
+    /// The value does not need to be valid UTF-8, although guests that read it
+    /// through `std::env::var` will only see valid UTF-8 values. Looking up a
+    /// variable that is not set is not an error: the `sys_getenv` syscall
+    /// reports it with a distinct status.
+    ///
     /// # Example
     ///
     /// ```
@@ -203,13 +500,59 @@ impl<'a> ExecutorEnvBuilder<'a> {
     ///
     /// let env = ExecutorEnv::builder()
     ///     .env_var("VAR1", "SOME_VALUE")
+    ///     .env_var("VAR2", [0xff, 0xfe])
     ///     .build()
     ///     .unwrap();
     /// ```
-    pub fn env_var(&mut self, name: &str, val: &str) -> &mut Self {
+    pub fn env_var(&mut self, name: &str, val: impl AsRef<[u8]>) -> &mut Self {
         self.inner
             .env_vars
-            .insert(name.to_string(), val.to_string());
+            .insert(name.to_string(), val.as_ref().to_vec());
+        self
+    }
+
+    /// Seed the generator behind `sys_random`, which also backs `getrandom`
+    /// in the guest.
+    ///
+    /// Random values are drawn from a ChaCha20 generator, so two runs with the
+    /// same seed and inputs produce identical journals. Without a seed, the
+    /// generator is seeded with all zeros: guest randomness is deterministic
+    /// by default and must not be relied upon as a source of secrets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .rng_seed([7; 32])
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn rng_seed(&mut self, seed: [u8; 32]) -> &mut Self {
+        self.inner.rng = Some(Rc::new(RefCell::new(ChaCha20Rng::from_seed(seed))));
+        self
+    }
+
+    /// Set the time returned by `sys_clock`, in nanoseconds since the Unix
+    /// epoch.
+    ///
+    /// The guest never sees the wall-clock time of the host: every call
+    /// returns this fixed value, or zero if no clock was set, so two runs with
+    /// the same clock and inputs produce identical journals.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .clock(1_700_000_000_000_000_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn clock(&mut self, fixed_nanos: u64) -> &mut Self {
+        self.inner.clock_nanos = fixed_nanos;
         self
     }
 
@@ -269,33 +612,168 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Append `data` to the input channel `index`.
+    ///
+    /// Input channels are byte streams separate from stdin and from each
+    /// other, each with its own read position. The guest reads from a channel
+    /// with the `sys_read_channel` syscall; reads from a channel that was never
+    /// added fail execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .add_input_channel(0, b"public parameters")
+    ///     .add_input_channel(1, b"private witness")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn add_input_channel(&mut self, index: u32, data: &[u8]) -> &mut Self {
+        self.inner
+            .input_channels
+            .entry(index)
+            .or_default()
+            .extend_from_slice(data);
+        self
+    }
+
     /// Add a posix-style standard input.
     pub fn stdin(&mut self, reader: impl Read + 'a) -> &mut Self {
         self.read_fd(fileno::STDIN, BufReader::new(reader))
     }
 
+    /// Stream the guest stdin from `reader`.
+    ///
+    /// Unlike [Self::write], which requires all input to be materialized
+    /// before execution starts, the reader is only polled when the guest reads
+    /// from stdin, and only for as many bytes as the guest requests. This
+    /// suits large inputs that the guest consumes incrementally. Once the
+    /// reader returns EOF, guest reads come back short, as they would for a
+    /// file. Input added with [Self::write] or [Self::write_slice] takes
+    /// precedence over the reader.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::{repeat, Read as _};
+    ///
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .stdin_reader(Box::new(repeat(0).take(1 << 20)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn stdin_reader(&mut self, reader: Box<dyn Read + 'a>) -> &mut Self {
+        // Without a buffer, reads are passed straight through to the reader,
+        // so it never runs ahead of the guest.
+        self.read_fd(fileno::STDIN, BufReader::with_capacity(0, reader))
+    }
+
     /// Add a posix-style standard output.
     pub fn stdout(&mut self, writer: impl Write + 'a) -> &mut Self {
         self.write_fd(fileno::STDOUT, writer)
     }
 
+    /// Keep only the guest log messages that are at most as verbose as
+    /// `level`, one of the levels in
+    /// [log_level](risc0_zkvm_platform::syscall::log_level).
+    ///
+    /// The guest can change the level as it runs with
+    /// [set_log_level](crate::guest::env::set_log_level). By default, all
+    /// messages are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    /// use risc0_zkvm_platform::syscall::log_level;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .log_level(log_level::WARN)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn log_level(&mut self, level: u32) -> &mut Self {
+        self.inner.posix_io.borrow_mut().log_level = level;
+        self
+    }
+
     /// Add a posix-style standard error.
     pub fn stderr(&mut self, writer: impl Write + 'a) -> &mut Self {
         self.write_fd(fileno::STDERR, writer)
     }
 
     /// Add a posix-style file descriptor for reading.
+    ///
+    /// The guest reads from `fd` with the `sys_read` syscall. Reads past the
+    /// end of `reader` return fewer bytes than requested, and reads from a
+    /// descriptor that was never added fail execution.
     pub fn read_fd(&mut self, fd: u32, reader: impl BufRead + 'a) -> &mut Self {
         self.inner.posix_io.borrow_mut().with_read_fd(fd, reader);
         self
     }
 
     /// Add a posix-style file descriptor for writing.
+    ///
+    /// The guest writes to `fd` with the `sys_write` syscall. Writes to a
+    /// descriptor that was never added fail execution.
     pub fn write_fd(&mut self, fd: u32, writer: impl Write + 'a) -> &mut Self {
         self.inner.posix_io.borrow_mut().with_write_fd(fd, writer);
         self
     }
 
+    /// Keep the journal in memory for [Session::journal], which is the
+    /// default, or not.
+    ///
+    /// Without capture, only the digest of the journal is kept, so that the
+    /// session can still be proven. This saves memory when the journal is
+    /// consumed elsewhere, e.g. by a [journal writer](Self::journal_writer).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let mut journal = Vec::new();
+    /// let env = ExecutorEnv::builder()
+    ///     .capture_journal(false)
+    ///     .journal_writer(&mut journal)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [Session::journal]: crate::Session::journal
+    pub fn capture_journal(&mut self, capture: bool) -> &mut Self {
+        self.inner.disable_journal_capture = !capture;
+        self
+    }
+
+    /// Stream the journal to `writer` as the guest writes it, e.g. to report
+    /// progress to a client before the session ends.
+    ///
+    /// The journal is also kept in memory for [Session::journal] unless
+    /// [capture](Self::capture_journal) is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .journal_writer(std::io::stdout())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [Session::journal]: crate::Session::journal
+    pub fn journal_writer(&mut self, writer: impl Write + 'a) -> &mut Self {
+        self.inner.journal_writer = Some(Rc::new(RefCell::new(writer)));
+        self
+    }
+
     /// Add a handler for simple I/O handling.
     pub fn slice_io(&mut self, channel: &str, handler: impl SliceIo + 'a) -> &mut Self {
         self.inner
@@ -350,9 +828,597 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
-    /// Enable the profiler and output results to the specified path.
-    pub fn enable_profiler<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
-        self.inner.pprof_out = Some(path.as_ref().to_path_buf());
+    /// Track the call stack of the guest, so that it can be inspected with
+    /// [ExecutorImpl::call_stack](crate::ExecutorImpl::call_stack) after a
+    /// fault or a pause.
+    ///
+    /// This slows down execution, since the executor has to report every
+    /// instruction it executes.
+    pub fn capture_call_stack(&mut self) -> &mut Self {
+        self.inner.capture_call_stack = true;
+        self
+    }
+
+    /// Accumulate the cycles spent in each basic block of the guest, so that
+    /// the hottest blocks can be inspected with
+    /// [Session::hot_blocks](crate::Session::hot_blocks).
+    ///
+    /// This is finer-grained than the function level profile written by
+    /// [enable_profiler](Self::enable_profiler), and pinpoints the loop bodies
+    /// that dominate execution. Like
+    /// [capture_call_stack](Self::capture_call_stack), it slows down
+    /// execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .profile_basic_blocks(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn profile_basic_blocks(&mut self, enable: bool) -> &mut Self {
+        self.inner.profile_basic_blocks = enable;
+        self
+    }
+
+    /// Accumulate the cycles spent on each source line of the guest, so that
+    /// the hottest lines can be inspected with
+    /// [Session::line_profile](crate::Session::line_profile).
+    ///
+    /// Lines are resolved from the DWARF line table of the ELF, so this needs
+    /// a guest built with debug info and an executor constructed with
+    /// [ExecutorImpl::from_elf](crate::ExecutorImpl::from_elf). Like
+    /// [enable_profiler](Self::enable_profiler), it slows down execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .profile_lines(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn profile_lines(&mut self, enable: bool) -> &mut Self {
+        self.inner.profile_lines = enable;
+        self
+    }
+
+    /// Record the address of every instruction that executes at least once,
+    /// to be returned by [Session::covered_pcs](crate::Session::covered_pcs).
+    ///
+    /// Mapped back to the source with the debug info of the guest, this gives
+    /// the line and branch coverage of a guest test suite.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .track_coverage(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn track_coverage(&mut self, enable: bool) -> &mut Self {
+        self.inner.track_coverage = enable;
+        self
+    }
+
+    /// Report every instruction the guest executes from memory that it wrote
+    /// earlier in the session, to be returned by
+    /// [Session::self_modified_pcs](crate::Session::self_modified_pcs).
+    ///
+    /// Well-behaved guests never do this, so a report can point to e.g. an
+    /// exploited memory safety bug. This tracks every write of the guest,
+    /// including those of syscalls, and slows down execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .detect_self_modifying_code(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn detect_self_modifying_code(&mut self, enable: bool) -> &mut Self {
+        self.inner.detect_self_modifying_code = enable;
+        self
+    }
+
+    /// Abort execution if a single run takes longer than `timeout` of wall
+    /// clock time.
+    ///
+    /// This guards against guests that loop forever, or much longer than
+    /// intended, without having to pick a [session
+    /// limit](Self::session_limit) in cycles. The clock is only sampled
+    /// periodically, so execution may run slightly past the timeout before it
+    /// is aborted with [ExecutorError::Timeout](crate::ExecutorError::Timeout).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .wall_clock_timeout(Duration::from_secs(60))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn wall_clock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.inner.wall_clock_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort execution once `flag` is set.
+    ///
+    /// This allows another thread to cancel an in-flight execution, e.g. when
+    /// the client that requested it goes away. Like the [wall clock
+    /// timeout](Self::wall_clock_timeout), the flag is only polled
+    /// periodically, and the run fails with
+    /// [ExecutorError::Cancelled](crate::ExecutorError::Cancelled) rather than a
+    /// panic.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::{atomic::AtomicBool, Arc};
+    ///
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let env = ExecutorEnv::builder()
+    ///     .cancel_flag(cancel.clone())
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn cancel_flag(&mut self, flag: Arc<AtomicBool>) -> &mut Self {
+        self.inner.cancel_flag = Some(flag);
+        self
+    }
+
+    /// Enable the profiler and output results to the specified path.
+    ///
+    /// The profile attributes the cycles of the guest to its call stacks, with
+    /// functions resolved from the symbols of the ELF. It is written in the
+    /// uncompressed [pprof] protobuf format, which `go tool pprof` and
+    /// Speedscope open directly.
+    ///
+    /// [pprof]: https://github.com/google/pprof/blob/main/proto/profile.proto
+    pub fn enable_profiler<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.pprof_out = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Mark a range of guest memory as read-only.
+    ///
+    /// Execution fails if the guest writes to any address within the range.
+    /// This is useful to protect constant data provided by the host and to
+    /// catch pointer bugs in the guest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .readonly_region(0x0B00_0000..0x0B00_1000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn readonly_region(&mut self, region: Range<u32>) -> &mut Self {
+        self.inner.readonly_regions.push(region);
+        self
+    }
+
+    /// Limit the number of times the guest may invoke a syscall per run.
+    ///
+    /// Unlike an error, exceeding the limit pauses execution with
+    /// [ExitCode::Paused] right before the call that would exceed it. The host
+    /// can then decide when to resume, at which point the call is reissued and
+    /// the count starts over. Only fresh calls count toward the limit; calls
+    /// that are replayed after a segment split are not counted twice.
+    ///
+    /// This is intended to model hosts with limited throughput. Because the
+    /// pause is initiated by the host rather than the guest, a session paused
+    /// this way cannot be proven: it reports [Unprovable::HostPause] in
+    /// [Session::unprovable], and [Session::get_claim] and the prover reject
+    /// it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .syscall_rate_limit("my_syscall", 10)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExitCode::Paused]: crate::ExitCode::Paused
+    /// [Unprovable::HostPause]: crate::Unprovable::HostPause
+    /// [Session::unprovable]: crate::Session::unprovable
+    /// [Session::get_claim]: crate::Session::get_claim
+    pub fn syscall_rate_limit<C: AsRef<str>>(&mut self, name: C, max_per_run: u64) -> &mut Self {
+        self.inner
+            .syscall_rate_limits
+            .insert(name.as_ref().to_string(), max_per_run);
+        self
+    }
+
+    /// Pause execution the first time the guest stores `value` to the word
+    /// containing `addr`.
+    ///
+    /// Execution pauses with [ExitCode::Paused] right after the store, and
+    /// [Session::memory_breakpoint] reports which breakpoint was hit. The state
+    /// at that point can then be inspected through [Session::post_image]. Once
+    /// hit, a breakpoint does not fire again when execution is resumed.
+    ///
+    /// As with [Self::syscall_rate_limit], the pause is initiated by the host,
+    /// so a session paused this way reports [Unprovable::HostPause] and cannot
+    /// be proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .pause_on_memory_value(0x8000, 42)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExitCode::Paused]: crate::ExitCode::Paused
+    /// [Session::memory_breakpoint]: crate::Session::memory_breakpoint
+    /// [Session::post_image]: crate::Session::post_image
+    /// [Unprovable::HostPause]: crate::Unprovable::HostPause
+    pub fn pause_on_memory_value(&mut self, addr: u32, value: u32) -> &mut Self {
+        self.inner.memory_breakpoints.push((addr, value));
+        self
+    }
+
+    /// Disable the replay of syscalls that are re-executed after a segment
+    /// split.
+    ///
+    /// Normally, when a segment split happens during a syscall, the syscall is
+    /// re-executed at the start of the next segment with the result recorded
+    /// the first time, so the handler is invoked once per call. With replay
+    /// disabled, the handler is invoked afresh every time instead. This is
+    /// useful to observe raw handler behavior and to stress-test that handlers
+    /// are deterministic.
+    ///
+    /// This is a debugging aid only and must not be used for proving: a
+    /// handler that returns a different result when re-invoked produces
+    /// segments that are inconsistent with each other.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .disable_syscall_replay(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn disable_syscall_replay(&mut self, disable: bool) -> &mut Self {
+        self.inner.disable_syscall_replay = disable;
+        self
+    }
+
+    /// Record the software syscalls made by the guest, along with the
+    /// responses of the host, in [Session::syscalls].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .record_syscalls(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [Session::syscalls]: crate::Session::syscalls
+    pub fn record_syscalls(&mut self, enable: bool) -> &mut Self {
+        self.inner.record_syscalls = enable;
+        self
+    }
+
+    /// Answer the software syscalls of the guest from `syscalls`, in order,
+    /// instead of from the registered handlers.
+    ///
+    /// Given the syscalls recorded by [Self::record_syscalls], this re-runs a
+    /// session deterministically without access to the original host, e.g. to
+    /// check that it reproduces or to prove it elsewhere. Execution fails with
+    /// an error describing the divergence if the guest requests a syscall
+    /// other than the next one recorded, requests a different number of words
+    /// from it, or halts before all of them are replayed. Writes are still delivered to their
+    /// file descriptor, so that the journal and the output of the guest are
+    /// captured as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, SyscallRecord};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let syscalls: Vec<SyscallRecord> = Vec::new();
+    /// let env = ExecutorEnv::builder().replay(syscalls).build().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn replay(&mut self, syscalls: Vec<crate::SyscallRecord>) -> &mut Self {
+        self.inner.replay = Some(syscalls);
+        self
+    }
+
+    /// Fail execution if the guest halts with an output digest other than
+    /// `digest`.
+    ///
+    /// The output digest is the digest of the [Output](crate::Output) the
+    /// guest commits to when it halts, which binds its journal and
+    /// assumptions. Checking it while executing catches a guest that produces
+    /// unexpected output before any proving work is done.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{sha::Digest, ExecutorEnv};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .expect_output_digest(Digest::ZERO)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn expect_output_digest(&mut self, digest: Digest) -> &mut Self {
+        self.inner.expected_output_digest = Some(digest);
+        self
+    }
+
+    /// Charge cycles for the accelerated ecalls according to the given
+    /// [CycleCostModel](crate::CycleCostModel).
+    ///
+    /// This makes it possible to estimate the cycle counts of a guest under an
+    /// alternate circuit. Sessions executed with anything but the default
+    /// model report [Unprovable::CycleCostModel](crate::Unprovable) in
+    /// [Session::unprovable](crate::Session::unprovable) and cannot be proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{CycleCostModel, ExecutorEnv};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .cycle_cost_model(CycleCostModel {
+    ///         sha_per_block: 100,
+    ///         ..Default::default()
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn cycle_cost_model(&mut self, model: crate::CycleCostModel) -> &mut Self {
+        self.inner.cycle_cost_model = model;
+        self
+    }
+
+    /// Charge the given number of cycles for every instruction of each
+    /// [MajorType](crate::MajorType) in `costs`, instead of its cost in the
+    /// circuit.
+    ///
+    /// Instruction types missing from `costs` keep their default cost. As with
+    /// [Self::cycle_cost_model], sessions executed with custom costs report
+    /// [Unprovable::InsnCycleCosts](crate::Unprovable) and cannot be proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, MajorType};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .insn_cycle_costs([(MajorType::Load, 2), (MajorType::Store, 2)].into())
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn insn_cycle_costs(&mut self, costs: HashMap<crate::MajorType, u32>) -> &mut Self {
+        self.inner.insn_cycle_costs = costs;
+        self
+    }
+
+    /// Model a loader that spends the given [LoaderCycles](crate::LoaderCycles)
+    /// initializing and finalizing every segment, e.g. that of an alternate
+    /// guest runtime.
+    ///
+    /// Unlike [Self::reserved_cycles], this is reflected in the
+    /// [SegmentStats](crate::SegmentStats) of every segment. As with
+    /// [Self::cycle_cost_model], only the default can be proven: sessions
+    /// executed with any other loader report
+    /// [Unprovable::LoaderCycles](crate::Unprovable).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, LoaderCycles};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .loader_cycles(LoaderCycles { init: 512, fini: 16 })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn loader_cycles(&mut self, loader: crate::LoaderCycles) -> &mut Self {
+        self.inner.loader_cycles = loader;
+        self
+    }
+
+    /// Reserve `cycles` of every segment for the fixed overhead of the
+    /// circuit, instead of the cycles the current circuit needs to initialize
+    /// and finalize a segment.
+    ///
+    /// Like [Self::cycle_cost_model], this is only useful to estimate a guest
+    /// under an alternate circuit, here its segment count. The
+    /// [SegmentStats](crate::SegmentStats) still report the default overhead,
+    /// and the session reports [Unprovable::ReservedCycles](crate::Unprovable)
+    /// and cannot be proven.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .reserved_cycles(2048)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn reserved_cycles(&mut self, cycles: u32) -> &mut Self {
+        self.inner.reserved_cycles = Some(cycles);
+        self
+    }
+
+    /// Fail execution when the guest passes a string longer than `len` bytes
+    /// to the host, e.g. as the name of a syscall.
+    ///
+    /// This bounds the work the host does for a guest that fails to terminate
+    /// such a string. The default is 1024 bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .max_string_len(256)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn max_string_len(&mut self, len: u32) -> &mut Self {
+        self.inner.max_string_len = Some(len);
+        self
+    }
+
+    /// Report the progress of execution to `callback`, e.g. to drive a
+    /// progress bar.
+    ///
+    /// The callback is invoked with a [ProgressInfo](crate::ProgressInfo) each
+    /// time a segment is completed, and additionally every
+    /// [progress_interval](Self::progress_interval) instructions if set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, ProgressInfo};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .progress_callback(|info: ProgressInfo| {
+    ///         println!("{} segments, {} cycles", info.segments_completed, info.session_cycle)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn progress_callback(
+        &mut self,
+        callback: impl FnMut(crate::ProgressInfo) + 'a,
+    ) -> &mut Self {
+        self.inner.progress_callback = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Also invoke the [progress_callback](Self::progress_callback) every
+    /// `insns` instructions, rather than only when a segment is completed.
+    pub fn progress_interval(&mut self, insns: usize) -> &mut Self {
+        self.inner.progress_interval = Some(insns);
+        self
+    }
+
+    /// Invoke `callback` with a [SplitInfo](crate::SplitInfo) each time a
+    /// segment is split, e.g. to monitor where a guest crosses segment
+    /// boundaries.
+    ///
+    /// Unlike the [progress_callback](Self::progress_callback), this is not
+    /// invoked for the final segment, so a session with `n` segments reports
+    /// `n - 1` splits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{ExecutorEnv, SplitInfo};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .on_split(|info: SplitInfo| {
+    ///         println!("segment {} ended after {} instructions", info.index, info.split_insn)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn on_split(&mut self, callback: impl FnMut(crate::SplitInfo) + 'a) -> &mut Self {
+        self.inner.split_callback = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Invoke `callback` with an [EcallInfo](crate::EcallInfo) after each
+    /// ecall, reporting the host time spent on it.
+    ///
+    /// This helps profile slow syscall handlers without instrumenting each of
+    /// them, since the cycles charged to the guest for an ecall do not reflect
+    /// the latency of the host.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "prove")]
+    /// use risc0_zkvm::{EcallInfo, ExecutorEnv};
+    ///
+    /// # #[cfg(feature = "prove")]
+    /// # {
+    /// let env = ExecutorEnv::builder()
+    ///     .on_ecall(|info: EcallInfo| {
+    ///         println!("ecall {:?} took {:?}", info.syscall, info.duration)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn on_ecall(&mut self, callback: impl FnMut(crate::EcallInfo) + 'a) -> &mut Self {
+        self.inner.ecall_callback = Some(Rc::new(RefCell::new(callback)));
         self
     }
 }
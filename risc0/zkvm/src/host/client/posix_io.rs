@@ -19,13 +19,19 @@ use std::{
     rc::Rc,
 };
 
-use risc0_zkvm_platform::fileno;
+use risc0_zkvm_platform::{fileno, syscall::log_level};
 
 /// Posix-style I/O
 #[derive(Clone)]
 pub struct PosixIo<'a> {
     pub(crate) read_fds: BTreeMap<u32, Rc<RefCell<dyn BufRead + 'a>>>,
     pub(crate) write_fds: BTreeMap<u32, Rc<RefCell<dyn Write + 'a>>>,
+    // Input channels read with SYS_READ_CHANNEL, separate from the fds.
+    pub(crate) read_channels: BTreeMap<u32, Rc<RefCell<dyn BufRead + 'a>>>,
+    // Bytes read by the guest from stdin during the current run.
+    pub(crate) consumed_stdin: Vec<u8>,
+    // The most verbose level of the guest log messages that are kept.
+    pub(crate) log_level: u32,
 }
 
 impl<'a> Default for PosixIo<'a> {
@@ -33,6 +39,9 @@ impl<'a> Default for PosixIo<'a> {
         let mut new = Self {
             read_fds: Default::default(),
             write_fds: Default::default(),
+            read_channels: Default::default(),
+            consumed_stdin: Vec::new(),
+            log_level: log_level::TRACE,
         };
         new.with_read_fd(fileno::STDIN, Cursor::new(vec![]))
             .with_write_fd(fileno::STDOUT, stdout())
@@ -47,6 +56,12 @@ impl<'a> PosixIo<'a> {
         self
     }
 
+    pub fn with_read_channel(&mut self, channel: u32, reader: impl BufRead + 'a) -> &mut Self {
+        self.read_channels
+            .insert(channel, Rc::new(RefCell::new(reader)));
+        self
+    }
+
     pub fn with_write_fd(&mut self, fd: u32, writer: impl Write + 'a) -> &mut Self {
         self.write_fds.insert(fd, Rc::new(RefCell::new(writer)));
         self
@@ -79,6 +79,7 @@ impl Executor for LocalProver {
             segments,
             journal: session.journal.unwrap_or_default().into(),
             exit_code: session.exit_code,
+            panic_message: session.panic_message,
         })
     }
 }
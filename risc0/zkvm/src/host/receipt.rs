@@ -107,6 +107,13 @@ use crate::{
 /// The public outputs of the [Receipt] are contained in the [Receipt::journal].
 /// You can use [Journal::decode] to deserialize the journal as typed and
 /// structured data, or access the [Journal::bytes] directly.
+///
+/// A guest that panics halts with
+/// [PANIC_EXIT_CODE](risc0_zkvm_platform::syscall::halt::PANIC_EXIT_CODE)
+/// rather than faulting, so a panic can be proven. [Receipt::verify] rejects
+/// such a receipt like any other unsuccessful exit code, while
+/// [Receipt::verify_integrity_with_context] accepts it, with an exit code of
+/// `Halted(101)` in its claim.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Receipt {
@@ -199,8 +206,9 @@ impl Receipt {
     ///
     /// This does not verify the success of the guest execution. In
     /// particular, the guest could have exited with an error (e.g.
-    /// `ExitCode::Halted(1)`) or faulted state. It also does not check the
-    /// image ID, or otherwise constrain what guest was executed. After calling
+    /// `ExitCode::Halted(1)`, or `ExitCode::Halted(101)` after a panic) or
+    /// faulted state. It also does not check the image ID, or otherwise
+    /// constrain what guest was executed. After calling
     /// this method, the caller should check the [ReceiptClaim] fields
     /// relevant to their application. If you need to verify a successful
     /// guest execution and access the journal, the `verify` function is
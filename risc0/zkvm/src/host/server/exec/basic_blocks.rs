@@ -0,0 +1,134 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for profiling the guest by basic block.
+//!
+//! Basic blocks are recovered from the stream of executed instructions: a
+//! block starts at the target of any jump or taken branch and after any
+//! instruction that may transfer control, and extends over consecutive
+//! instructions up to the next such boundary.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use risc0_zkvm_platform::WORD_SIZE;
+use rrs_lib::instruction_formats::{OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_SYSTEM};
+
+use crate::{TraceCallback, TraceEvent};
+
+/// The cycles spent in a basic block of the guest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockProfile {
+    /// The address of the first instruction of the block.
+    pub start_pc: u32,
+
+    /// The address of the last instruction of the block.
+    pub end_pc: u32,
+
+    /// The number of cycles spent executing the block.
+    pub cycles: u64,
+
+    /// The number of times the block was entered.
+    pub executions: u64,
+}
+
+#[derive(Default)]
+struct InsnStats {
+    insn: u32,
+    cycles: u64,
+    executions: u64,
+}
+
+/// Accumulates cycles by basic block from the trace of executed instructions.
+#[derive(Default)]
+pub(crate) struct BasicBlockProfiler {
+    // The pc and cycle count of the previous instruction, if any.
+    prev: Option<(u32, u32)>,
+
+    // Statistics for each executed instruction, by pc.
+    insns: BTreeMap<u32, InsnStats>,
+
+    // The pc of each instruction that was entered other than by falling
+    // through from the preceding instruction.
+    leaders: HashSet<u32>,
+}
+
+fn ends_block(insn: u32) -> bool {
+    matches!(
+        insn & 0x7f,
+        OPCODE_BRANCH | OPCODE_JAL | OPCODE_JALR | OPCODE_SYSTEM
+    )
+}
+
+impl BasicBlockProfiler {
+    /// Returns the profile of every executed block, hottest first, and resets
+    /// the profiler.
+    pub(crate) fn take_blocks(&mut self) -> Vec<BlockProfile> {
+        let insns = std::mem::take(&mut self.insns);
+        let leaders = std::mem::take(&mut self.leaders);
+        self.prev = None;
+
+        let mut blocks: Vec<BlockProfile> = Vec::new();
+        let mut prev_insn = None;
+        for (pc, stats) in insns {
+            let block = blocks.last_mut().filter(|block| {
+                block.end_pc + WORD_SIZE as u32 == pc
+                    && !prev_insn.is_some_and(ends_block)
+                    && !leaders.contains(&pc)
+            });
+            if let Some(block) = block {
+                block.end_pc = pc;
+                block.cycles += stats.cycles;
+            } else {
+                blocks.push(BlockProfile {
+                    start_pc: pc,
+                    end_pc: pc,
+                    cycles: stats.cycles,
+                    executions: stats.executions,
+                });
+            }
+            prev_insn = Some(stats.insn);
+        }
+
+        blocks.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.start_pc.cmp(&b.start_pc)));
+        blocks
+    }
+}
+
+impl TraceCallback for BasicBlockProfiler {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        if let TraceEvent::InstructionStart { cycle, pc, insn } = event {
+            match self.prev {
+                Some((prev_pc, prev_cycle)) if cycle >= prev_cycle => {
+                    let prev = self.insns.get_mut(&prev_pc).unwrap();
+                    prev.cycles += (cycle - prev_cycle) as u64;
+                    if ends_block(prev.insn) || prev_pc + WORD_SIZE as u32 != pc {
+                        self.leaders.insert(pc);
+                    }
+                }
+                // The first instruction, or the first after the executor was
+                // resumed.
+                _ => {
+                    self.leaders.insert(pc);
+                }
+            }
+
+            let stats = self.insns.entry(pc).or_default();
+            stats.insn = insn;
+            stats.executions += 1;
+            self.prev = Some((pc, cycle));
+        }
+        Ok(())
+    }
+}
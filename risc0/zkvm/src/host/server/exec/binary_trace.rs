@@ -0,0 +1,123 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Capture of the execution trace in a compact binary log.
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{TraceCallback, TraceEvent};
+
+/// Writes the [TraceEvent]s of an execution to a file as they happen.
+///
+/// Each event is stored as a little-endian `u32` length followed by that many
+/// bytes of the event encoded with `bincode`. The log can be read back with a
+/// [BinaryTraceReader].
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::{BinaryTraceWriter, ExecutorEnv, ExecutorImpl};
+/// # use risc0_zkvm_methods::HELLO_COMMIT_ELF;
+///
+/// let writer = BinaryTraceWriter::create("trace.bin").unwrap();
+/// let env = ExecutorEnv::builder()
+///     .trace_callback(writer.callback())
+///     .build()
+///     .unwrap();
+/// ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+///     .unwrap()
+///     .run()
+///     .unwrap();
+/// writer.finish().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct BinaryTraceWriter {
+    out: Rc<RefCell<BufWriter<File>>>,
+}
+
+impl BinaryTraceWriter {
+    /// Create a log at `path`, truncating any existing file.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path.as_ref())
+            .with_context(|| format!("failed to create {}", path.as_ref().display()))?;
+        Ok(Self {
+            out: Rc::new(RefCell::new(BufWriter::new(file))),
+        })
+    }
+
+    /// Returns a callback to pass to
+    /// [ExecutorEnvBuilder::trace_callback](crate::ExecutorEnvBuilder::trace_callback)
+    /// that appends events to this log.
+    pub fn callback(&self) -> impl TraceCallback {
+        let out = self.out.clone();
+        move |event| {
+            let bytes = bincode::serialize(&event)?;
+            let mut out = out.borrow_mut();
+            out.write_all(&u32::try_from(bytes.len())?.to_le_bytes())?;
+            out.write_all(&bytes)?;
+            Ok(())
+        }
+    }
+
+    /// Flush the events recorded so far to the file.
+    pub fn finish(&self) -> Result<()> {
+        Ok(self.out.borrow_mut().flush()?)
+    }
+}
+
+/// Reads back the [TraceEvent]s written by a [BinaryTraceWriter], in order.
+pub struct BinaryTraceReader {
+    input: BufReader<File>,
+}
+
+impl BinaryTraceReader {
+    /// Open the log at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("failed to open {}", path.as_ref().display()))?;
+        Ok(Self {
+            input: BufReader::new(file),
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Option<TraceEvent>> {
+        let mut len = [0u8; 4];
+        match self.input.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.input
+            .read_exact(&mut bytes)
+            .context("truncated trace event")?;
+        Ok(Some(bincode::deserialize(&bytes)?))
+    }
+}
+
+impl Iterator for BinaryTraceReader {
+    type Item = Result<TraceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
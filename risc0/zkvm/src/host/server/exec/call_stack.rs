@@ -0,0 +1,291 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for capturing the call stack of the guest.
+//!
+//! The stack is unwound in guest memory, starting from the current pc and
+//! registers, using the DWARF call frame information in the ELF's `.eh_frame`
+//! or `.debug_frame` section. Guests are not required to carry that
+//! information, so as a fallback this also keeps a shadow stack of return
+//! addresses that is updated as the guest executes calls and returns,
+//! following the same rules as the [profiler](super::profiler).
+
+use std::collections::BTreeMap;
+
+use addr2line::gimli::{
+    BaseAddresses, CfaRule, DebugFrame, EhFrame, LittleEndian as DwarfEndian, Reader, Register,
+    RegisterRule, UnwindContext, UnwindSection, UnwindTableRow,
+};
+use anyhow::Result;
+use elf::{abi::STT_FUNC, endian::LittleEndian, ElfBytes};
+use risc0_zkvm_platform::syscall::reg_abi::{REG_MAX, REG_RA, REG_SP};
+use rustc_demangle::demangle;
+
+use super::profiler::{extract_call_stack_op, CallStackOp};
+use crate::{TraceCallback, TraceEvent};
+
+/// A frame of the guest call stack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackFrame {
+    /// The demangled name of the function containing `pc`, if known.
+    pub function: Option<String>,
+
+    /// The program counter within the function: the current instruction for
+    /// the innermost frame, and the call instruction for all others.
+    pub pc: u32,
+}
+
+/// Tracks the call stack of the guest from the trace of executed instructions.
+pub(crate) struct CallStackTracker {
+    // The pc of the most recently executed instruction.
+    pc: u32,
+
+    // The most recently executed instruction.
+    insn: u32,
+
+    // The pc of each call instruction that has not yet returned.
+    call_sites: Vec<u32>,
+
+    // Function start address to (end address, name).
+    functions: BTreeMap<u32, (u32, String)>,
+
+    // The call frame information used to unwind the stack in memory.
+    cfi: CallFrameInfo,
+}
+
+impl CallStackTracker {
+    pub(crate) fn new(elf_data: &[u8]) -> Result<Self> {
+        let mut functions = BTreeMap::new();
+        let elf = ElfBytes::<LittleEndian>::minimal_parse(elf_data)?;
+        if let Some((symtab, strtab)) = elf.symbol_table()? {
+            for sym in symtab {
+                if sym.st_symtype() == STT_FUNC {
+                    let name = strtab.get(sym.st_name as usize)?;
+                    let start = sym.st_value as u32;
+                    let end = start.saturating_add(sym.st_size.max(1) as u32);
+                    functions.insert(start, (end, format!("{:#}", demangle(name))));
+                }
+            }
+        }
+
+        Ok(Self {
+            pc: u32::MAX,
+            insn: 0,
+            call_sites: Vec::new(),
+            functions,
+            cfi: CallFrameInfo::new(&elf)?,
+        })
+    }
+
+    /// Returns the call stack, innermost frame first, as of the point where
+    /// the guest is about to execute the instruction at `pc` with the
+    /// registers `regs`, reading guest memory through `read_u32`.
+    ///
+    /// The stack is unwound from the call frame information if it covers
+    /// `pc`, and taken from the shadow stack otherwise.
+    pub(crate) fn frames(
+        &self,
+        pc: u32,
+        regs: &[u32],
+        read_u32: impl FnMut(u32) -> Option<u32>,
+    ) -> Vec<StackFrame> {
+        self.unwind(pc, regs, read_u32)
+            .unwrap_or_else(|| self.shadow_frames(pc))
+    }
+
+    fn unwind(
+        &self,
+        pc: u32,
+        regs: &[u32],
+        mut read_u32: impl FnMut(u32) -> Option<u32>,
+    ) -> Option<Vec<StackFrame>> {
+        let regs: &[u32; REG_MAX] = regs.try_into().ok()?;
+        let mut frames = vec![self.frame(pc)];
+        let mut caller = self.cfi.unwind_frame(pc, regs, &mut read_u32)?;
+        while frames.len() < MAX_FRAMES {
+            let return_address = caller[REG_RA];
+            if return_address == 0 {
+                break;
+            }
+            let call_site = return_address.wrapping_sub(4);
+            frames.push(self.frame(call_site));
+            let Some(next) = self.cfi.unwind_frame(call_site, &caller, &mut read_u32) else {
+                break;
+            };
+            // The stack grows down, so each caller's frame must be above its
+            // callee's; anything else is a corrupt stack.
+            if next[REG_SP] < caller[REG_SP] || next == caller {
+                break;
+            }
+            caller = next;
+        }
+        Some(frames)
+    }
+
+    fn shadow_frames(&self, pc: u32) -> Vec<StackFrame> {
+        let mut call_sites = self.call_sites.clone();
+        apply(&mut call_sites, self.pc, self.insn, pc);
+        std::iter::once(pc)
+            .chain(call_sites.into_iter().rev())
+            .map(|pc| self.frame(pc))
+            .collect()
+    }
+
+    fn frame(&self, pc: u32) -> StackFrame {
+        StackFrame {
+            function: self.lookup(pc),
+            pc,
+        }
+    }
+
+    pub(crate) fn lookup(&self, pc: u32) -> Option<String> {
+        let (_, (end, name)) = self.functions.range(..=pc).next_back()?;
+        (pc < *end).then(|| name.clone())
+    }
+}
+
+// Bounds the number of frames unwound, in case the stack is cyclic.
+const MAX_FRAMES: usize = 1024;
+
+// The size in bytes of a guest address.
+const ADDRESS_SIZE: u8 = 4;
+
+/// The DWARF call frame information of a program.
+struct CallFrameInfo {
+    // The contents and load address of `.eh_frame`, if present.
+    eh_frame: Option<(Vec<u8>, u64)>,
+
+    // The contents of `.debug_frame`, if present.
+    debug_frame: Option<Vec<u8>>,
+}
+
+impl CallFrameInfo {
+    fn new(elf: &ElfBytes<LittleEndian>) -> Result<Self> {
+        let section = |name| -> Result<Option<(Vec<u8>, u64)>> {
+            let Some(shdr) = elf.section_header_by_name(name)? else {
+                return Ok(None);
+            };
+            let (data, compression) = elf.section_data(&shdr)?;
+            Ok(compression.is_none().then(|| (data.to_vec(), shdr.sh_addr)))
+        };
+        Ok(Self {
+            eh_frame: section(".eh_frame")?,
+            debug_frame: section(".debug_frame")?.map(|(data, _)| data),
+        })
+    }
+
+    /// Returns the registers of the caller of the frame executing `pc` with
+    /// the registers `regs`, or `None` if there is no call frame information
+    /// for `pc` or it cannot be evaluated.
+    ///
+    /// The return address of the caller is in `ra`.
+    fn unwind_frame(
+        &self,
+        pc: u32,
+        regs: &[u32; REG_MAX],
+        read_u32: &mut impl FnMut(u32) -> Option<u32>,
+    ) -> Option<[u32; REG_MAX]> {
+        if let Some((data, address)) = &self.eh_frame {
+            let mut section = EhFrame::new(data, DwarfEndian);
+            section.set_address_size(ADDRESS_SIZE);
+            let bases = BaseAddresses::default().set_eh_frame(*address);
+            let mut ctx = UnwindContext::new();
+            if let Ok(row) = section.unwind_info_for_address(
+                &bases,
+                &mut ctx,
+                pc as u64,
+                EhFrame::cie_from_offset,
+            ) {
+                return apply_row(row, regs, read_u32);
+            }
+        }
+        let mut section = DebugFrame::new(self.debug_frame.as_ref()?, DwarfEndian);
+        section.set_address_size(ADDRESS_SIZE);
+        let mut ctx = UnwindContext::new();
+        let row = section
+            .unwind_info_for_address(
+                &BaseAddresses::default(),
+                &mut ctx,
+                pc as u64,
+                DebugFrame::cie_from_offset,
+            )
+            .ok()?;
+        apply_row(row, regs, read_u32)
+    }
+}
+
+/// Evaluate the unwind table `row` against the registers `regs` of the callee,
+/// returning the registers of the caller.
+fn apply_row<R: Reader>(
+    row: &UnwindTableRow<R>,
+    regs: &[u32; REG_MAX],
+    read_u32: &mut impl FnMut(u32) -> Option<u32>,
+) -> Option<[u32; REG_MAX]> {
+    let reg = |register: Register| regs.get(usize::from(register.0)).copied();
+    let cfa = match row.cfa() {
+        CfaRule::RegisterAndOffset { register, offset } => {
+            reg(*register)?.wrapping_add(*offset as u32)
+        }
+        CfaRule::Expression(_) => return None,
+    };
+
+    let mut caller = *regs;
+    for (register, rule) in row.registers() {
+        let value = match rule {
+            RegisterRule::Undefined => 0,
+            RegisterRule::SameValue => continue,
+            RegisterRule::Offset(offset) => read_u32(cfa.wrapping_add(*offset as u32))?,
+            RegisterRule::ValOffset(offset) => cfa.wrapping_add(*offset as u32),
+            RegisterRule::Register(other) => reg(*other)?,
+            _ => return None,
+        };
+        if let Some(slot) = caller.get_mut(usize::from(register.0)) {
+            *slot = value;
+        }
+    }
+    caller[REG_SP] = cfa;
+    Some(caller)
+}
+
+/// Update `call_sites` for the instruction `insn` at `prev_pc`, which
+/// transferred control to `pc`.
+fn apply(call_sites: &mut Vec<u32>, prev_pc: u32, insn: u32, pc: u32) {
+    let mut pop = |call_sites: &mut Vec<u32>| {
+        while let Some(call_site) = call_sites.pop() {
+            if pc.wrapping_sub(4) == call_site {
+                break;
+            }
+        }
+    };
+    match extract_call_stack_op(insn) {
+        Some(CallStackOp::Push) => call_sites.push(prev_pc),
+        Some(CallStackOp::Pop) => pop(call_sites),
+        Some(CallStackOp::PopPush) => {
+            pop(call_sites);
+            call_sites.push(prev_pc);
+        }
+        None => (),
+    }
+}
+
+impl TraceCallback for CallStackTracker {
+    fn trace_callback(&mut self, event: TraceEvent) -> Result<()> {
+        if let TraceEvent::InstructionStart { pc, insn, .. } = event {
+            apply(&mut self.call_sites, self.pc, self.insn, pc);
+            self.pc = pc;
+            self.insn = insn;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the execution trace in the Chrome trace event format.
+
+use std::{
+    cell::RefCell,
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use anyhow::Result;
+
+use crate::{TraceCallback, TraceEvent};
+
+/// Collects the [TraceEvent]s of an execution and writes them out as a JSON
+/// array of [trace events] that can be opened in `chrome://tracing` or
+/// Perfetto.
+///
+/// Each instruction becomes a complete event named after its pc on thread 0,
+/// and each syscall becomes a duration event named after the syscall on
+/// thread 1. Timestamps are in cycles rather than microseconds.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::{ChromeTraceWriter, ExecutorEnv, ExecutorImpl};
+/// # use risc0_zkvm_methods::HELLO_COMMIT_ELF;
+///
+/// let writer = ChromeTraceWriter::default();
+/// let env = ExecutorEnv::builder()
+///     .trace_callback(writer.callback())
+///     .build()
+///     .unwrap();
+/// ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+///     .unwrap()
+///     .run()
+///     .unwrap();
+/// writer.finish("trace.json").unwrap();
+/// ```
+///
+/// [trace events]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+#[derive(Clone, Default)]
+pub struct ChromeTraceWriter {
+    inner: Rc<RefCell<ChromeTrace>>,
+}
+
+#[derive(Default)]
+struct ChromeTrace {
+    events: Vec<ChromeEvent>,
+
+    // The pc and start cycle of the instruction being executed, if any.
+    insn: Option<(u32, u32)>,
+
+    // The name of the syscall that completed during the instruction being
+    // executed, if any. It ends along with that instruction.
+    syscall: Option<String>,
+}
+
+enum ChromeEvent {
+    Instruction { pc: u32, cycle: u32, dur: u32 },
+    SyscallBegin { name: String, cycle: u32 },
+    SyscallEnd { name: String, cycle: u32 },
+}
+
+impl ChromeTraceWriter {
+    /// Returns a callback to pass to
+    /// [ExecutorEnvBuilder::trace_callback](crate::ExecutorEnvBuilder::trace_callback)
+    /// that records events into this writer.
+    pub fn callback(&self) -> impl TraceCallback {
+        let inner = self.inner.clone();
+        move |event| {
+            inner.borrow_mut().record(event);
+            Ok(())
+        }
+    }
+
+    /// Write the events recorded so far to `path`.
+    pub fn finish(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        inner.end_insn(None);
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"[")?;
+        for (i, event) in inner.events.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"\n")?;
+            out.write_all(event.to_json().as_bytes())?;
+        }
+        out.write_all(b"\n]\n")?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl ChromeTrace {
+    fn record(&mut self, event: TraceEvent) {
+        match event {
+            TraceEvent::InstructionStart { cycle, pc, .. } => {
+                self.end_insn(Some(cycle));
+                self.insn = Some((pc, cycle));
+            }
+            TraceEvent::SyscallStart { name, cycle } => {
+                self.events.push(ChromeEvent::SyscallBegin { name, cycle });
+            }
+            TraceEvent::SyscallEnd { name, .. } => self.syscall = Some(name),
+            TraceEvent::RegisterSet { .. }
+            | TraceEvent::MemorySet { .. }
+            | TraceEvent::PageIn { .. }
+            | TraceEvent::PageOut { .. } => {}
+        }
+    }
+
+    // Close the events of the current instruction at `end`, or one cycle after
+    // it started if the end is unknown.
+    fn end_insn(&mut self, end: Option<u32>) {
+        let Some((pc, cycle)) = self.insn.take() else {
+            return;
+        };
+        let end = end.unwrap_or(cycle + 1).max(cycle);
+        self.events.push(ChromeEvent::Instruction {
+            pc,
+            cycle,
+            dur: end - cycle,
+        });
+        if let Some(name) = self.syscall.take() {
+            self.events
+                .push(ChromeEvent::SyscallEnd { name, cycle: end });
+        }
+    }
+}
+
+impl ChromeEvent {
+    fn to_json(&self) -> String {
+        match self {
+            Self::Instruction { pc, cycle, dur } => format!(
+                r#"{{"name":"0x{pc:08x}","cat":"insn","ph":"X","ts":{cycle},"dur":{dur},"pid":0,"tid":0}}"#
+            ),
+            Self::SyscallBegin { name, cycle } => format!(
+                r#"{{"name":"{}","cat":"syscall","ph":"B","ts":{cycle},"pid":0,"tid":1}}"#,
+                json_escape(name)
+            ),
+            Self::SyscallEnd { name, cycle } => format!(
+                r#"{{"name":"{}","cat":"syscall","ph":"E","ts":{cycle},"pid":0,"tid":1}}"#,
+                json_escape(name)
+            ),
+        }
+    }
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
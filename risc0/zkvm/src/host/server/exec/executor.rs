@@ -12,32 +12,116 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{cell::RefCell, io::Write, mem, rc::Rc, sync::Arc, time::Instant};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    io::Write,
+    mem, panic,
+    path::Path,
+    rc::Rc,
+    sync::{mpsc, Arc},
+    thread,
+    time::Instant,
+};
 
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use bytes::Bytes;
 use human_repr::HumanDuration as _;
-use risc0_binfmt::{MemoryImage, Program};
-use risc0_circuit_rv32im::prove::emu::{
-    addr::ByteAddr,
-    exec::{
-        Executor, Syscall as NewSyscall, SyscallContext as NewSyscallContext,
-        DEFAULT_SEGMENT_LIMIT_PO2,
+use risc0_binfmt::{MemoryImage, Program, SystemState};
+use risc0_circuit_rv32im::prove::{
+    emu::{
+        addr::ByteAddr,
+        exec::{
+            Executor, ExecutorError, Fault, Syscall as NewSyscall,
+            SyscallContext as NewSyscallContext, DEFAULT_SEGMENT_LIMIT_PO2,
+        },
+    },
+    segment::{Segment as CircuitSegment, SyscallRecord as CircuitSyscallRecord},
+};
+use risc0_zkp::core::{digest::Digest, hash::sha::BLOCK_BYTES};
+use risc0_zkvm_platform::{
+    fileno,
+    memory::GUEST_MAX_MEM,
+    syscall::{
+        nr::{SYS_IS_REPLAY, SYS_WRITE},
+        reg_abi::REG_MAX,
     },
+    PAGE_SIZE, WORD_SIZE,
 };
-use risc0_zkp::core::digest::Digest;
-use risc0_zkvm_platform::{fileno, memory::GUEST_MAX_MEM, PAGE_SIZE};
+use serde::{Deserialize, Serialize};
 use tempfile::tempdir;
 
 use crate::{
-    host::client::env::SegmentPath, Assumption, Assumptions, ExecutorEnv, FileSegmentRef, Output,
-    Segment, SegmentRef, Session,
+    host::{
+        client::{env::SegmentPath, slice_io::slice_io_from_fn},
+        prove_info::SessionStats,
+        server::session::NullSegmentRef,
+    },
+    Assumption, Assumptions, ExecutorEnv, ExitCode, FileSegmentRef, MaybePruned, Output, Segment,
+    SegmentRef, Session, SyscallRecord,
 };
 
 use super::{
+    basic_blocks::BasicBlockProfiler,
+    call_stack::{CallStackTracker, StackFrame},
     profiler::Profiler,
-    syscall::{SyscallContext, SyscallTable},
+    syscall::{SysSliceIo, SyscallContext, SyscallTable},
 };
 
+/// The state of a paused [ExecutorImpl], from which execution can be resumed
+/// later, e.g. in a different process.
+///
+/// Cycle counters, segment indices, and syscall state are all reset at the
+/// start of every run, so the memory image, which includes the registers and
+/// the program counter, is all that is needed to resume. The exit code of the
+/// last run is kept as well, so that the resumed [Session] knows whether it
+/// continues a pause.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutorCheckpoint {
+    image: MemoryImage,
+    exit_code: Option<ExitCode>,
+}
+
+impl ExecutorCheckpoint {
+    /// Returns the program counter execution will resume from.
+    pub fn pc(&self) -> u32 {
+        self.image.pc
+    }
+}
+
+/// A guest program loaded into its initial [MemoryImage] once, from which
+/// many executions can start with [ExecutorImpl::from_prepared].
+///
+/// Every [ExecutorImpl::from_elf] decodes the ELF binary and hashes the pages
+/// of its image. Running the same guest on many inputs only needs to do so
+/// once: each execution then starts from a copy of the prepared image.
+#[derive(Clone)]
+pub struct PreparedProgram {
+    image: MemoryImage,
+    page_size: u32,
+    memory_size: u32,
+}
+
+impl PreparedProgram {
+    /// Load `elf` into an image with the default page size and memory size.
+    pub fn new(elf: &[u8]) -> Result<Self> {
+        let page_size = PAGE_SIZE as u32;
+        let memory_size = GUEST_MAX_MEM as u32;
+        let program = Program::load_elf(elf, memory_size)?;
+        let image = MemoryImage::new(&program, page_size)?;
+        Ok(Self {
+            image,
+            page_size,
+            memory_size,
+        })
+    }
+
+    /// Returns the ImageID of the program.
+    pub fn image_id(&self) -> Digest {
+        self.image.compute_id()
+    }
+}
+
 // The Executor provides an implementation for the execution phase.
 ///
 /// The proving phase uses an execution trace generated by the Executor.
@@ -46,6 +130,15 @@ pub struct ExecutorImpl<'a> {
     image: MemoryImage,
     pub(crate) syscall_table: SyscallTable<'a>,
     profiler: Option<Rc<RefCell<Profiler>>>,
+    call_stack: Option<Rc<RefCell<CallStackTracker>>>,
+    call_stack_frames: Option<Vec<StackFrame>>,
+    basic_blocks: Option<Rc<RefCell<BasicBlockProfiler>>>,
+    pc: u32,
+    exit_code: Option<ExitCode>,
+    fault: Option<Fault>,
+    recorded_syscalls: RefCell<Vec<SyscallRecord>>,
+    replay: Option<RefCell<VecDeque<SyscallRecord>>>,
+    replayed: Cell<usize>,
 }
 
 impl<'a> ExecutorImpl<'a> {
@@ -77,10 +170,25 @@ impl<'a> ExecutorImpl<'a> {
     /// let mut exec = ExecutorImpl::from_elf(env, BENCH_ELF).unwrap();
     /// ```
     pub fn from_elf(mut env: ExecutorEnv<'a>, elf: &[u8]) -> Result<Self> {
-        let program = Program::load_elf(elf, GUEST_MAX_MEM as u32)?;
-        let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+        let page_size = env.page_size.unwrap_or(PAGE_SIZE as u32);
+        let memory_size = env.memory_size.unwrap_or(GUEST_MAX_MEM as u32);
+        ensure!(
+            page_size.is_power_of_two() && page_size as usize >= BLOCK_BYTES,
+            "page size {page_size} must be a power of two of at least {BLOCK_BYTES}"
+        );
+        ensure!(
+            memory_size > 0 && memory_size % page_size == 0,
+            "memory size {memory_size} must be a non-zero multiple of the page size {page_size}"
+        );
+        ensure!(
+            memory_size as usize <= GUEST_MAX_MEM,
+            "memory size {memory_size} exceeds the guest memory limit of {GUEST_MAX_MEM}"
+        );
+
+        let program = Program::load_elf(elf, memory_size)?;
+        let image = MemoryImage::new(&program, page_size)?;
 
-        let profiler = if env.pprof_out.is_some() {
+        let profiler = if env.pprof_out.is_some() || env.profile_lines {
             let profiler = Rc::new(RefCell::new(Profiler::new(elf, None)?));
             env.trace.push(profiler.clone());
             Some(profiler)
@@ -88,20 +196,123 @@ impl<'a> ExecutorImpl<'a> {
             None
         };
 
-        Self::with_details(env, image, profiler)
+        let call_stack = if env.capture_call_stack {
+            let call_stack = Rc::new(RefCell::new(CallStackTracker::new(elf)?));
+            env.trace.push(call_stack.clone());
+            Some(call_stack)
+        } else {
+            None
+        };
+
+        let mut exec = Self::with_details(env, image, profiler)?;
+        exec.call_stack = call_stack;
+        Ok(exec)
+    }
+
+    /// Construct a new [ExecutorImpl] that starts from a [PreparedProgram].
+    ///
+    /// This runs the same guest as [ExecutorImpl::from_elf] without decoding
+    /// the ELF binary again. The [ExecutorEnv] may not set a page size or
+    /// memory size other than those the program was prepared with, and, as
+    /// with [ExecutorImpl::new], options that need the ELF binary, such as the
+    /// profiler, are not available.
+    ///
+    /// # Example
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, ExecutorImpl, PreparedProgram};
+    /// use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+    ///
+    /// let prepared = PreparedProgram::new(MULTI_TEST_ELF).unwrap();
+    /// for _ in 0..2 {
+    ///     let env = ExecutorEnv::builder()
+    ///         .write(&MultiTestSpec::DoNothing)
+    ///         .unwrap()
+    ///         .build()
+    ///         .unwrap();
+    ///     let session = ExecutorImpl::from_prepared(env, &prepared)
+    ///         .unwrap()
+    ///         .run()
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn from_prepared(env: ExecutorEnv<'a>, prepared: &PreparedProgram) -> Result<Self> {
+        let page_size = env.page_size.unwrap_or(prepared.page_size);
+        let memory_size = env.memory_size.unwrap_or(prepared.memory_size);
+        ensure!(
+            page_size == prepared.page_size,
+            "page size {page_size} does not match the prepared program's {}",
+            prepared.page_size
+        );
+        ensure!(
+            memory_size == prepared.memory_size,
+            "memory size {memory_size} does not match the prepared program's {}",
+            prepared.memory_size
+        );
+        Self::new(env, prepared.image.clone())
+    }
+
+    /// Construct a new [ExecutorImpl] from a [MemoryImage] snapshot written by
+    /// [MemoryImage::save], starting execution at `pc`.
+    ///
+    /// This skips loading the ELF binary and paging in the image, which speeds
+    /// up repeated runs of the same guest. `pc` is normally the entry point
+    /// the image was built with, i.e. its [MemoryImage::pc]. As with
+    /// [ExecutorImpl::new], options that need the ELF binary, such as the
+    /// profiler, are not available.
+    pub fn from_image_file(env: ExecutorEnv<'a>, path: impl AsRef<Path>, pc: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let mut image = MemoryImage::load(path)
+            .with_context(|| format!("failed to load image snapshot {}", path.display()))?;
+        image.pc = pc;
+        Self::new(env, image)
+    }
+
+    /// Construct a new [ExecutorImpl] that resumes execution from
+    /// `checkpoint`.
+    ///
+    /// The [ExecutorEnv] should provide whatever the guest still expects to
+    /// read after the checkpoint, and be configured like the one used before
+    /// it. Options that need the ELF binary, such as the profiler, are not
+    /// available.
+    pub fn from_checkpoint(env: ExecutorEnv<'a>, checkpoint: ExecutorCheckpoint) -> Result<Self> {
+        let mut exec = Self::new(env, checkpoint.image)?;
+        exec.exit_code = checkpoint.exit_code;
+        Ok(exec)
     }
 
     fn with_details(
-        env: ExecutorEnv<'a>,
+        mut env: ExecutorEnv<'a>,
         image: MemoryImage,
         profiler: Option<Rc<RefCell<Profiler>>>,
     ) -> Result<Self> {
+        let basic_blocks = if env.profile_basic_blocks {
+            let basic_blocks = Rc::new(RefCell::new(BasicBlockProfiler::default()));
+            env.trace.push(basic_blocks.clone());
+            Some(basic_blocks)
+        } else {
+            None
+        };
+
         let syscall_table = SyscallTable::new(&env);
+        let replay = env
+            .replay
+            .take()
+            .map(|syscalls| RefCell::new(syscalls.into()));
+        let pc = image.pc;
         Ok(Self {
             env,
             image,
             syscall_table,
             profiler,
+            call_stack: None,
+            call_stack_frames: None,
+            basic_blocks,
+            pc,
+            exit_code: None,
+            fault: None,
+            recorded_syscalls: RefCell::new(Vec::new()),
+            replay,
+            replayed: Cell::new(0),
         })
     }
 
@@ -122,12 +333,36 @@ impl<'a> ExecutorImpl<'a> {
     where
         F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
     {
-        let journal = Journal::default();
+        let resumed_from_pause = matches!(self.exit_code, Some(ExitCode::Paused(_)));
+        let journal = Journal {
+            contents: match self.env.disable_journal_capture {
+                true => JournalContents::Hashed(Rc::default()),
+                false => JournalContents::Captured(Rc::default()),
+            },
+            inner: self.env.journal_writer.clone(),
+        };
         self.env
             .posix_io
             .borrow_mut()
             .with_write_fd(fileno::JOURNAL, journal.clone());
 
+        // Capture stderr, while still forwarding it to the configured writer.
+        let stderr = Rc::new(RefCell::new(Vec::new()));
+        let prev_stderr = self
+            .env
+            .posix_io
+            .borrow()
+            .write_fds
+            .get(&fileno::STDERR)
+            .cloned();
+        self.env.posix_io.borrow_mut().with_write_fd(
+            fileno::STDERR,
+            Capture {
+                buf: stderr.clone(),
+                inner: prev_stderr.clone(),
+            },
+        );
+
         let segment_limit_po2 = self
             .env
             .segment_limit_po2
@@ -135,8 +370,13 @@ impl<'a> ExecutorImpl<'a> {
 
         let mut refs = Vec::new();
         let mut exec = Executor::new(self.image.clone(), self, self.env.trace.clone());
+        self.configure(&mut exec);
 
+        let mut segment_durations = Vec::new();
+        let mut segment_po2s = Vec::new();
+        let mut syscall_summary = BTreeMap::new();
         let start_time = Instant::now();
+        let mut segment_start = start_time;
         let result = exec.run(segment_limit_po2, self.env.session_limit, |inner| {
             let output = inner
                 .exit_code
@@ -144,12 +384,10 @@ impl<'a> ExecutorImpl<'a> {
                 .then(|| -> Option<Result<_>> {
                     inner
                         .output_digest
-                        .and_then(|digest| {
-                            (digest != Digest::ZERO).then(|| journal.buf.borrow().clone())
-                        })
+                        .and_then(|digest| (digest != Digest::ZERO).then(|| journal.output()))
                         .map(|journal| {
                             Ok(Output {
-                                journal: journal.into(),
+                                journal,
                                 assumptions: Assumptions(
                                     self.env
                                         .assumptions
@@ -171,41 +409,106 @@ impl<'a> ExecutorImpl<'a> {
                 .flatten()
                 .transpose()?;
 
+            for syscall in inner.syscalls.iter() {
+                *syscall_summary.entry(syscall.name.clone()).or_default() += 1;
+            }
+            segment_po2s.push(inner.po2);
+
             let segment = Segment {
                 index: inner.index as u32,
                 inner,
                 output,
             };
+            segment_durations.push(segment_start.elapsed());
             let segment_ref = callback(segment.into())?;
             refs.push(segment_ref);
+            segment_start = Instant::now();
             Ok(())
-        })?;
+        });
+        let fault = exec.fault();
+        let final_registers: Result<Vec<u32>> =
+            (0..REG_MAX).map(|idx| exec.get_register(idx)).collect();
+        self.pc = exec.pc();
+        if let (Some(call_stack), Ok(regs)) = (&self.call_stack, &final_registers) {
+            let read_u32 = |addr| {
+                let bytes = exec.read_memory(addr, WORD_SIZE as u32).ok()?;
+                Some(u32::from_le_bytes(bytes.try_into().ok()?))
+            };
+            self.call_stack_frames = Some(call_stack.borrow().frames(self.pc, regs, read_u32));
+        }
+        self.fault = fault;
+
+        // Restore the configured stderr writer, so that a resumed run doesn't
+        // capture through a stale buffer.
+        let mut posix_io = self.env.posix_io.borrow_mut();
+        match prev_stderr {
+            Some(prev_stderr) => posix_io.write_fds.insert(fileno::STDERR, prev_stderr),
+            None => posix_io.write_fds.remove(&fileno::STDERR),
+        };
+        drop(posix_io);
+
+        // Guests built against an older runtime fault after reporting a panic,
+        // rather than halting, so keep the message with the error.
+        let panic_message = self.env.panic_message.borrow_mut().take();
+        let result = match (result, &panic_message) {
+            (Err(err), Some(msg)) => return Err(err.context(format!("Guest panicked: {msg}"))),
+            (result, _) => result?,
+        };
         let elapsed = start_time.elapsed();
 
+        if let (ExitCode::Halted(_), Some(replay)) = (result.exit_code, &self.replay) {
+            let replay = replay.borrow();
+            if let Some(next) = replay.front() {
+                bail!(
+                    "replay diverged after syscall {}: the guest halted, but {} more syscalls \
+                    were recorded, starting with {:?}",
+                    self.replayed.get(),
+                    replay.len(),
+                    next.name
+                );
+            }
+        }
+
         // Set the session_journal to the committed data iff the the guest set a non-zero output.
         let session_journal = result
             .output_digest
-            .and_then(|digest| (digest != Digest::ZERO).then(|| journal.buf.take()));
+            .and_then(|digest| (digest != Digest::ZERO).then(|| journal.take()));
         if !result.exit_code.expects_output() && session_journal.is_some() {
             tracing::debug!(
-                "dropping non-empty journal due to exit code {:?}: 0x{}",
+                "dropping non-empty journal due to exit code {:?}: {:?}",
                 result.exit_code,
-                hex::encode(journal.buf.borrow().as_slice())
+                session_journal
             );
         };
+        // A journal that was not captured is only known by its digest.
+        let (session_journal, journal_digest) = match session_journal {
+            Some(MaybePruned::Value(journal)) => (Some(journal), None),
+            Some(MaybePruned::Pruned(digest)) => (None, Some(digest)),
+            None => (None, None),
+        };
 
         // Take (clear out) the list of accessed assumptions.
         // Leave the assumptions cache so it can be used if execution is resumed from pause.
         let assumptions = mem::take(&mut self.env.assumptions.borrow_mut().accessed);
 
+        let mut line_profile = Vec::new();
         if let Some(profiler) = self.profiler.take() {
-            let report = profiler.borrow_mut().finalize_to_vec();
-            std::fs::write(self.env.pprof_out.as_ref().unwrap(), report)?;
+            let mut profiler = profiler.borrow_mut();
+            if self.env.profile_lines {
+                line_profile = profiler.line_profile();
+            }
+            if let Some(path) = &self.env.pprof_out {
+                std::fs::write(path, profiler.finalize_to_vec())?;
+            }
         }
 
         self.image = result.post_image.clone();
+        self.exit_code = Some(result.exit_code);
+        if let Some(breakpoint) = result.memory_breakpoint {
+            self.env.memory_breakpoints.retain(|bp| *bp != breakpoint);
+        }
 
-        let session = Session::new(
+        let mut session = Session::new(
             refs,
             session_journal,
             result.exit_code,
@@ -216,6 +519,30 @@ impl<'a> ExecutorImpl<'a> {
             result.pre_state,
             result.post_state,
         );
+        session.consumed_input = mem::take(&mut self.env.posix_io.borrow_mut().consumed_stdin);
+        session.opcode_counts = result.opcode_counts;
+        session.covered_pcs = result.covered_pcs;
+        session.self_modified_pcs = result.self_modified_pcs;
+        session.memory_breakpoint = result.memory_breakpoint;
+        session.output_digest = result.output_digest;
+        session.journal_digest = journal_digest;
+        session.stderr = stderr.take();
+        session.panic_message = panic_message;
+        session.commits = mem::take(&mut self.env.commits.borrow_mut());
+        session.final_registers.copy_from_slice(&final_registers?);
+        session.paging_stats = result.paging_stats;
+        session.total_instructions = result.total_insns;
+        session.syscalls = self.recorded_syscalls.take();
+        session.syscall_summary = syscall_summary;
+        session.segment_durations = segment_durations;
+        session.segment_po2s = segment_po2s;
+        session.resumed_from_pause = resumed_from_pause;
+        session.executor_only_ecalls = result.executor_only_ecalls;
+        session.unprovable = result.unprovable;
+        session.line_profile = line_profile;
+        if let Some(basic_blocks) = &self.basic_blocks {
+            session.hot_blocks = basic_blocks.borrow_mut().take_blocks();
+        }
 
         tracing::info_span!("executor").in_scope(|| {
             tracing::info!("execution time: {}", elapsed.human_duration());
@@ -224,6 +551,256 @@ impl<'a> ExecutorImpl<'a> {
 
         Ok(session)
     }
+
+    /// Run the executor like [Self::run_with_callback], but hand each
+    /// [Segment] to `callback` on a separate worker thread.
+    ///
+    /// This lets persisting a segment, e.g. serializing or uploading it,
+    /// overlap with executing the next one. Up to `capacity` segments are
+    /// queued for the worker; once the queue is full, execution waits for the
+    /// worker to catch up, so a slow callback bounds the memory used rather
+    /// than letting segments pile up. The callback receives the segments in
+    /// order, and an error it returns fails the run.
+    pub fn run_with_async_callback<F>(
+        &mut self,
+        capacity: usize,
+        mut callback: F,
+    ) -> Result<Session>
+    where
+        F: FnMut(Segment) -> Result<Box<dyn SegmentRef>> + Send,
+    {
+        let (sender, receiver) = mpsc::sync_channel::<Segment>(capacity);
+        thread::scope(|scope| {
+            let worker = scope.spawn(move || {
+                receiver
+                    .into_iter()
+                    .map(&mut callback)
+                    .collect::<Result<Vec<_>>>()
+            });
+
+            let result = self.run_with_callback(|segment| {
+                sender
+                    .send(segment)
+                    .map_err(|_| anyhow!("segment worker stopped"))?;
+                Ok(Box::new(NullSegmentRef))
+            });
+            drop(sender);
+
+            // A failed callback stops the worker, which in turn fails the
+            // run, so report the error of the worker first.
+            let refs = worker
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err))?;
+            let mut session = result?;
+            session.segments = refs;
+            Ok(session)
+        })
+    }
+
+    /// Run the executor to count the segments and cycles of the guest program
+    /// without materializing any [Segment]s.
+    ///
+    /// This is cheaper than [Self::run] when only the cycle counts are needed,
+    /// e.g. to estimate proving costs. Like [Self::run], this consumes the
+    /// input provided by the [ExecutorEnv], so the same [ExecutorImpl] should
+    /// not be run again afterwards.
+    pub fn dry_run(&mut self) -> Result<SessionStats> {
+        let segment_limit_po2 = self
+            .env
+            .segment_limit_po2
+            .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32) as usize;
+
+        let mut exec = Executor::new(self.image.clone(), self, self.env.trace.clone());
+        self.configure(&mut exec);
+
+        let result = exec.dry_run(segment_limit_po2, self.env.session_limit);
+        self.fault = exec.fault();
+        let result = result?;
+        for (idx, po2) in result.segment_po2s.iter().enumerate() {
+            tracing::debug!("segment {idx}: {} cycles", 1u64 << po2);
+        }
+
+        Ok(SessionStats {
+            segments: result.segments,
+            total_cycles: result.total_cycles,
+            user_cycles: result.user_cycles,
+        })
+    }
+
+    /// Run the executor to count the [Segment]s the guest program splits into,
+    /// e.g. to size proving resources up front.
+    ///
+    /// This performs the same splits as [Self::run], so the count always
+    /// matches the number of segments of the resulting [Session], but like
+    /// [Self::dry_run] it does not materialize them.
+    pub fn count_segments(&mut self) -> Result<usize> {
+        Ok(self.dry_run()?.segments)
+    }
+
+    /// Capture the state of this [ExecutorImpl] after it has paused, so that
+    /// execution can be resumed with [Self::from_checkpoint].
+    ///
+    /// This fails if the guest has halted, since there is nothing left to
+    /// resume.
+    pub fn checkpoint(&self) -> Result<ExecutorCheckpoint> {
+        if let Some(ExitCode::Halted(_)) = self.exit_code {
+            bail!("cannot checkpoint an execution that has halted");
+        }
+        Ok(ExecutorCheckpoint {
+            image: self.image.clone(),
+            exit_code: self.exit_code,
+        })
+    }
+
+    /// Re-execute `segment` from its partial image, replaying the syscalls it
+    /// recorded, and return its post state.
+    ///
+    /// The [ExecutorEnv] should be configured like the one the segment was
+    /// originally produced with, e.g. with the same segment limit.
+    pub(crate) fn run_segment(&self, segment: &CircuitSegment) -> Result<SystemState> {
+        let segment_limit_po2 = self
+            .env
+            .segment_limit_po2
+            .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32) as usize;
+
+        let replay = SyscallReplay {
+            syscalls: RefCell::new(segment.syscalls.iter().cloned().collect()),
+        };
+        let mut exec = Executor::new(
+            segment.partial_image.clone(),
+            &replay,
+            self.env.trace.clone(),
+        );
+        self.configure(&mut exec);
+        exec.run_segment(segment_limit_po2)
+    }
+
+    /// Handle the syscall `name` with `callback`, in the manner of
+    /// [ExecutorEnvBuilder::io_callback], replacing any existing handler.
+    ///
+    /// Unlike the handlers of the [ExecutorEnv], this can be called after
+    /// construction, e.g. once the available handlers have been discovered.
+    /// The handler is used for every invocation from the next run on. This
+    /// fails while syscalls recorded with
+    /// [ExecutorEnvBuilder::replay] are pending, since those are not handled
+    /// by the host at all.
+    ///
+    /// [ExecutorEnvBuilder::io_callback]: crate::ExecutorEnvBuilder::io_callback
+    /// [ExecutorEnvBuilder::replay]: crate::ExecutorEnvBuilder::replay
+    pub fn register_syscall<C: AsRef<str>>(
+        &mut self,
+        name: C,
+        callback: impl Fn(Bytes) -> Result<Bytes> + 'a,
+    ) -> Result<()> {
+        let name = name.as_ref();
+        if let Some(replay) = &self.replay {
+            ensure!(
+                replay.borrow().is_empty(),
+                "cannot register syscall {name:?} while replaying recorded syscalls"
+            );
+        }
+        let handler = SysSliceIo::new(slice_io_from_fn(callback));
+        self.syscall_table
+            .inner
+            .insert(name.to_string(), Rc::new(RefCell::new(handler)));
+        Ok(())
+    }
+
+    /// Returns the call stack of the guest, innermost frame first, as of where
+    /// the last run stopped.
+    ///
+    /// After a fault, the innermost frame is the instruction that faulted;
+    /// after a pause, it is the instruction execution will resume from. The
+    /// stack is unwound in guest memory using the DWARF call frame information
+    /// in the ELF, if present, and otherwise reconstructed from the calls and
+    /// returns the guest executed. This requires [ExecutorEnvBuilder::capture_call_stack] and an
+    /// [ExecutorImpl] constructed with [ExecutorImpl::from_elf], so that
+    /// function names can be resolved from the symbol table.
+    ///
+    /// [ExecutorEnvBuilder::capture_call_stack]: crate::ExecutorEnvBuilder::capture_call_stack
+    pub fn call_stack(&self) -> Result<Vec<StackFrame>> {
+        let call_stack = self
+            .call_stack
+            .as_ref()
+            .context("call stack capture is not enabled")?;
+        Ok(match &self.call_stack_frames {
+            Some(frames) => frames.clone(),
+            None => call_stack.borrow().frames(self.pc, &[], |_| None),
+        })
+    }
+
+    /// Returns the instruction that the last run faulted at, and why.
+    ///
+    /// A fault fails the run with an error rather than producing a [Session],
+    /// so this is where debuggers can tell an undecodable instruction from an
+    /// unknown ecall or an invalid memory access. It is `None` if the last run
+    /// succeeded, or failed for a reason other than executing an instruction,
+    /// e.g. reaching the session limit.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// Apply the execution options of the [ExecutorEnv] to `exec`.
+    fn configure<S: NewSyscall>(&self, exec: &mut Executor<'_, 'a, S>) {
+        for region in self.env.readonly_regions.iter() {
+            exec.readonly_region(region.clone());
+        }
+        for (name, max_per_run) in self.env.syscall_rate_limits.iter() {
+            exec.syscall_rate_limit(name, *max_per_run);
+        }
+        if let Some(insns) = self.env.split_every_insns {
+            exec.split_every_insns(insns);
+        }
+        if let Some(max_segments) = self.env.max_segments {
+            exec.max_segments(max_segments);
+        }
+        exec.loader_cycles(self.env.loader_cycles);
+        if let Some(cycles) = self.env.reserved_cycles {
+            exec.reserved_cycles(cycles as usize);
+        }
+        if let Some(len) = self.env.max_string_len {
+            exec.max_string_len(len as usize);
+        }
+        if let Some(fuel) = self.env.initial_fuel {
+            exec.initial_fuel(fuel);
+        }
+        if let Some(limit) = self.env.fuel_limit {
+            exec.fuel_limit(limit);
+        }
+        exec.fuel_costs(self.env.fuel_costs.clone());
+        for (addr, value) in self.env.memory_breakpoints.iter() {
+            exec.pause_on_memory_value(*addr, *value);
+        }
+        exec.syscall_replay(!self.env.disable_syscall_replay);
+        exec.track_coverage(self.env.track_coverage);
+        exec.detect_self_modifying_code(self.env.detect_self_modifying_code);
+        exec.cycle_cost_model(self.env.cycle_cost_model);
+        exec.insn_cycle_costs(self.env.insn_cycle_costs.clone());
+        exec.session_limit_behavior(self.env.session_limit_behavior);
+        if let Some(digest) = self.env.expected_output_digest {
+            exec.expect_output_digest(digest);
+        }
+        if let Some(timeout) = self.env.wall_clock_timeout {
+            exec.wall_clock_timeout(timeout);
+        }
+        if let Some(flag) = &self.env.cancel_flag {
+            exec.cancel_flag(flag.clone());
+        }
+        if let Some(callback) = &self.env.progress_callback {
+            let callback = callback.clone();
+            exec.progress_callback(self.env.progress_interval, move |info| {
+                (callback.borrow_mut())(info)
+            });
+        }
+        if let Some(callback) = &self.env.split_callback {
+            let callback = callback.clone();
+            exec.split_callback(move |info| (callback.borrow_mut())(info));
+        }
+        if let Some(callback) = &self.env.ecall_callback {
+            let callback = callback.clone();
+            exec.ecall_callback(move |info| (callback.borrow_mut())(info));
+        }
+    }
 }
 
 struct ContextAdapter<'a> {
@@ -235,6 +812,10 @@ impl<'a> SyscallContext for ContextAdapter<'a> {
         self.ctx.get_cycle()
     }
 
+    fn get_segment_remaining(&self) -> usize {
+        self.ctx.get_segment_remaining()
+    }
+
     fn load_register(&mut self, idx: usize) -> u32 {
         self.ctx.peek_register(idx).unwrap()
     }
@@ -242,6 +823,10 @@ impl<'a> SyscallContext for ContextAdapter<'a> {
     fn load_u8(&mut self, addr: u32) -> Result<u8> {
         self.ctx.peek_u8(ByteAddr(addr))
     }
+
+    fn call_guest(&mut self, addr: u32, args: &[u32]) -> Result<u32> {
+        self.ctx.call_guest(ByteAddr(addr), args)
+    }
 }
 
 impl<'a> NewSyscall for ExecutorImpl<'a> {
@@ -252,26 +837,185 @@ impl<'a> NewSyscall for ExecutorImpl<'a> {
         into_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
         let mut ctx = ContextAdapter { ctx };
+        // Whether the guest is being replayed differs between the recording
+        // and the replay, so it is neither recorded nor replayed.
+        if syscall == SYS_IS_REPLAY.as_str() {
+            return self.dispatch(syscall, &mut ctx, into_guest);
+        }
+        let regs = match &self.replay {
+            Some(replay) => {
+                let record = self.next_replayed(replay, syscall, into_guest.len())?;
+                // Deliver writes, so that the journal and output are captured.
+                if syscall == SYS_WRITE.as_str() {
+                    self.dispatch(syscall, &mut ctx, into_guest)?;
+                }
+                into_guest.copy_from_slice(&record.to_guest);
+                record.regs
+            }
+            None => self.dispatch(syscall, &mut ctx, into_guest)?,
+        };
+        if self.env.record_syscalls {
+            self.recorded_syscalls.borrow_mut().push(SyscallRecord {
+                name: syscall.to_string(),
+                to_guest: into_guest.to_vec(),
+                regs,
+            });
+        }
+        Ok(regs)
+    }
+}
+
+impl<'a> ExecutorImpl<'a> {
+    // Take the next recorded syscall, checking that it is the one the guest
+    // requests, so that a guest which diverges from the recording fails loudly.
+    fn next_replayed(
+        &self,
+        replay: &RefCell<VecDeque<SyscallRecord>>,
+        syscall: &str,
+        to_guest_words: usize,
+    ) -> Result<SyscallRecord> {
+        let index = self.replayed.get();
+        let record = replay.borrow_mut().pop_front().with_context(|| {
+            format!(
+                "replay diverged at syscall {index}: expected no more syscalls, \
+                but the guest requested {syscall:?}"
+            )
+        })?;
+        ensure!(
+            record.name == syscall,
+            "replay diverged at syscall {index}: expected {:?}, but the guest requested {syscall:?}",
+            record.name
+        );
+        ensure!(
+            record.to_guest.len() == to_guest_words,
+            "replay diverged at syscall {index} ({syscall:?}): expected {} words to the guest, \
+            but the guest requested {to_guest_words}",
+            record.to_guest.len()
+        );
+        self.replayed.set(index + 1);
+        Ok(record)
+    }
+
+    fn dispatch(
+        &self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
         self.syscall_table
-            .get_syscall(&syscall)
-            .context(format!("Unknown syscall: {syscall:?}"))?
+            .get_syscall(syscall)
+            .ok_or_else(|| ExecutorError::UnknownSyscall(syscall.to_string()))?
             .borrow_mut()
-            .syscall(&syscall, &mut ctx, into_guest)
+            .syscall(syscall, ctx, into_guest)
     }
 }
 
-// Capture the journal output in a buffer that we can access afterwards.
-#[derive(Clone, Default)]
-struct Journal {
+// Replays the syscalls recorded in a segment, in order.
+struct SyscallReplay {
+    syscalls: RefCell<VecDeque<CircuitSyscallRecord>>,
+}
+
+impl NewSyscall for SyscallReplay {
+    fn syscall(
+        &self,
+        _syscall: &str,
+        _ctx: &mut dyn NewSyscallContext,
+        into_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        // The instruction that triggers a split is undone and its syscall is
+        // recorded in the next segment, so running out is expected there.
+        let Some(syscall) = self.syscalls.borrow_mut().pop_front() else {
+            return Ok((0, 0));
+        };
+        ensure!(
+            into_guest.len() == syscall.to_guest.len(),
+            "recorded syscall returned {} words, expected {}",
+            syscall.to_guest.len(),
+            into_guest.len()
+        );
+        into_guest.copy_from_slice(&syscall.to_guest);
+        Ok(syscall.regs)
+    }
+}
+
+// Capture the journal output in a buffer that we can access afterwards, or
+// only its digest if capture is disabled, while passing it through to the
+// journal writer of the env as it is written.
+#[derive(Clone)]
+struct Journal<'a> {
+    contents: JournalContents,
+    inner: Option<Rc<RefCell<dyn Write + 'a>>>,
+}
+
+#[derive(Clone)]
+enum JournalContents {
+    Captured(Rc<RefCell<Vec<u8>>>),
+    Hashed(Rc<RefCell<sha2::Sha256>>),
+}
+
+impl Journal<'_> {
+    // The journal written so far, as committed to by the output of a segment.
+    fn output(&self) -> MaybePruned<Vec<u8>> {
+        match &self.contents {
+            JournalContents::Captured(buf) => MaybePruned::Value(buf.borrow().clone()),
+            JournalContents::Hashed(hasher) => MaybePruned::Pruned(Self::digest(hasher)),
+        }
+    }
+
+    // Like [Self::output], but moving the captured journal out of the buffer.
+    fn take(&self) -> MaybePruned<Vec<u8>> {
+        match &self.contents {
+            JournalContents::Captured(buf) => MaybePruned::Value(buf.take()),
+            JournalContents::Hashed(hasher) => MaybePruned::Pruned(Self::digest(hasher)),
+        }
+    }
+
+    fn digest(hasher: &RefCell<sha2::Sha256>) -> Digest {
+        use sha2::Digest as _;
+        Digest::try_from(hasher.borrow().clone().finalize().as_slice()).unwrap()
+    }
+}
+
+impl Write for Journal<'_> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        use sha2::Digest as _;
+        if let Some(inner) = &self.inner {
+            inner.borrow_mut().write_all(bytes)?;
+        }
+        match &self.contents {
+            JournalContents::Captured(buf) => buf.borrow_mut().extend_from_slice(bytes),
+            JournalContents::Hashed(hasher) => hasher.borrow_mut().update(bytes),
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &self.inner {
+            Some(inner) => inner.borrow_mut().flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+// Capture output in a buffer, while passing it through to an inner writer.
+struct Capture<'a> {
     buf: Rc<RefCell<Vec<u8>>>,
+    inner: Option<Rc<RefCell<dyn Write + 'a>>>,
 }
 
-impl Write for Journal {
+impl Write for Capture<'_> {
     fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
-        self.buf.borrow_mut().write(bytes)
+        if let Some(inner) = &self.inner {
+            inner.borrow_mut().write_all(bytes)?;
+        }
+        self.buf.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.buf.borrow_mut().flush()
+        match &self.inner {
+            Some(inner) => inner.borrow_mut().flush(),
+            None => Ok(()),
+        }
     }
 }
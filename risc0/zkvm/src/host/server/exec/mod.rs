@@ -18,6 +18,10 @@
 //! [crate::Session] contains one or more [crate::Segment]s, each of which
 //! contains an execution trace of the specified program.
 
+pub(crate) mod basic_blocks;
+pub(crate) mod binary_trace;
+pub(crate) mod call_stack;
+pub(crate) mod chrome_trace;
 pub(crate) mod executor;
 pub(crate) mod profiler;
 pub(crate) mod syscall;
@@ -18,10 +18,13 @@
 //! guest.  It does not trace full stack traces, but only provides the
 //! top level stack frame.  (More than one stack frame may show up
 //! in the case of inlined functions).
+//!
+//! Cycles are also counted by instruction, so that they can be attributed to
+//! source lines when the guest is built with debug info.
 
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::Write,
     hash::{Hash, Hasher},
     rc::Rc,
@@ -43,14 +46,14 @@ use rustc_demangle::demangle;
 use self::proto::Line;
 use crate::{TraceCallback, TraceEvent};
 
-mod proto {
+pub(crate) mod proto {
     // Generated proto interface.
     include!(concat!(env!("OUT_DIR"), "/perftools.profiles.rs"));
 }
 
 /// Operations effecting the function call stack.
 #[derive(Debug)]
-enum CallStackOp {
+pub(crate) enum CallStackOp {
     Push,
     Pop,
     PopPush,
@@ -64,7 +67,7 @@ enum CallStackOp {
 /// return, or neither.
 ///
 /// [RISC-V ISA manual]: https://riscv.org/wp-content/uploads/2017/05/riscv-spec-v2.2.pdf
-fn extract_call_stack_op(insn: u32) -> Option<CallStackOp> {
+pub(crate) fn extract_call_stack_op(insn: u32) -> Option<CallStackOp> {
     let opcode: u32 = insn & 0x7f;
 
     match opcode {
@@ -159,6 +162,9 @@ pub struct Profiler {
     // Current CallNode key in the stack
     current_key: u32,
 
+    // Cycles spent at each program counter
+    pc_cycles: HashMap<u32, u64>,
+
     ctx: ObjectContext,
 
     profile: ProfileBuilder,
@@ -222,6 +228,7 @@ impl Profiler {
             root: Rc::clone(&root),
             current_node: Some(root),
             current_key: 0,
+            pc_cycles: HashMap::new(),
             call_stack_path: Vec::new(),
             ctx,
             profile: ProfileBuilder::new(),
@@ -298,6 +305,32 @@ impl Profiler {
         frames
     }
 
+    /// Returns the cycles spent on each source line, as `(file, line, cycles)`
+    /// sorted from the hottest line down.
+    ///
+    /// Lines are resolved from the DWARF line table of the ELF. Instructions
+    /// without line info, which is all of them in a guest built without debug
+    /// info, are left out; their cycles are still attributed to functions in
+    /// the pprof output.
+    pub fn line_profile(&self) -> Vec<(String, u32, u64)> {
+        let mut lines: BTreeMap<(String, u32), u64> = BTreeMap::new();
+        for (&pc, &cycles) in &self.pc_cycles {
+            let Ok(Some(location)) = self.ctx.find_location(pc as u64) else {
+                continue;
+            };
+            let (Some(file), Some(line)) = (location.file, location.line) else {
+                continue;
+            };
+            *lines.entry((file.to_string(), line)).or_default() += cycles;
+        }
+        let mut lines: Vec<_> = lines
+            .into_iter()
+            .map(|((file, line), cycles)| (file, line, cycles))
+            .collect();
+        lines.sort_by(|a, b| b.2.cmp(&a.2));
+        lines
+    }
+
     /// Walk the profile tree rooted at node_ref, adding all call stacks in the profile to the
     /// profile under construction. All call stacks encountered build on top of the base_stack.
     fn walk_stacks(&mut self, node_ref: Rc<RefCell<CallNode>>, base_stack: Vec<Frame>) {
@@ -369,6 +402,10 @@ impl TraceCallback for Profiler {
                 let orig_pc = self.pc;
                 let orig_insn = self.insn;
 
+                if orig_pc != u32::MAX {
+                    *self.pc_cycles.entry(orig_pc).or_default() += cycles as u64;
+                }
+
                 if self.call_stack_path.len() > 0 {
                     let current_node = self
                         .current_node
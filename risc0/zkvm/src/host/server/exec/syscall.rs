@@ -18,12 +18,20 @@ use std::{cell::RefCell, cmp::min, collections::HashMap, rc::Rc, str::from_utf8}
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
+use rand_chacha::{
+    rand_core::{RngCore as _, SeedableRng as _},
+    ChaCha20Rng,
+};
+use risc0_circuit_rv32im::prove::emu::exec::{ExecutorError, DEFAULT_SEGMENT_LIMIT_PO2};
 use risc0_zkvm_platform::{
     fileno,
     syscall::{
+        getenv, log_level,
         nr::{
-            SYS_ARGC, SYS_ARGV, SYS_CYCLE_COUNT, SYS_GETENV, SYS_LOG, SYS_PANIC, SYS_RANDOM,
-            SYS_READ, SYS_VERIFY, SYS_VERIFY_INTEGRITY, SYS_WRITE,
+            SYS_ARGC, SYS_ARGV, SYS_CLOCK, SYS_COMMIT, SYS_CYCLE_COUNT, SYS_GETENV, SYS_IS_REPLAY,
+            SYS_LOG, SYS_LOG_LEVEL, SYS_PANIC, SYS_RANDOM, SYS_READ, SYS_READ_CHANNEL,
+            SYS_SEGMENT_PO2, SYS_SEGMENT_REMAINING, SYS_SPLIT, SYS_VERIFY, SYS_VERIFY_INTEGRITY,
+            SYS_WRITE,
         },
         reg_abi::{REG_A3, REG_A4, REG_A5},
         SyscallName, DIGEST_BYTES, DIGEST_WORDS,
@@ -57,12 +65,23 @@ pub trait SyscallContext {
     /// Returns the current cycle being executed.
     fn get_cycle(&self) -> usize;
 
+    /// Returns the number of cycles left in the current segment before it is
+    /// split, or zero if the segment is already full.
+    fn get_segment_remaining(&self) -> usize;
+
     /// Loads the value of the given register, e.g. REG_A0.
     fn load_register(&mut self, idx: usize) -> u32;
 
     /// Loads an individual byte from memory.
     fn load_u8(&mut self, addr: u32) -> Result<u8>;
 
+    /// Calls the guest function at `addr` with up to eight arguments and
+    /// returns the value it leaves in `a0`.
+    ///
+    /// All side effects of the call are rolled back once it returns. The
+    /// callee may not issue any ecalls, and calls cannot be nested.
+    fn call_guest(&mut self, addr: u32, args: &[u32]) -> Result<u32>;
+
     /// Loads bytes from the given region of memory.
     fn load_region(&mut self, addr: u32, size: u32) -> Result<Vec<u8>> {
         let mut region = Vec::new();
@@ -86,13 +105,30 @@ impl<'a> SyscallTable<'a> {
 
         let sys_verify = SysVerify::new(env.assumptions.clone());
 
+        let segment_po2 = env
+            .segment_limit_po2
+            .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32);
+
+        let rng = env
+            .rng
+            .clone()
+            .unwrap_or_else(|| Rc::new(RefCell::new(ChaCha20Rng::from_seed([0; 32]))));
+
         let posix_io = env.posix_io.clone();
-        this.with_syscall(SYS_CYCLE_COUNT, SysCycleCount)
+        this.with_syscall(SYS_COMMIT, SysCommit(env.commits.clone()))
+            .with_syscall(SYS_CYCLE_COUNT, SysCycleCount)
+            .with_syscall(SYS_CLOCK, SysClock(env.clock_nanos))
+            .with_syscall(SYS_IS_REPLAY, SysIsReplay(env.replay.is_some()))
+            .with_syscall(SYS_SEGMENT_PO2, SysSegmentPo2(segment_po2))
+            .with_syscall(SYS_SEGMENT_REMAINING, SysSegmentRemaining)
+            .with_syscall(SYS_SPLIT, SysSplit)
             .with_syscall(SYS_LOG, posix_io.clone())
-            .with_syscall(SYS_PANIC, SysPanic)
-            .with_syscall(SYS_RANDOM, SysRandom)
+            .with_syscall(SYS_LOG_LEVEL, posix_io.clone())
+            .with_syscall(SYS_PANIC, SysPanic(env.panic_message.clone()))
+            .with_syscall(SYS_RANDOM, SysRandom(rng))
             .with_syscall(SYS_GETENV, SysGetenv(env.env_vars.clone()))
             .with_syscall(SYS_READ, posix_io.clone())
+            .with_syscall(SYS_READ_CHANNEL, posix_io.clone())
             .with_syscall(SYS_WRITE, posix_io)
             .with_syscall(SYS_VERIFY, sys_verify.clone())
             .with_syscall(SYS_VERIFY_INTEGRITY, sys_verify)
@@ -122,6 +158,23 @@ impl<'a> SyscallTable<'a> {
     }
 }
 
+// Keeps each message for the session, in order.
+pub(crate) struct SysCommit(Rc<RefCell<Vec<Vec<u8>>>>);
+impl Syscall for SysCommit {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let buf_ptr = ctx.load_register(REG_A3);
+        let buf_len = ctx.load_register(REG_A4);
+        let from_guest = ctx.load_region(buf_ptr, buf_len)?;
+        self.0.borrow_mut().push(from_guest);
+        Ok((0, 0))
+    }
+}
+
 pub(crate) struct SysCycleCount;
 impl Syscall for SysCycleCount {
     fn syscall(
@@ -130,11 +183,79 @@ impl Syscall for SysCycleCount {
         ctx: &mut dyn SyscallContext,
         _to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        Ok((ctx.get_cycle() as u32, 0))
+        // Split across a0 (low) and a1 (high), for guests that read the whole
+        // count with sys_cycle_count_u64.
+        let cycle = ctx.get_cycle() as u64;
+        Ok((cycle as u32, (cycle >> 32) as u32))
+    }
+}
+
+pub(crate) struct SysClock(pub u64);
+impl Syscall for SysClock {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((self.0 as u32, (self.0 >> 32) as u32))
+    }
+}
+
+// Replayed syscalls bypass the table, except for this one, which the executor
+// always dispatches live.
+pub(crate) struct SysIsReplay(pub bool);
+impl Syscall for SysIsReplay {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((self.0 as u32, 0))
+    }
+}
+
+pub(crate) struct SysSegmentPo2(pub u32);
+impl Syscall for SysSegmentPo2 {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((self.0, 0))
+    }
+}
+
+pub(crate) struct SysSegmentRemaining;
+impl Syscall for SysSegmentRemaining {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let remaining = ctx.get_segment_remaining() as u64;
+        Ok((remaining as u32, (remaining >> 32) as u32))
     }
 }
 
-pub(crate) struct SysGetenv(pub HashMap<String, String>);
+// The split itself is carried out by the executor, which recognizes the
+// syscall by name.
+pub(crate) struct SysSplit;
+impl Syscall for SysSplit {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        _ctx: &mut dyn SyscallContext,
+        _to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        Ok((0, 0))
+    }
+}
+
+pub(crate) struct SysGetenv(pub HashMap<String, Vec<u8>>);
 impl Syscall for SysGetenv {
     fn syscall(
         &mut self,
@@ -148,18 +269,19 @@ impl Syscall for SysGetenv {
         let msg = from_utf8(&from_guest)?;
 
         match self.0.get(msg) {
-            None => Ok((u32::MAX, 0)),
+            None => Ok((u32::MAX, getenv::NOT_FOUND)),
             Some(val) => {
-                let nbytes = min(to_guest.len() * WORD_SIZE, val.as_bytes().len());
+                let nbytes = min(to_guest.len() * WORD_SIZE, val.len());
                 let to_guest_u8s: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
-                to_guest_u8s[0..nbytes].clone_from_slice(&val.as_bytes()[0..nbytes]);
-                Ok((val.as_bytes().len() as u32, 0))
+                to_guest_u8s[0..nbytes].clone_from_slice(&val[0..nbytes]);
+                Ok((val.len() as u32, getenv::FOUND))
             }
         }
     }
 }
 
-pub(crate) struct SysPanic;
+// Keeps the message for the session; the guest halts after this syscall.
+pub(crate) struct SysPanic(Rc<RefCell<Option<String>>>);
 impl Syscall for SysPanic {
     fn syscall(
         &mut self,
@@ -170,12 +292,14 @@ impl Syscall for SysPanic {
         let buf_ptr = ctx.load_register(REG_A3);
         let buf_len = ctx.load_register(REG_A4);
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
-        let msg = from_utf8(&from_guest)?;
-        bail!("Guest panicked: {msg}");
+        let msg = String::from_utf8_lossy(&from_guest).into_owned();
+        tracing::debug!("Guest panicked: {msg}");
+        *self.0.borrow_mut() = Some(msg);
+        Ok((0, 0))
     }
 }
 
-pub(crate) struct SysRandom;
+pub(crate) struct SysRandom(Rc<RefCell<ChaCha20Rng>>);
 impl Syscall for SysRandom {
     fn syscall(
         &mut self,
@@ -184,9 +308,9 @@ impl Syscall for SysRandom {
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
         tracing::debug!("SYS_RANDOM: {}", to_guest.len());
-        let mut rand_buf = vec![0u8; to_guest.len() * WORD_SIZE];
-        getrandom::getrandom(rand_buf.as_mut_slice())?;
-        bytemuck::cast_slice_mut(to_guest).clone_from_slice(rand_buf.as_slice());
+        self.0
+            .borrow_mut()
+            .fill_bytes(bytemuck::cast_slice_mut(to_guest));
         Ok((0, 0))
     }
 }
@@ -391,7 +515,7 @@ impl Syscall for Args {
             to_guest_u8s[0..nbytes].clone_from_slice(&arg_val.as_bytes()[0..nbytes]);
             Ok((arg_val.as_bytes().len() as u32, 0))
         } else {
-            bail!("Unknown syscall {syscall}")
+            bail!(ExecutorError::UnknownSyscall(syscall.to_string()))
         }
     }
 }
@@ -463,13 +587,19 @@ impl<'a> Syscall for PosixIo<'a> {
     ) -> Result<(u32, u32)> {
         // TODO: Is there a way to use "match" here instead of if statements?
         if syscall == SYS_READ.as_str() {
-            self.sys_read(ctx, to_guest)
+            self.sys_read(ctx, to_guest, false)
+        } else if syscall == SYS_READ_CHANNEL.as_str() {
+            self.sys_read(ctx, to_guest, true)
         } else if syscall == SYS_WRITE.as_str() {
             self.sys_write(ctx)
         } else if syscall == SYS_LOG.as_str() {
             self.sys_log(ctx)
+        } else if syscall == SYS_LOG_LEVEL.as_str() {
+            let level = ctx.load_register(REG_A3);
+            tracing::debug!("sys_log_level({level})");
+            Ok((std::mem::replace(&mut self.log_level, level), 0))
         } else {
-            bail!("Unknown syscall {syscall}")
+            bail!(ExecutorError::UnknownSyscall(syscall.to_string()))
         }
     }
 }
@@ -486,16 +616,19 @@ impl<'a> Syscall for Rc<RefCell<PosixIo<'a>>> {
 }
 
 impl<'a> PosixIo<'a> {
+    // Reads from the fd in a3, or from the input channel in a3 if `channel`
+    // is set.
     fn sys_read(
         &mut self,
         ctx: &mut dyn SyscallContext,
         to_guest: &mut [u32],
+        channel: bool,
     ) -> Result<(u32, u32)> {
         let fd = ctx.load_register(REG_A3);
         let nbytes = ctx.load_register(REG_A4) as usize;
 
         tracing::trace!(
-            "sys_read(fd: {fd}, nbytes: {nbytes}, into: {} bytes)",
+            "sys_read(fd: {fd}, channel: {channel}, nbytes: {nbytes}, into: {} bytes)",
             to_guest.len() * WORD_SIZE
         );
 
@@ -504,10 +637,15 @@ impl<'a> PosixIo<'a> {
             "Word-aligned read buffer must be fully filled"
         );
 
-        let reader = self
-            .read_fds
-            .get_mut(&fd)
-            .ok_or(anyhow!("Bad read file descriptor {fd}"))?;
+        let reader = if channel {
+            self.read_channels
+                .get_mut(&fd)
+                .ok_or(anyhow!("Bad read channel {fd}"))?
+        } else {
+            self.read_fds
+                .get_mut(&fd)
+                .ok_or(anyhow!("Bad read file descriptor {fd}"))?
+        };
 
         // So that we don't have to deal with short reads, keep
         // reading until we get EOF or fill the buffer.
@@ -541,6 +679,13 @@ impl<'a> PosixIo<'a> {
         let mut to_guest_end: [u8; WORD_SIZE] = [0; WORD_SIZE];
         let nread_end = read_all(&mut to_guest_end[0..unaligned_end])?;
 
+        if !channel && fd == fileno::STDIN {
+            self.consumed_stdin
+                .extend_from_slice(&to_guest_u8[..nread_main]);
+            self.consumed_stdin
+                .extend_from_slice(&to_guest_end[..nread_end]);
+        }
+
         Ok((
             (nread_main + nread_end) as u32,
             u32::from_le_bytes(to_guest_end),
@@ -566,6 +711,16 @@ impl<'a> PosixIo<'a> {
     fn sys_log(&mut self, ctx: &mut dyn SyscallContext) -> Result<(u32, u32)> {
         let buf_ptr = ctx.load_register(REG_A3);
         let buf_len = ctx.load_register(REG_A4);
+        // Guests built before log levels were added leave a5 unset, so log
+        // anything that isn't a level as info.
+        let level = match ctx.load_register(REG_A5) {
+            level @ log_level::ERROR..=log_level::TRACE => level,
+            _ => log_level::INFO,
+        };
+        if level > self.log_level {
+            tracing::trace!("sys_log({buf_len} bytes) dropped at level {level}");
+            return Ok((0, 0));
+        }
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
         // write to stdout, but be sure to point it to where the file descriptor is pointing
         let writer = self
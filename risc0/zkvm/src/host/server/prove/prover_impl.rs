@@ -64,6 +64,7 @@ where
             session.journal.as_ref().map(|x| hex::encode(x)),
             session.segments.len()
         );
+        session.ensure_provable()?;
         let mut segments = Vec::new();
         for segment_ref in session.segments.iter() {
             let segment = segment_ref.resolve()?;
@@ -16,20 +16,32 @@
 //! execution traces between the execution phase and the proving phase.
 
 use std::{
-    collections::BTreeSet,
-    fs,
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write as _},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use anyhow::{ensure, Result};
-use risc0_binfmt::{MemoryImage, SystemState};
-use risc0_circuit_rv32im::prove::segment::Segment as CircuitSegment;
+use anyhow::{bail, ensure, Context as _, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use rayon::prelude::*;
+use risc0_binfmt::{tagged_struct, MemoryImage, SystemState};
+use risc0_circuit_rv32im::prove::{
+    emu::{
+        exec::{PagingStats, Unprovable},
+        rv32im::InsnKind,
+    },
+    segment::Segment as CircuitSegment,
+};
+use risc0_zkvm_platform::syscall::reg_abi::REG_MAX;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     host::{client::env::SegmentPath, prove_info::SessionStats},
-    sha::Digest,
-    Assumption, Assumptions, ExitCode, Journal, Output, ReceiptClaim,
+    sha::{self, Digest, Digestible, Sha256},
+    Assumption, Assumptions, BlockProfile, ExecutorEnv, ExecutorImpl, ExitCode, Journal,
+    MaybePruned, Output, ReceiptClaim,
 };
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -47,11 +59,15 @@ pub struct PageFaults {
 pub struct Session {
     /// The constituent [Segment]s of the Session. The final [Segment] will have
     /// an [ExitCode] of [Halted](ExitCode::Halted), [Paused](ExitCode::Paused),
-    /// or [SessionLimit](ExitCode::SessionLimit), and all other [Segment]s (if
+    /// [SessionLimit](ExitCode::SessionLimit), [OutOfFuel](ExitCode::OutOfFuel),
+    /// or [FuelExhausted](ExitCode::FuelExhausted), and all other [Segment]s (if
     /// any) will have [ExitCode::SystemSplit].
     pub segments: Vec<Box<dyn SegmentRef>>,
 
     /// The data publicly committed by the guest program.
+    ///
+    /// This is `None` if journal capture was disabled with
+    /// [ExecutorEnvBuilder::capture_journal](crate::ExecutorEnvBuilder::capture_journal).
     pub journal: Option<Journal>,
 
     /// The [ExitCode] of the session.
@@ -79,6 +95,153 @@ pub struct Session {
 
     /// The system state of the final [MemoryImage] at the end of execution.
     pub post_state: SystemState,
+
+    /// The data written by the guest program to stderr.
+    ///
+    /// This is captured in addition to being passed through to the stderr
+    /// writer configured in the [ExecutorEnv].
+    pub stderr: Vec<u8>,
+
+    /// The message the guest panicked with, if it did.
+    ///
+    /// A panicking guest reports its message and then halts with
+    /// [PANIC_EXIT_CODE](risc0_zkvm_platform::syscall::halt::PANIC_EXIT_CODE).
+    pub panic_message: Option<String>,
+
+    /// The messages sent by the guest with
+    /// [commit_frame](crate::guest::env::commit_frame), in order.
+    ///
+    /// Unlike the [journal](Session::journal), each message is kept separate.
+    /// These are not part of the receipt.
+    pub commits: Vec<Vec<u8>>,
+
+    /// The guest registers `x0`-`x31` at the end of the session, e.g. the exit
+    /// code and output pointer the guest passed when it halted or paused.
+    pub final_registers: [u32; REG_MAX],
+
+    pub(crate) consumed_input: Vec<u8>,
+
+    pub(crate) opcode_counts: BTreeMap<InsnKind, u64>,
+
+    pub(crate) covered_pcs: BTreeSet<u32>,
+
+    pub(crate) self_modified_pcs: Vec<u32>,
+
+    pub(crate) memory_breakpoint: Option<(u32, u32)>,
+
+    pub(crate) output_digest: Option<Digest>,
+
+    // The digest of the journal, when it was not captured.
+    pub(crate) journal_digest: Option<Digest>,
+
+    pub(crate) hot_blocks: Vec<BlockProfile>,
+
+    pub(crate) line_profile: Vec<(String, u32, u64)>,
+
+    pub(crate) paging_stats: PagingStats,
+
+    pub(crate) total_instructions: u64,
+
+    pub(crate) syscalls: Vec<SyscallRecord>,
+
+    pub(crate) syscall_summary: BTreeMap<String, usize>,
+
+    pub(crate) segment_durations: Vec<Duration>,
+
+    pub(crate) segment_po2s: Vec<usize>,
+
+    pub(crate) resumed_from_pause: bool,
+
+    pub(crate) executor_only_ecalls: BTreeSet<u32>,
+
+    pub(crate) unprovable: BTreeSet<Unprovable>,
+}
+
+// The contents of a [Session] written by [Session::save], with every segment
+// resolved.
+#[derive(Serialize, Deserialize)]
+struct SessionArchive {
+    segments: Vec<Segment>,
+    journal: Option<Journal>,
+    journal_digest: Option<Digest>,
+    exit_code: ExitCode,
+    post_image: MemoryImage,
+    assumptions: Vec<Assumption>,
+    user_cycles: u64,
+    total_cycles: u64,
+    pre_state: SystemState,
+    post_state: SystemState,
+    stderr: Vec<u8>,
+    panic_message: Option<String>,
+    commits: Vec<Vec<u8>>,
+    final_registers: [u32; REG_MAX],
+    executor_only_ecalls: BTreeSet<u32>,
+    unprovable: BTreeSet<Unprovable>,
+}
+
+/// A manifest binding together the identity, input, and output of a
+/// [Session].
+///
+/// The manifest carries a digest over all of its other fields, so that a
+/// consumer can detect whether any of them were altered in transit. Note that
+/// the manifest is not a proof: it attests to nothing beyond its own
+/// integrity.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionManifest {
+    /// The image ID of the guest that was executed.
+    pub image_id: Digest,
+
+    /// The SHA-256 digest of the input consumed by the guest.
+    pub input_digest: Digest,
+
+    /// The data publicly committed by the guest program.
+    pub journal: Vec<u8>,
+
+    /// The [ExitCode] of the session.
+    pub exit_code: ExitCode,
+
+    /// The number of segments in the session.
+    pub segments: u32,
+
+    /// The total number of cycles that a prover will experience.
+    pub total_cycles: u64,
+
+    /// The digest over all of the other fields of this manifest.
+    pub digest: Digest,
+}
+
+impl SessionManifest {
+    /// Compute the digest over the fields of this manifest, excluding
+    /// [SessionManifest::digest] itself.
+    pub fn compute_digest(&self) -> Digest {
+        let (sys_exit, user_exit) = self.exit_code.into_pair();
+        tagged_struct::<sha::Impl>(
+            "risc0.SessionManifest",
+            &[
+                self.image_id,
+                self.input_digest,
+                *sha::Impl::hash_bytes(&self.journal),
+            ],
+            &[
+                sys_exit,
+                user_exit,
+                self.segments,
+                self.total_cycles as u32,
+                (self.total_cycles >> 32) as u32,
+            ],
+        )
+    }
+
+    /// Check that the digest of this manifest matches its contents.
+    pub fn verify(&self) -> Result<()> {
+        let expected = self.compute_digest();
+        ensure!(
+            self.digest == expected,
+            "session manifest digest mismatch: {} != {expected}",
+            self.digest
+        );
+        Ok(())
+    }
 }
 
 /// The execution trace of a portion of a program.
@@ -105,6 +268,69 @@ impl Segment {
     pub fn po2(&self) -> usize {
         self.inner.po2
     }
+
+    /// Returns a breakdown of how the cycles of this [Segment] were spent.
+    pub fn stats(&self) -> SegmentStats {
+        SegmentStats {
+            init_cycles: self.inner.loader_cycles.init as u64,
+            page_read_cycles: self.inner.page_read_cycles as u64,
+            page_write_cycles: self.inner.page_write_cycles as u64,
+            body_cycles: self.inner.insn_cycles as u64,
+            fini_cycles: (self.inner.loader_cycles.reserved_cycles()
+                - self.inner.loader_cycles.init) as u64,
+        }
+    }
+}
+
+/// A breakdown of the cycles of a [Segment].
+///
+/// The [total](Self::total_cycles) is padded up to the next power of two to
+/// give the size of the [Segment], see [Segment::po2].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentStats {
+    /// Cycles spent initializing the circuit.
+    pub init_cycles: u64,
+
+    /// Cycles spent reading in the pages accessed by the [Segment].
+    pub page_read_cycles: u64,
+
+    /// Cycles spent writing out the pages modified by the [Segment].
+    pub page_write_cycles: u64,
+
+    /// Cycles spent executing instructions.
+    pub body_cycles: u64,
+
+    /// Cycles reserved for halting and finalizing the circuit.
+    pub fini_cycles: u64,
+}
+
+impl SegmentStats {
+    /// Returns the sum of all cycles, before padding.
+    pub fn total_cycles(&self) -> u64 {
+        self.init_cycles
+            + self.page_read_cycles
+            + self.page_write_cycles
+            + self.body_cycles
+            + self.fini_cycles
+    }
+}
+
+/// A software syscall made by the guest, along with the response of the host.
+///
+/// See [ExecutorEnvBuilder::record_syscalls] and [ExecutorEnvBuilder::replay].
+///
+/// [ExecutorEnvBuilder::record_syscalls]: crate::ExecutorEnvBuilder::record_syscalls
+/// [ExecutorEnvBuilder::replay]: crate::ExecutorEnvBuilder::replay
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallRecord {
+    /// The name of the syscall.
+    pub name: String,
+
+    /// The words the host returned into guest memory.
+    pub to_guest: Vec<u32>,
+
+    /// The values the host returned in registers `a0` and `a1`.
+    pub regs: (u32, u32),
 }
 
 /// A reference to a [Segment].
@@ -152,9 +378,285 @@ impl Session {
             total_cycles,
             pre_state,
             post_state,
+            stderr: Vec::new(),
+            panic_message: None,
+            commits: Vec::new(),
+            final_registers: [0; REG_MAX],
+            consumed_input: Vec::new(),
+            opcode_counts: BTreeMap::new(),
+            covered_pcs: BTreeSet::new(),
+            self_modified_pcs: Vec::new(),
+            memory_breakpoint: None,
+            output_digest: None,
+            journal_digest: None,
+            hot_blocks: Vec::new(),
+            line_profile: Vec::new(),
+            paging_stats: PagingStats::default(),
+            total_instructions: 0,
+            syscalls: Vec::new(),
+            syscall_summary: BTreeMap::new(),
+            segment_durations: Vec::new(),
+            segment_po2s: Vec::new(),
+            resumed_from_pause: false,
+            executor_only_ecalls: BTreeSet::new(),
+            unprovable: BTreeSet::new(),
         }
     }
 
+    /// Write this [Session] to a single archive at `path`, e.g. to prove it
+    /// later or on another machine.
+    ///
+    /// Every segment is resolved, so the archive does not depend on the files
+    /// of a [FileSegmentRef]. The journal, exit code, states, assumptions, and
+    /// guest output are saved along with the segments; the
+    /// [hooks](Session::hooks) and the statistics gathered during execution
+    /// are not.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.resolve())
+            .collect::<Result<Vec<_>>>()?;
+        let archive = SessionArchive {
+            segments,
+            journal: self.journal.clone(),
+            journal_digest: self.journal_digest,
+            exit_code: self.exit_code,
+            post_image: self.post_image.clone(),
+            assumptions: self.assumptions.clone(),
+            user_cycles: self.user_cycles,
+            total_cycles: self.total_cycles,
+            pre_state: self.pre_state.clone(),
+            post_state: self.post_state.clone(),
+            stderr: self.stderr.clone(),
+            panic_message: self.panic_message.clone(),
+            commits: self.commits.clone(),
+            final_registers: self.final_registers,
+            executor_only_ecalls: self.executor_only_ecalls.clone(),
+            unprovable: self.unprovable.clone(),
+        };
+        let file = File::create(path.as_ref())
+            .with_context(|| format!("failed to create {}", path.as_ref().display()))?;
+        let mut out = BufWriter::new(file);
+        bincode::serialize_into(&mut out, &archive)?;
+        Ok(out.flush()?)
+    }
+
+    /// Read back a [Session] written by [Session::save].
+    ///
+    /// The segments are held in memory, as [SimpleSegmentRef]s.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("failed to open {}", path.as_ref().display()))?;
+        let archive: SessionArchive = bincode::deserialize_from(BufReader::new(file))?;
+        let segments = archive
+            .segments
+            .into_iter()
+            .map(|segment| Box::new(SimpleSegmentRef::new(segment)) as Box<dyn SegmentRef>)
+            .collect();
+        Ok(Self {
+            stderr: archive.stderr,
+            panic_message: archive.panic_message,
+            commits: archive.commits,
+            final_registers: archive.final_registers,
+            journal_digest: archive.journal_digest,
+            executor_only_ecalls: archive.executor_only_ecalls,
+            unprovable: archive.unprovable,
+            ..Self::new(
+                segments,
+                archive.journal.map(|journal| journal.bytes),
+                archive.exit_code,
+                archive.post_image,
+                archive.assumptions,
+                archive.user_cycles,
+                archive.total_cycles,
+                archive.pre_state,
+                archive.post_state,
+            )
+        })
+    }
+
+    /// Returns the bytes the guest actually read from stdin during execution.
+    ///
+    /// This may be a prefix of the input supplied through the [ExecutorEnv],
+    /// e.g. if the guest halted before reading all of it.
+    ///
+    /// [ExecutorEnv]: crate::ExecutorEnv
+    pub fn consumed_input(&self) -> &[u8] {
+        &self.consumed_input
+    }
+
+    /// Returns the number of instructions executed over the whole session,
+    /// by [InsnKind].
+    ///
+    /// This is useful to tell whether a guest is dominated by e.g. loads,
+    /// branches, or ecalls.
+    pub fn opcode_counts(&self) -> &BTreeMap<InsnKind, u64> {
+        &self.opcode_counts
+    }
+
+    /// Returns the address of every instruction executed at least once in
+    /// this session, if coverage was tracked with
+    /// [ExecutorEnvBuilder::track_coverage](crate::ExecutorEnvBuilder::track_coverage),
+    /// and an empty set otherwise.
+    pub fn covered_pcs(&self) -> &BTreeSet<u32> {
+        &self.covered_pcs
+    }
+
+    /// Returns the address of every instruction that the guest executed after
+    /// writing to it in this session, in the order they were first executed,
+    /// if detection was enabled with
+    /// [ExecutorEnvBuilder::detect_self_modifying_code](crate::ExecutorEnvBuilder::detect_self_modifying_code).
+    pub fn self_modified_pcs(&self) -> &[u32] {
+        &self.self_modified_pcs
+    }
+
+    /// Returns true if this session continues the execution of an earlier
+    /// session that ended with [ExitCode::Paused], rather than starting from
+    /// the initial image of the guest.
+    ///
+    /// The first segment of a resumed session starts from the post image of
+    /// the paused session.
+    pub fn resumed_from_pause(&self) -> bool {
+        self.resumed_from_pause
+    }
+
+    /// Returns the executor-only ecalls made by the guest, see
+    /// [ecall](risc0_zkvm_platform::syscall::ecall).
+    ///
+    /// A session that made any of them cannot be proven: [Session::get_claim]
+    /// and the prover reject it.
+    pub fn executor_only_ecalls(&self) -> &BTreeSet<u32> {
+        &self.executor_only_ecalls
+    }
+
+    /// Returns the reasons, other than [executor-only
+    /// ecalls](Session::executor_only_ecalls), that this session cannot be
+    /// proven, e.g. because the host paused it.
+    ///
+    /// [Session::get_claim] and the prover reject a session with any of them.
+    pub fn unprovable(&self) -> &BTreeSet<Unprovable> {
+        &self.unprovable
+    }
+
+    // Fail with an error naming the executor-only ecalls made by the guest, or
+    // any other reason the session cannot be proven, rather than failing deep
+    // inside the circuit.
+    pub(crate) fn ensure_provable(&self) -> Result<()> {
+        ensure!(
+            self.executor_only_ecalls.is_empty(),
+            "session cannot be proven: the guest made executor-only ecalls {:?}",
+            self.executor_only_ecalls
+        );
+        ensure!(
+            !matches!(
+                self.exit_code,
+                ExitCode::OutOfFuel | ExitCode::FuelExhausted
+            ),
+            "session cannot be proven: the guest ran out of fuel"
+        );
+        ensure!(
+            self.unprovable.is_empty(),
+            "session cannot be proven: {}",
+            self.unprovable
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(())
+    }
+
+    /// Returns the `(addr, value)` of the memory breakpoint that paused this
+    /// session, if any.
+    ///
+    /// See [ExecutorEnvBuilder::pause_on_memory_value].
+    ///
+    /// [ExecutorEnvBuilder::pause_on_memory_value]: crate::ExecutorEnvBuilder::pause_on_memory_value
+    pub fn memory_breakpoint(&self) -> Option<(u32, u32)> {
+        self.memory_breakpoint
+    }
+
+    /// Returns the output digest the guest passed when it last halted or
+    /// paused, if any.
+    ///
+    /// This is the digest of the [Output] committed to by the guest.
+    pub fn output_digest(&self) -> Option<Digest> {
+        self.output_digest
+    }
+
+    /// Returns the cycles spent in each basic block of the guest, hottest
+    /// first.
+    ///
+    /// This is empty unless [ExecutorEnvBuilder::profile_basic_blocks] was
+    /// enabled.
+    ///
+    /// [ExecutorEnvBuilder::profile_basic_blocks]: crate::ExecutorEnvBuilder::profile_basic_blocks
+    pub fn hot_blocks(&self) -> Vec<BlockProfile> {
+        self.hot_blocks.clone()
+    }
+
+    /// Returns the cycles spent on each source line of the guest, as `(file,
+    /// line, cycles)` sorted from the hottest line down.
+    ///
+    /// This is empty unless [ExecutorEnvBuilder::profile_lines] was enabled.
+    /// Instructions without line info, e.g. those of a guest built without
+    /// debug info, are left out.
+    ///
+    /// [ExecutorEnvBuilder::profile_lines]: crate::ExecutorEnvBuilder::profile_lines
+    pub fn line_profile(&self) -> &[(String, u32, u64)] {
+        &self.line_profile
+    }
+
+    /// Returns the number of pages read in and written back, and the cycles
+    /// spent doing so, summed over all segments of the session.
+    ///
+    /// Paging starts afresh in every segment, so splitting a session into more
+    /// segments tends to increase its paging overhead.
+    pub fn paging_stats(&self) -> PagingStats {
+        self.paging_stats
+    }
+
+    /// Returns the number of instructions retired over all segments of the
+    /// session.
+    ///
+    /// Unlike [user_cycles](Session::user_cycles), this does not depend on the
+    /// cycle cost of each instruction.
+    pub fn total_instructions(&self) -> u64 {
+        self.total_instructions
+    }
+
+    /// Returns the software syscalls made by the guest, in order.
+    ///
+    /// This is only recorded if [ExecutorEnvBuilder::record_syscalls] is
+    /// enabled, and can be passed to [ExecutorEnvBuilder::replay] to run the
+    /// session again without the original host.
+    ///
+    /// [ExecutorEnvBuilder::record_syscalls]: crate::ExecutorEnvBuilder::record_syscalls
+    /// [ExecutorEnvBuilder::replay]: crate::ExecutorEnvBuilder::replay
+    pub fn syscalls(&self) -> &[SyscallRecord] {
+        &self.syscalls
+    }
+
+    /// Returns how many times the guest invoked each software syscall, by
+    /// name, over all segments.
+    ///
+    /// Unlike [syscalls](Session::syscalls), this is always collected. It shows
+    /// which host services the guest relied on.
+    pub fn syscall_summary(&self) -> BTreeMap<String, usize> {
+        self.syscall_summary.clone()
+    }
+
+    /// Returns how long the host took to execute each segment, in the same
+    /// order as [segments](Session::segments).
+    ///
+    /// This is wall clock time, so unlike the cycle counts it depends on the
+    /// speed of the host, e.g. on paging and on the latency of syscalls. It
+    /// does not include the time spent in the segment callback.
+    pub fn segment_durations(&self) -> &[Duration] {
+        &self.segment_durations
+    }
+
     /// Add a hook to be called during the proving phase.
     pub fn add_hook<E: SessionEvents + 'static>(&mut self, hook: E) {
         self.hooks.push(Box::new(hook));
@@ -163,16 +665,25 @@ impl Session {
     /// Calculate for the [ReceiptClaim] associated with this [Session]. The
     /// [ReceiptClaim] is the claim that will be proven if this [Session]
     /// is passed to the [crate::Prover].
+    ///
+    /// This fails if the session made any [executor-only
+    /// ecalls](Session::executor_only_ecalls).
     pub fn get_claim(&self) -> Result<ReceiptClaim> {
+        self.ensure_provable()?;
         // Construct the Output struct for the session, checking internal consistency.
         // NOTE: The Session output is distinct from the final Segment output because in the
         // Session output any proven assumptions are not included.
         let output = if self.exit_code.expects_output() {
-            self.journal
-                .as_ref()
+            // A journal that was not captured still binds the output by its digest.
+            let journal = match (&self.journal, self.journal_digest) {
+                (Some(journal), _) => Some(journal.bytes.clone().into()),
+                (None, Some(digest)) => Some(MaybePruned::Pruned(digest)),
+                (None, None) => None,
+            };
+            journal
                 .map(|journal| -> Result<_> {
                     Ok(Output {
-                        journal: journal.bytes.clone().into(),
+                        journal,
                         assumptions: Assumptions(
                             self.assumptions
                                 .iter()
@@ -231,6 +742,143 @@ impl Session {
             user_cycles: self.user_cycles,
         }
     }
+
+    /// Check that the [Segment]s of this [Session] form an unbroken chain.
+    ///
+    /// Each [Segment] is resolved in order, and its pre-state is compared
+    /// against the post-state of the [Segment] before it (or against the
+    /// pre-state of the [Session] for the first one). This is useful for
+    /// debugging the split logic or a custom [SegmentRef] implementation; an
+    /// error identifies the index at which the chain is broken.
+    pub fn verify_continuity(&self) -> Result<()> {
+        let mut expected = self.pre_state.digest();
+        for (idx, segment) in self.segments.iter().enumerate() {
+            let segment = segment.resolve()?;
+            let pre_state = segment.inner.pre_state.digest();
+            if pre_state != expected {
+                bail!(
+                    "continuity broken at segment {idx}: pre-state {pre_state} does not match expected {expected}"
+                );
+            }
+            expected = segment.inner.post_state.digest();
+        }
+        Ok(())
+    }
+
+    /// Re-execute every [Segment] of this [Session] in parallel and check that
+    /// each one reaches the post-state it recorded.
+    ///
+    /// Each [Segment] is re-executed independently from its partial image,
+    /// replaying the syscalls it recorded, in an [ExecutorEnv] produced by
+    /// `env_factory`. The environments must be configured like the one the
+    /// [Session] was executed with, e.g. with the same segment limit, for the
+    /// results to match. All segments are resolved up front. An error lists
+    /// every segment that did not match.
+    ///
+    /// [ExecutorEnv]: crate::ExecutorEnv
+    pub fn validate_parallel<'a, F>(&self, env_factory: F) -> Result<()>
+    where
+        F: Fn() -> ExecutorEnv<'a> + Sync,
+    {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.resolve())
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut errors: Vec<(u32, anyhow::Error)> = segments
+            .par_iter()
+            .filter_map(|segment| {
+                let check = || -> Result<()> {
+                    let inner = &segment.inner;
+                    let exec = ExecutorImpl::new(env_factory(), inner.partial_image.clone())?;
+                    let post_state = exec.run_segment(inner)?.digest();
+                    let expected = inner.post_state.digest();
+                    ensure!(
+                        post_state == expected,
+                        "post-state {post_state} does not match expected {expected}"
+                    );
+                    Ok(())
+                };
+                check().err().map(|err| (segment.index, err))
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            errors.sort_by_key(|(index, _)| *index);
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|(index, err)| format!("segment {index}: {err}"))
+                .collect();
+            bail!("validation failed:\n{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// Assemble a [SessionManifest] for this [Session], given the image ID of
+    /// the guest that was executed.
+    pub fn manifest(&self, image_id: Digest) -> SessionManifest {
+        let mut manifest = SessionManifest {
+            image_id,
+            input_digest: *sha::Impl::hash_bytes(&self.consumed_input),
+            journal: self
+                .journal
+                .as_ref()
+                .map(|journal| journal.bytes.clone())
+                .unwrap_or_default(),
+            exit_code: self.exit_code,
+            segments: self.segments.len() as u32,
+            total_cycles: self.total_cycles,
+            digest: Digest::ZERO,
+        };
+        manifest.digest = manifest.compute_digest();
+        manifest
+    }
+
+    /// Returns a human-readable summary of this [Session].
+    ///
+    /// The summary is a multi-line block intended to be printed at the end of
+    /// a run, e.g. by a CLI. Figures that were not collected during execution
+    /// are omitted, e.g. the proving size, paging, and syscalls of a session
+    /// read back with [Session::load].
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        lines.push(format!("exit code: {:?}", self.exit_code));
+        lines.push(format!("segments: {}", self.segments.len()));
+        lines.push(format!("total cycles: {}", self.total_cycles));
+        lines.push(format!("user cycles: {}", self.user_cycles));
+        if self.total_cycles != 0 {
+            let cycle_efficiency = self.user_cycles as f64 / self.total_cycles as f64 * 100.0;
+            lines.push(format!("cycle efficiency: {}%", cycle_efficiency as u32));
+        }
+        if let Some(max_po2) = self.segment_po2s.iter().max() {
+            lines.push(format!(
+                "proving size: {} segments, largest 2^{max_po2} cycles",
+                self.segment_po2s.len()
+            ));
+        }
+        let paging_cycles =
+            self.paging_stats.page_read_cycles + self.paging_stats.page_write_cycles;
+        if paging_cycles != 0 && self.total_cycles != 0 {
+            let paging = paging_cycles as f64 / self.total_cycles as f64 * 100.0;
+            lines.push(format!("paging: {}% of cycles", paging as u32));
+        }
+        if !self.syscall_summary.is_empty() {
+            let mut syscalls: Vec<_> = self.syscall_summary.iter().collect();
+            syscalls.sort_by(|a, b| b.1.cmp(a.1));
+            let top: Vec<_> = syscalls
+                .iter()
+                .take(3)
+                .map(|(name, count)| format!("{name} ({count})"))
+                .collect();
+            lines.push(format!("top syscalls: {}", top.join(", ")));
+        }
+        if let Some(journal) = &self.journal {
+            lines.push(format!("journal: {} bytes", journal.bytes.len()));
+        }
+        lines.push(format!("assumptions: {}", self.assumptions.len()));
+        lines.join("\n")
+    }
 }
 
 /// Implementation of a [SegmentRef] that does not save the segment.
@@ -270,6 +918,52 @@ impl SimpleSegmentRef {
     }
 }
 
+/// An implementation of a [SegmentRef] that keeps the segment in memory,
+/// compressed.
+///
+/// A serialized [Segment] is large because it embeds the full pre-image of
+/// its memory, most of which compresses well. The [Segment] is serialized with
+/// bincode, compressed with deflate, and only decompressed on
+/// [SegmentRef::resolve].
+///
+/// ```no_run
+/// use risc0_zkvm::{CompressedSegmentRef, ExecutorEnv, ExecutorImpl};
+/// # use risc0_zkvm_methods::MULTI_TEST_ELF;
+///
+/// let env = ExecutorEnv::builder().build().unwrap();
+/// let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+///     .unwrap()
+///     .run_with_callback(|segment| Ok(Box::new(CompressedSegmentRef::new(&segment)?)))
+///     .unwrap();
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CompressedSegmentRef {
+    compressed: Vec<u8>,
+}
+
+impl SegmentRef for CompressedSegmentRef {
+    fn resolve(&self) -> Result<Segment> {
+        let decoder = DeflateDecoder::new(self.compressed.as_slice());
+        Ok(bincode::deserialize_from(decoder)?)
+    }
+}
+
+impl CompressedSegmentRef {
+    /// Construct a [CompressedSegmentRef] that holds `segment` compressed.
+    pub fn new(segment: &Segment) -> Result<Self> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        bincode::serialize_into(&mut encoder, segment)?;
+        Ok(Self {
+            compressed: encoder.finish()?,
+        })
+    }
+
+    /// The size of the compressed segment, in bytes.
+    pub fn compressed_len(&self) -> usize {
+        self.compressed.len()
+    }
+}
+
 /// A basic implementation of a [SegmentRef] that saves the segment to a file
 ///
 /// The [Segment] is stored in a user-specified file in this implementation,
@@ -312,4 +1006,35 @@ impl FileSegmentRef {
             _dir: dir.clone(),
         })
     }
+
+    /// Construct a [FileSegmentRef] that stores `segment` in a file in `dir`.
+    ///
+    /// The file is named after the index of the segment and is read back
+    /// lazily on [SegmentRef::resolve], so that only the segment currently
+    /// being worked on needs to be kept in memory. This is meant to be used
+    /// as the callback of [ExecutorImpl::run_with_callback]:
+    ///
+    /// ```no_run
+    /// use risc0_zkvm::{ExecutorEnv, ExecutorImpl, FileSegmentRef};
+    /// # use risc0_zkvm_methods::MULTI_TEST_ELF;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let env = ExecutorEnv::builder().build().unwrap();
+    /// let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+    ///     .unwrap()
+    ///     .run_with_callback(|segment| {
+    ///         Ok(Box::new(FileSegmentRef::in_dir(&segment, dir.path())?))
+    ///     })
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [ExecutorImpl::run_with_callback]: crate::ExecutorImpl::run_with_callback
+    pub fn in_dir<P: AsRef<Path>>(segment: &Segment, dir: P) -> Result<Self> {
+        Self::new(segment, &SegmentPath::Path(dir.as_ref().to_path_buf()))
+    }
+
+    /// The path of the file that holds the segment.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 }
@@ -99,14 +99,30 @@ pub use {
         client::prove::local::LocalProver,
         recursion::RECURSION_PO2,
         server::{
-            exec::executor::ExecutorImpl,
+            exec::{
+                basic_blocks::BlockProfile,
+                binary_trace::{BinaryTraceReader, BinaryTraceWriter},
+                call_stack::StackFrame,
+                chrome_trace::ChromeTraceWriter,
+                executor::{ExecutorCheckpoint, ExecutorImpl, PreparedProgram},
+            },
             prove::{get_prover_server, HalPair, ProverServer},
             session::{
-                FileSegmentRef, Segment, SegmentRef, Session, SessionEvents, SimpleSegmentRef,
+                CompressedSegmentRef, FileSegmentRef, Segment, SegmentRef, SegmentStats, Session,
+                SessionEvents, SessionManifest, SimpleSegmentRef, SyscallRecord,
+            },
+        },
+    },
+    risc0_circuit_rv32im::prove::{
+        emu::{
+            exec::{
+                CycleCostModel, EcallInfo, ExecutorError, Fault, FaultCause, LoaderCycles,
+                PagingStats, ProgressInfo, SessionLimitBehavior, SplitInfo, Unprovable,
             },
+            rv32im::{InsnKind, MajorType},
         },
+        engine::loader::Loader,
     },
-    risc0_circuit_rv32im::prove::engine::loader::Loader,
     risc0_groth16::{
         docker::stark_to_snark, to_json as seal_to_json, ProofJson as Groth16ProofJson,
     },